@@ -10,6 +10,18 @@ function setFormWarning(description, focus_element) {
 
 let method;
 let target;
+let original_created = "";
+
+// Turns a datetime-local value into an RFC 3339 timestamp in the local timezone.
+function localToRfc3339(value) {
+    const date = new Date(value);
+    const offset = -date.getTimezoneOffset();
+    const sign = offset < 0 ? "-" : "+";
+    const hours = String(Math.floor(Math.abs(offset) / 60)).padStart(2, "0");
+    const minutes = String(Math.abs(offset) % 60).padStart(2, "0");
+    const seconds = value.length > 16 ? "" : ":00";
+    return value + seconds + sign + hours + ":" + minutes;
+}
 
 function submitJson(form) {
     const title_element = document.getElementById("title");
@@ -25,6 +37,8 @@ function submitJson(form) {
         if(xhr.readyState == 4) {
             if(xhr.status > 99 && xhr.status < 300) {
                 window.location.href = "/timeline.html";
+            } else if(xhr.status == 400) {
+                setFormWarning(xhr.responseText, submit_element);
             } else {
                 setFormWarning(
                     "Something has gone wrong! " +
@@ -37,11 +51,24 @@ function submitJson(form) {
     const data = {};
     data.title = title_element.value;
     data.body = body_element.value;
+    data.tags = document.getElementById("tags").value
+        .split(",")
+        .map((tag) => tag.trim())
+        .filter((tag) => tag.length > 0);
 
+    const timezone = Intl.DateTimeFormat().resolvedOptions().timeZone;
     if(method == "POST") {
+        data.timezone = timezone;
         data.timezone_offset = new Date().getTimezoneOffset();
     }
 
+    // The date is only sent when it has been changed, to keep the original timezone otherwise.
+    const created = document.getElementById("created").value;
+    if(created != "" && created != original_created) {
+        data.created = localToRfc3339(created);
+        data.timezone = timezone;
+    }
+
     xhr.send(JSON.stringify(data));
 }
 
@@ -59,7 +86,7 @@ const user_uuid = get_cookie_value("user_uuid");
 const entry_uuid = url_parameters.get("entry-uuid");
 
 function deleteEntry() {
-    if(confirm("Do you really want to delete this entry?")) {
+    if(confirm("Move this entry to the trash?")) {
         const xhr = new XMLHttpRequest();
         xhr.open("DELETE", "/api/users/" + user_uuid + "/entries/" + entry_uuid)
         xhr.onreadystatechange = function() {
@@ -96,6 +123,10 @@ if(entry_uuid != null) {
             const entry = JSON.parse(xhr.response);
             document.getElementById("title").value = entry.title;
             document.getElementById("body").value = entry.body;
+            document.getElementById("tags").value = entry.tags.join(", ");
+            // Date and time of the entry as written, in the timezone it was written in.
+            original_created = entry.created.slice(0, 19);
+            document.getElementById("created").value = original_created;
         }
     };
 
@@ -8,9 +8,121 @@ function setFormWarning(description, focus_element) {
     focus_element.focus();
 }
 
+// Given by the server when a code is required after the password.
+let challenge = null;
+
+function showTotpForm() {
+    document.getElementById("login-form").hidden = true;
+    document.getElementById("totp-form").hidden = false;
+    document.getElementById("warning-container").hidden = true;
+    document.getElementById("code").focus();
+}
+
+function submitTotp() {
+    const code_input_element = document.getElementById("code");
+    const submit_input_element = document.getElementById("totp-submit");
+
+    let xhr = new XMLHttpRequest();
+    xhr.open("POST", "/api/login/totp");
+    xhr.setRequestHeader("Content-Type", "application/json");
+
+    xhr.onreadystatechange = function() {
+        if(xhr.readyState == 4) {
+            if(xhr.status > 99 && xhr.status < 300) {
+                window.location.href = "/timeline.html";
+            } else if(xhr.status == 401 || xhr.status == 429) {
+                setFormWarning(xhr.responseText, code_input_element);
+            } else {
+                setFormWarning(
+                    "Something has gone wrong! " +
+                    "Please contact the server admin if the problem persists.",
+                    submit_input_element);
+            }
+        }
+    };
+
+    if(code_input_element.value.length == 0) {
+        setFormWarning("Code is required!", code_input_element);
+        return;
+    }
+
+    xhr.send(JSON.stringify({ challenge: challenge, code: code_input_element.value }));
+}
+
+function finishPasskeyLogin(ceremony, credential) {
+    const submit_input_element = document.getElementById("passkey-submit");
+
+    let xhr = new XMLHttpRequest();
+    xhr.open("POST", "/api/login/passkey");
+    xhr.setRequestHeader("Content-Type", "application/json");
+
+    xhr.onreadystatechange = function() {
+        if(xhr.readyState == 4) {
+            if(xhr.status > 99 && xhr.status < 300) {
+                window.location.href = "/timeline.html";
+            } else if(xhr.status == 401) {
+                setFormWarning(xhr.responseText, submit_input_element);
+            } else {
+                setFormWarning(
+                    "Something has gone wrong! " +
+                    "Please contact the server admin if the problem persists.",
+                    submit_input_element);
+            }
+        }
+    };
+
+    xhr.send(JSON.stringify({
+        ceremony: ceremony,
+        credential: credential,
+        remember: document.getElementById("remember").checked,
+    }));
+}
+
+function submitPasskeyLogin() {
+    const username_input_element = document.getElementById("username");
+    const submit_input_element = document.getElementById("passkey-submit");
+
+    if(username_input_element.value.length == 0) {
+        setFormWarning("Username is required!", username_input_element);
+        return;
+    }
+
+    let xhr = new XMLHttpRequest();
+    xhr.open("POST", "/api/login/passkey/options");
+    xhr.setRequestHeader("Content-Type", "application/json");
+
+    xhr.onreadystatechange = async function() {
+        if(xhr.readyState == 4) {
+            if(xhr.status == 200) {
+                const authentication = JSON.parse(xhr.response);
+
+                let credential;
+                try {
+                    credential = await getPasskey(authentication.options);
+                } catch(error) {
+                    setFormWarning("Passkey was not used.", submit_input_element);
+                    return;
+                }
+
+                finishPasskeyLogin(authentication.ceremony, credential);
+            } else if(xhr.status == 429) {
+                setFormWarning(xhr.responseText, submit_input_element);
+            } else {
+                setFormWarning(
+                    "Something has gone wrong! " +
+                    "Please contact the server admin if the problem persists.",
+                    submit_input_element);
+            }
+        }
+    };
+
+    xhr.send(JSON.stringify({ username: username_input_element.value }));
+}
+
 function submitLogin() {
     const username_input_element = document.getElementById("username");
     const password_input_element = document.getElementById("password");
+    const remember_input_element = document.getElementById("remember");
     const submit_input_element = document.getElementById("submit");
 
     let xhr = new XMLHttpRequest();
@@ -20,10 +132,15 @@ function submitLogin() {
 
     xhr.onreadystatechange = function() {
         if(xhr.readyState == 4) {
-            if(xhr.status > 99 && xhr.status < 300) {
+            if(xhr.status == 202) {
+                challenge = JSON.parse(xhr.response).challenge;
+                showTotpForm();
+            } else if(xhr.status > 99 && xhr.status < 300) {
                 window.location.href = "/timeline.html";
             } else if(xhr.status == 401) {
                 setFormWarning("Username and/or password is incorrect.", username_input_element);
+            } else if(xhr.status == 403 || xhr.status == 429) {
+                setFormWarning(xhr.responseText, submit_input_element);
             } else {
                 setFormWarning(
                     "Something has gone wrong! " +
@@ -36,6 +153,7 @@ function submitLogin() {
     const data = {};
     data.username = username_input_element.value;
     data.password = password_input_element.value;
+    data.remember = remember_input_element.checked;
 
     if(data.username.length == 0) {
         setFormWarning("Username is required!", username_input_element);
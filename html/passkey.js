@@ -0,0 +1,67 @@
+// Conversions between the JSON of the passkey endpoints and the WebAuthn API of the browser.
+
+function base64urlToBuffer(value) {
+    const base64 = value.replace(/-/g, "+").replace(/_/g, "/");
+    const padded = base64 + "=".repeat((4 - base64.length % 4) % 4);
+    return Uint8Array.from(atob(padded), (c) => c.charCodeAt(0)).buffer;
+}
+
+function bufferToBase64url(buffer) {
+    const bytes = String.fromCharCode(...new Uint8Array(buffer));
+    return btoa(bytes).replace(/\+/g, "-").replace(/\//g, "_").replace(/=+$/, "");
+}
+
+function decodeCredentialDescriptors(descriptors) {
+    return (descriptors ?? []).map((descriptor) => ({
+        ...descriptor,
+        id: base64urlToBuffer(descriptor.id),
+    }));
+}
+
+async function createPasskey(options) {
+    const public_key = options.publicKey;
+    const credential = await navigator.credentials.create({
+        publicKey: {
+            ...public_key,
+            challenge: base64urlToBuffer(public_key.challenge),
+            user: { ...public_key.user, id: base64urlToBuffer(public_key.user.id) },
+            excludeCredentials: decodeCredentialDescriptors(public_key.excludeCredentials),
+        },
+    });
+
+    return {
+        id: credential.id,
+        rawId: bufferToBase64url(credential.rawId),
+        type: credential.type,
+        response: {
+            attestationObject: bufferToBase64url(credential.response.attestationObject),
+            clientDataJSON: bufferToBase64url(credential.response.clientDataJSON),
+        },
+        extensions: credential.getClientExtensionResults(),
+    };
+}
+
+async function getPasskey(options) {
+    const public_key = options.publicKey;
+    const credential = await navigator.credentials.get({
+        publicKey: {
+            ...public_key,
+            challenge: base64urlToBuffer(public_key.challenge),
+            allowCredentials: decodeCredentialDescriptors(public_key.allowCredentials),
+        },
+    });
+
+    const response = credential.response;
+    return {
+        id: credential.id,
+        rawId: bufferToBase64url(credential.rawId),
+        type: credential.type,
+        response: {
+            authenticatorData: bufferToBase64url(response.authenticatorData),
+            clientDataJSON: bufferToBase64url(response.clientDataJSON),
+            signature: bufferToBase64url(response.signature),
+            userHandle: response.userHandle ? bufferToBase64url(response.userHandle) : null,
+        },
+        extensions: credential.getClientExtensionResults(),
+    };
+}
@@ -11,7 +11,8 @@ function isPersonalPage(path) {
 function isAuthPage(path) {
     const auth_pages = [
         "/login.html",
-        "/register.html"
+        "/register.html",
+        "/reset.html"
     ];
     
     return auth_pages.includes(path);
@@ -12,6 +12,7 @@ function submitRegister() {
     const username_input_element = document.getElementById("username");
     const password_input_element = document.getElementById("password");
     const password_confirm_input_element = document.getElementById("password-confirm");
+    const invite_input_element = document.getElementById("invite");
     const submit_input_element = document.getElementById("submit");
 
     let xhr = new XMLHttpRequest();
@@ -21,8 +22,21 @@ function submitRegister() {
 
     xhr.onreadystatechange = function() {
         if(xhr.readyState == 4) {
-            if(xhr.status > 99 && xhr.status < 300) {
+            if(xhr.status == 202) {
+                document.getElementById("register-form").hidden = true;
+                document.getElementById("warning-container").hidden = true;
+                document.getElementById("pending-paragraph").hidden = false;
+            } else if(xhr.status > 99 && xhr.status < 300) {
                 window.location.href = "/login.html";
+            } else if(xhr.status == 403) {
+                setFormWarning(xhr.responseText, invite_input_element);
+            } else if(xhr.status == 409) {
+                setFormWarning("Username is already taken!", username_input_element);
+            } else if(xhr.status == 422) {
+                const violation = JSON.parse(xhr.response);
+                const field_element = violation.field == "username" ?
+                    username_input_element : password_input_element;
+                setFormWarning(violation.message, field_element);
             } else {
                 setFormWarning(
                     "Something has gone wrong! " +
@@ -41,8 +55,8 @@ function submitRegister() {
         return;
     }
 
-    if(data.username.length < 2) {
-        setFormWarning("Username cannot be one character!", username_input_element);
+    if(data.username.length < 3) {
+        setFormWarning("Username must be at least 3 characters!", username_input_element);
         return;
     }
 
@@ -51,8 +65,8 @@ function submitRegister() {
         return;
     }
 
-    if(data.password.length < 6) {
-        setFormWarning("Password must be at least 6 characters!", password_input_element);
+    if(data.password.length < 8) {
+        setFormWarning("Password must be at least 8 characters!", password_input_element);
         return;
     }
 
@@ -61,5 +75,40 @@ function submitRegister() {
         return;
     }
 
+    if(!invite_input_element.hidden) {
+        if(invite_input_element.value.length == 0) {
+            setFormWarning("Invite code is required!", invite_input_element);
+            return;
+        }
+
+        data.invite = invite_input_element.value;
+    }
+
     xhr.send(JSON.stringify(data));
 }
+
+// Asks for an invite code or tells that registration is closed, as the server requires.
+function loadRegistration() {
+    let xhr = new XMLHttpRequest();
+    xhr.open("GET", "/api/registration");
+
+    xhr.onreadystatechange = function() {
+        if(xhr.readyState == 4 && xhr.status == 200) {
+            const mode = JSON.parse(xhr.response).mode;
+
+            if(mode == "closed") {
+                document.getElementById("register-form").hidden = true;
+                document.getElementById("closed-paragraph").hidden = false;
+            } else if(mode == "invite") {
+                const url_parameters = new URLSearchParams(window.location.search);
+                document.getElementById("invite").value = url_parameters.get("invite") ?? "";
+                document.getElementById("invite-label").hidden = false;
+                document.getElementById("invite").hidden = false;
+            }
+        }
+    };
+
+    xhr.send();
+}
+
+loadRegistration();
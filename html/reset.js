@@ -0,0 +1,61 @@
+function setFormWarning(description, focus_element) {
+    const container = document.getElementById("warning-container");
+    const paragraph = document.getElementById("warning-paragraph");
+
+    container.hidden = false;
+    paragraph.innerHTML = description;
+
+    focus_element.focus();
+}
+
+function submitReset() {
+    const username_input_element = document.getElementById("username");
+    const current_password_input_element = document.getElementById("current-password");
+    const password_input_element = document.getElementById("password");
+    const password_confirm_input_element = document.getElementById("password-confirm");
+    const submit_input_element = document.getElementById("submit");
+
+    let xhr = new XMLHttpRequest();
+    xhr.open("POST", "/api/password-reset");
+    xhr.setRequestHeader("Content-Type", "application/json");
+
+    xhr.onreadystatechange = function() {
+        if(xhr.readyState == 4) {
+            if(xhr.status > 99 && xhr.status < 300) {
+                window.location.href = "/login.html";
+            } else if(xhr.status == 422) {
+                setFormWarning(JSON.parse(xhr.response).message, password_input_element);
+            } else if(xhr.status == 401) {
+                setFormWarning("Username and/or password is incorrect.", current_password_input_element);
+            } else if(xhr.status == 403 || xhr.status == 409 || xhr.status == 429) {
+                setFormWarning(xhr.responseText, submit_input_element);
+            } else {
+                setFormWarning(
+                    "Something has gone wrong! " +
+                    "Please contact the server admin if the problem persists.",
+                    submit_input_element);
+            }
+        }
+    };
+
+    if(username_input_element.value.length == 0) {
+        setFormWarning("Username is required!", username_input_element);
+        return;
+    }
+
+    if(current_password_input_element.value.length == 0) {
+        setFormWarning("Current password is required!", current_password_input_element);
+        return;
+    }
+
+    if(password_input_element.value != password_confirm_input_element.value) {
+        setFormWarning("Passwords do not match!", password_confirm_input_element);
+        return;
+    }
+
+    xhr.send(JSON.stringify({
+        username: username_input_element.value,
+        current_password: current_password_input_element.value,
+        password: password_input_element.value,
+    }));
+}
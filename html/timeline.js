@@ -68,30 +68,63 @@ function create_journal_entry(entry_uuid, created, title, body) {
 
 const user_uuid = get_cookie_value("user_uuid");
 
-const list_xhr = new XMLHttpRequest();
-list_xhr.onreadystatechange = function() {
-    if(this.readyState == 4 && this.status == 200) {
-        const timeline = document.getElementById("timeline");
-        const response = JSON.parse(this.response);
-
-        response.uuid.forEach(function(uuid, index) {
-            const created = response.created[index];
-            const title = response.title[index];
-            const body = response.body[index];
-
-            let group = document.getElementById(get_group_id_from_created(response.created[index]));
-            if(group == null) {
-                group = create_journal_group(response.created[index]);
-            }
-
-            const entry_element = create_journal_entry(uuid, created, title, body);
-            if(group.children.length == 1) {
-                group.appendChild(entry_element);
-            } else {
-                group.insertBefore(entry_element, group.children[1]);
-            }
-        });
+let next_cursor = null;
+let loading = false;
+let exhausted = false;
+
+function load_entries() {
+    if(loading || exhausted) {
+        return;
+    }
+    loading = true;
+
+    let url = "/api/users/" + user_uuid + "/entries?limit=20&full_content=false";
+    if(next_cursor != null) {
+        url += "&before=" + encodeURIComponent(next_cursor);
     }
-};
-list_xhr.open("GET", "/api/users/" + user_uuid + "/entries");
-list_xhr.send();
+
+    const list_xhr = new XMLHttpRequest();
+    list_xhr.onreadystatechange = function() {
+        if(this.readyState != 4) {
+            return;
+        }
+        loading = false;
+
+        if(this.status == 200) {
+            const response = JSON.parse(this.response);
+
+            response.uuid.forEach(function(uuid, index) {
+                const created = response.created[index];
+                const title = response.title[index];
+                const body = response.excerpt[index];
+
+                let group = document.getElementById(get_group_id_from_created(response.created[index]));
+                if(group == null) {
+                    group = create_journal_group(response.created[index]);
+                }
+
+                const entry_element = create_journal_entry(uuid, created, title, body);
+                if(group.children.length == 1) {
+                    group.appendChild(entry_element);
+                } else {
+                    group.insertBefore(entry_element, group.children[1]);
+                }
+            });
+
+            next_cursor = response.next_cursor;
+            exhausted = next_cursor == null;
+            load_if_near_bottom();
+        }
+    };
+    list_xhr.open("GET", url);
+    list_xhr.send();
+}
+
+function load_if_near_bottom() {
+    if(window.innerHeight + window.scrollY >= document.body.offsetHeight - 200) {
+        load_entries();
+    }
+}
+
+window.addEventListener("scroll", load_if_near_bottom);
+load_entries();
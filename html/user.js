@@ -20,3 +20,394 @@ function logout() {
 
     xhr.send();
 }
+
+function saveProfile() {
+    const user_uuid = getCookieValue("user_uuid");
+    const status = document.getElementById("profile-status");
+
+    const data = {
+        display_name: document.getElementById("display-name").value,
+        timezone: document.getElementById("timezone").value,
+        week_start: document.getElementById("week-start").value,
+        theme: document.getElementById("theme").value,
+    };
+
+    let xhr = new XMLHttpRequest();
+    xhr.open("PATCH", "/api/users/" + user_uuid);
+    xhr.setRequestHeader("Content-Type", "application/json");
+
+    xhr.onreadystatechange = function() {
+        if(this.readyState == 4) {
+            if(this.status > 99 && this.status < 300) {
+                status.textContent = "Saved.";
+            } else if(this.status == 400) {
+                status.textContent = this.responseText;
+            } else {
+                status.textContent = "Something has gone wrong!";
+            }
+        }
+    };
+
+    xhr.send(JSON.stringify(data));
+}
+
+function loadProfile() {
+    const user_uuid = getCookieValue("user_uuid");
+
+    let xhr = new XMLHttpRequest();
+    xhr.open("GET", "/api/users/" + user_uuid);
+    xhr.setRequestHeader("Accept", "application/json");
+
+    xhr.onreadystatechange = function() {
+        if(this.readyState == 4 && this.status == 200) {
+            const user = JSON.parse(xhr.response);
+            document.getElementById("profile-summary").textContent =
+                user.username + ", " + user.entry_count + " entries since " + user.created.slice(0, 10);
+            document.getElementById("display-name").value = user.display_name ?? "";
+            document.getElementById("timezone").value = user.timezone ?? "";
+            document.getElementById("week-start").value = user.week_start;
+            document.getElementById("theme").value = user.theme;
+            showTotpState(user.two_factor ? "enabled" : "disabled");
+        }
+    };
+
+    xhr.send();
+}
+
+function changePassword() {
+    const user_uuid = getCookieValue("user_uuid");
+    const status = document.getElementById("password-status");
+
+    const data = {
+        current_password: document.getElementById("current-password").value,
+        new_password: document.getElementById("new-password").value,
+    };
+
+    let xhr = new XMLHttpRequest();
+    xhr.open("PUT", "/api/users/" + user_uuid + "/password");
+    xhr.setRequestHeader("Content-Type", "application/json");
+
+    xhr.onreadystatechange = function() {
+        if(this.readyState == 4) {
+            if(this.status > 99 && this.status < 300) {
+                status.textContent = "Password changed. Other sessions have been logged out and access tokens revoked.";
+                document.getElementById("password-form").reset();
+            } else if(this.status == 401) {
+                status.textContent = this.responseText;
+            } else if(this.status == 422) {
+                status.textContent = JSON.parse(this.responseText).message;
+            } else {
+                status.textContent = "Something has gone wrong!";
+            }
+        }
+    };
+
+    xhr.send(JSON.stringify(data));
+}
+
+// One of "disabled", "pending" and "enabled".
+let totp_state = "disabled";
+
+function showTotpState(state) {
+    const summaries = {
+        disabled: "Two-factor authentication is off. Enter your password to set it up.",
+        pending: "Add the key below to your authenticator app, then enter the code it shows.",
+        enabled: "Two-factor authentication is on. Enter your password and a code to turn it off.",
+    };
+    const submit_values = { disabled: "Set up", pending: "Enable", enabled: "Disable" };
+
+    totp_state = state;
+    document.getElementById("totp-summary").textContent = summaries[state];
+    document.getElementById("totp-submit").value = submit_values[state];
+    document.getElementById("totp-secret").hidden = state != "pending";
+    document.getElementById("totp-password-label").hidden = state == "pending";
+    document.getElementById("totp-password").hidden = state == "pending";
+    document.getElementById("totp-code-label").hidden = state == "disabled";
+    document.getElementById("totp-code").hidden = state == "disabled";
+}
+
+function submitTotp() {
+    const user_uuid = getCookieValue("user_uuid");
+    const status = document.getElementById("totp-status");
+    const password = document.getElementById("totp-password").value;
+    const code = document.getElementById("totp-code").value;
+
+    const requests = {
+        disabled: ["POST", { password: password }],
+        pending: ["PUT", { code: code }],
+        enabled: ["DELETE", { password: password, code: code }],
+    };
+    const [method, data] = requests[totp_state];
+
+    let xhr = new XMLHttpRequest();
+    xhr.open(method, "/api/users/" + user_uuid + "/totp");
+    xhr.setRequestHeader("Content-Type", "application/json");
+
+    xhr.onreadystatechange = function() {
+        if(this.readyState == 4) {
+            if(this.status > 99 && this.status < 300) {
+                document.getElementById("totp-form").reset();
+                status.textContent = "";
+
+                if(totp_state == "disabled") {
+                    const enrollment = JSON.parse(xhr.response);
+                    document.getElementById("totp-secret").textContent = "Key: " + enrollment.secret;
+                    showTotpState("pending");
+                } else if(totp_state == "pending") {
+                    const result = JSON.parse(xhr.response);
+                    status.textContent = "Enabled. Keep these recovery codes somewhere safe, " +
+                        "they are shown only once: " + result.recovery_codes.join(", ");
+                    showTotpState("enabled");
+                } else {
+                    status.textContent = "Disabled.";
+                    showTotpState("disabled");
+                }
+            } else if(this.status == 401 || this.status == 409) {
+                status.textContent = this.responseText;
+            } else {
+                status.textContent = "Something has gone wrong!";
+            }
+        }
+    };
+
+    xhr.send(JSON.stringify(data));
+}
+
+function deleteAccount() {
+    if(!confirm("Delete your account and every entry permanently? This cannot be undone.")) {
+        return;
+    }
+
+    const user_uuid = getCookieValue("user_uuid");
+    const status = document.getElementById("delete-status");
+
+    let xhr = new XMLHttpRequest();
+    xhr.open("DELETE", "/api/users/" + user_uuid);
+    xhr.setRequestHeader("Content-Type", "application/json");
+
+    xhr.onreadystatechange = function() {
+        if(this.readyState == 4) {
+            if(this.status > 99 && this.status < 300) {
+                const receipt = JSON.parse(xhr.response);
+                alert("Deleted " + receipt.entries + " entries and " + receipt.attachments + " attachments.");
+                window.location.href = "/login.html";
+            } else if(this.status == 401) {
+                status.textContent = this.responseText;
+            } else {
+                status.textContent = "Something has gone wrong!";
+            }
+        }
+    };
+
+    xhr.send(JSON.stringify({ password: document.getElementById("delete-password").value }));
+}
+
+function addPasskey() {
+    const user_uuid = getCookieValue("user_uuid");
+    const status = document.getElementById("passkey-status");
+    const name = document.getElementById("passkey-name").value;
+
+    if(name.trim().length == 0) {
+        status.textContent = "Name is required!";
+        return;
+    }
+
+    let xhr = new XMLHttpRequest();
+    xhr.open("POST", "/api/users/" + user_uuid + "/passkeys/options");
+    xhr.setRequestHeader("Content-Type", "application/json");
+
+    xhr.onreadystatechange = async function() {
+        if(this.readyState == 4) {
+            if(this.status == 200) {
+                const registration = JSON.parse(xhr.response);
+
+                let credential;
+                try {
+                    credential = await createPasskey(registration.options);
+                } catch(error) {
+                    status.textContent = "Passkey was not created.";
+                    return;
+                }
+
+                finishPasskey(registration.ceremony, name, credential);
+            } else if(this.status == 401) {
+                status.textContent = this.responseText;
+            } else {
+                status.textContent = "Something has gone wrong!";
+            }
+        }
+    };
+
+    xhr.send(JSON.stringify({ password: document.getElementById("passkey-password").value }));
+}
+
+function finishPasskey(ceremony, name, credential) {
+    const user_uuid = getCookieValue("user_uuid");
+    const status = document.getElementById("passkey-status");
+
+    let xhr = new XMLHttpRequest();
+    xhr.open("POST", "/api/users/" + user_uuid + "/passkeys");
+    xhr.setRequestHeader("Content-Type", "application/json");
+
+    xhr.onreadystatechange = function() {
+        if(this.readyState == 4) {
+            if(this.status > 99 && this.status < 300) {
+                status.textContent = "Passkey added.";
+                document.getElementById("passkey-form").reset();
+                loadPasskeys();
+            } else if(this.status == 400 || this.status == 401 || this.status == 409) {
+                status.textContent = this.responseText;
+            } else {
+                status.textContent = "Something has gone wrong!";
+            }
+        }
+    };
+
+    xhr.send(JSON.stringify({ ceremony: ceremony, name: name, credential: credential }));
+}
+
+function removePasskey(passkey_uuid) {
+    const user_uuid = getCookieValue("user_uuid");
+
+    let xhr = new XMLHttpRequest();
+    xhr.open("DELETE", "/api/users/" + user_uuid + "/passkeys/" + passkey_uuid);
+
+    xhr.onreadystatechange = function() {
+        if(this.readyState == 4 && this.status > 99 && this.status < 300) {
+            loadPasskeys();
+        }
+    };
+
+    xhr.send();
+}
+
+function loadPasskeys() {
+    const user_uuid = getCookieValue("user_uuid");
+
+    let xhr = new XMLHttpRequest();
+    xhr.open("GET", "/api/users/" + user_uuid + "/passkeys");
+    xhr.setRequestHeader("Accept", "application/json");
+
+    xhr.onreadystatechange = function() {
+        if(this.readyState == 4 && this.status == 200) {
+            const passkeys = JSON.parse(xhr.response);
+            const list = document.getElementById("passkey-list");
+            list.replaceChildren();
+
+            for(let index = 0; index < passkeys.uuid.length; index++) {
+                const row = document.createElement("tr");
+                const last_used = passkeys.last_used[index];
+                const cells = [
+                    passkeys.name[index],
+                    formatTimestamp(passkeys.created[index]),
+                    last_used ? formatTimestamp(last_used) : "Never",
+                ];
+
+                for(const text of cells) {
+                    const cell = document.createElement("td");
+                    cell.textContent = text;
+                    row.appendChild(cell);
+                }
+
+                const action = document.createElement("td");
+                const button = document.createElement("button");
+                button.type = "button";
+                button.textContent = "Remove";
+                button.onclick = () => removePasskey(passkeys.uuid[index]);
+                action.appendChild(button);
+                row.appendChild(action);
+
+                list.appendChild(row);
+            }
+        }
+    };
+
+    xhr.send();
+}
+
+function revokeSession(session_uuid) {
+    const user_uuid = getCookieValue("user_uuid");
+
+    let xhr = new XMLHttpRequest();
+    xhr.open("DELETE", "/api/users/" + user_uuid + "/sessions/" + session_uuid);
+
+    xhr.onreadystatechange = function() {
+        if(this.readyState == 4 && this.status > 99 && this.status < 300) {
+            loadSessions();
+        }
+    };
+
+    xhr.send();
+}
+
+function revokeOtherSessions() {
+    const user_uuid = getCookieValue("user_uuid");
+
+    let xhr = new XMLHttpRequest();
+    xhr.open("DELETE", "/api/users/" + user_uuid + "/sessions");
+
+    xhr.onreadystatechange = function() {
+        if(this.readyState == 4 && this.status > 99 && this.status < 300) {
+            loadSessions();
+        }
+    };
+
+    xhr.send();
+}
+
+function formatTimestamp(timestamp) {
+    return new Date(timestamp).toLocaleString();
+}
+
+function loadSessions() {
+    const user_uuid = getCookieValue("user_uuid");
+
+    let xhr = new XMLHttpRequest();
+    xhr.open("GET", "/api/users/" + user_uuid + "/sessions");
+    xhr.setRequestHeader("Accept", "application/json");
+
+    xhr.onreadystatechange = function() {
+        if(this.readyState == 4 && this.status == 200) {
+            const sessions = JSON.parse(xhr.response);
+            const list = document.getElementById("session-list");
+            list.replaceChildren();
+
+            for(let index = 0; index < sessions.uuid.length; index++) {
+                const row = document.createElement("tr");
+                const cells = [
+                    sessions.user_agent[index] ?? "Unknown",
+                    sessions.ip[index] ?? "Unknown",
+                    formatTimestamp(sessions.created[index]),
+                    formatTimestamp(sessions.last_used[index]),
+                ];
+
+                for(const text of cells) {
+                    const cell = document.createElement("td");
+                    cell.textContent = text;
+                    row.appendChild(cell);
+                }
+
+                const action = document.createElement("td");
+                if(sessions.current[index]) {
+                    action.textContent = "This session";
+                } else {
+                    const button = document.createElement("button");
+                    button.type = "button";
+                    button.textContent = "Revoke";
+                    button.onclick = () => revokeSession(sessions.uuid[index]);
+                    action.appendChild(button);
+                }
+                row.appendChild(action);
+
+                list.appendChild(row);
+            }
+        }
+    };
+
+    xhr.send();
+}
+
+document.getElementById("export-link").href = "/api/users/" + getCookieValue("user_uuid") + "/export?format=json";
+loadProfile();
+loadSessions();
+loadPasskeys();
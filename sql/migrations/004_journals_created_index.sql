@@ -0,0 +1 @@
+CREATE INDEX journals_user_created_idx ON journals (user_uuid, created DESC, uuid DESC);
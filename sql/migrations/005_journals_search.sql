@@ -0,0 +1,5 @@
+ALTER TABLE journals ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+    setweight(to_tsvector('simple', title), 'A') || setweight(to_tsvector('simple', body), 'B')
+) STORED;
+
+CREATE INDEX journals_search_idx ON journals USING GIN (search_vector);
@@ -0,0 +1,11 @@
+CREATE TABLE journal_revisions (
+    uuid CHAR(36) NOT NULL,
+    journal_uuid CHAR(36) NOT NULL,
+    created TIMESTAMP NOT NULL,
+    title TEXT NOT NULL,
+    body TEXT NOT NULL,
+    PRIMARY KEY (uuid),
+    FOREIGN KEY (journal_uuid) REFERENCES journals(uuid) ON DELETE CASCADE
+);
+
+CREATE INDEX journal_revisions_journal_created_idx ON journal_revisions (journal_uuid, created DESC);
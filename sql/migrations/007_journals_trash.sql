@@ -0,0 +1,6 @@
+ALTER TABLE journals ADD COLUMN deleted_at TIMESTAMP;
+
+DROP INDEX journals_user_created_idx;
+CREATE INDEX journals_user_created_idx ON journals (user_uuid, created DESC, uuid DESC) 
+    WHERE deleted_at IS NULL;
+CREATE INDEX journals_deleted_at_idx ON journals (deleted_at) WHERE deleted_at IS NOT NULL;
@@ -0,0 +1,18 @@
+CREATE TABLE tags (
+    uuid CHAR(36) NOT NULL,
+    user_uuid CHAR(36) NOT NULL,
+    name VARCHAR(64) NOT NULL,
+    PRIMARY KEY (uuid),
+    UNIQUE (user_uuid, name),
+    FOREIGN KEY (user_uuid) REFERENCES users(uuid)
+);
+
+CREATE TABLE entry_tags (
+    journal_uuid CHAR(36) NOT NULL,
+    tag_uuid CHAR(36) NOT NULL,
+    PRIMARY KEY (journal_uuid, tag_uuid),
+    FOREIGN KEY (journal_uuid) REFERENCES journals(uuid) ON DELETE CASCADE,
+    FOREIGN KEY (tag_uuid) REFERENCES tags(uuid) ON DELETE CASCADE
+);
+
+CREATE INDEX entry_tags_tag_idx ON entry_tags (tag_uuid);
@@ -0,0 +1,15 @@
+CREATE TABLE attachments (
+    uuid CHAR(36) NOT NULL,
+    journal_uuid CHAR(36) NOT NULL,
+    user_uuid CHAR(36) NOT NULL,
+    filename TEXT NOT NULL,
+    content_type VARCHAR(255) NOT NULL,
+    size BIGINT NOT NULL,
+    created TIMESTAMP NOT NULL,
+    PRIMARY KEY (uuid),
+    FOREIGN KEY (journal_uuid) REFERENCES journals(uuid),
+    FOREIGN KEY (user_uuid) REFERENCES users(uuid)
+);
+
+CREATE INDEX attachments_journal_idx ON attachments (journal_uuid);
+CREATE INDEX attachments_user_idx ON attachments (user_uuid);
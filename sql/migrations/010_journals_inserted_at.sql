@@ -0,0 +1,3 @@
+ALTER TABLE journals ADD COLUMN inserted_at TIMESTAMP;
+UPDATE journals SET inserted_at = created;
+ALTER TABLE journals ALTER COLUMN inserted_at SET NOT NULL;
@@ -0,0 +1,27 @@
+-- IANA time zone name. NULL for entries with an offset which maps to no zone.
+ALTER TABLE journals ADD COLUMN timezone TEXT;
+
+-- Whole hour offsets map onto the Etc/GMT zones, whose signs follow the JS offsets. Other
+-- offsets, such as +05:30, take the first zone which had the offset at the creation date.
+-- Offsets no zone ever had stay NULL and keep rendering at the offset itself.
+UPDATE journals SET timezone = CASE
+        WHEN journals.timezone_offset = 0 THEN 'Etc/UTC'
+        WHEN journals.timezone_offset % 60 = 0 AND journals.timezone_offset BETWEEN 1 AND 720
+            THEN 'Etc/GMT+' || (journals.timezone_offset / 60)
+        WHEN journals.timezone_offset % 60 = 0 AND journals.timezone_offset BETWEEN -840 AND -1
+            THEN 'Etc/GMT-' || (-journals.timezone_offset / 60)
+        ELSE matches.name
+    END
+    FROM (
+        SELECT DISTINCT ON (journals.uuid) journals.uuid, zones.name
+        FROM journals
+        LEFT JOIN (
+            SELECT name FROM pg_timezone_names
+            WHERE name LIKE '%/%' AND name NOT LIKE 'posix/%' AND name NOT LIKE 'right/%'
+        ) AS zones
+            ON journals.timezone_offset % 60 <> 0
+            AND (journals.created AT TIME ZONE 'UTC' AT TIME ZONE zones.name) - journals.created
+                = make_interval(mins => -journals.timezone_offset)
+        ORDER BY journals.uuid, zones.name
+    ) AS matches
+    WHERE matches.uuid = journals.uuid;
@@ -0,0 +1,18 @@
+ALTER TABLE users ADD COLUMN created TIMESTAMP;
+ALTER TABLE users ADD COLUMN display_name VARCHAR(64);
+
+-- Existing users are dated by their first entry, if any.
+UPDATE users SET created = COALESCE(
+    (SELECT min(inserted_at) FROM journals WHERE journals.user_uuid = users.uuid),
+    now() AT TIME ZONE 'UTC');
+ALTER TABLE users ALTER COLUMN created SET NOT NULL;
+
+-- Users without a row have the default settings.
+CREATE TABLE user_settings (
+    user_uuid CHAR(36) NOT NULL,
+    timezone TEXT,
+    week_start TEXT NOT NULL,
+    theme TEXT NOT NULL,
+    PRIMARY KEY (user_uuid),
+    FOREIGN KEY (user_uuid) REFERENCES users(uuid)
+);
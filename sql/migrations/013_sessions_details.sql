@@ -0,0 +1,9 @@
+ALTER TABLE sessions ADD COLUMN created TIMESTAMP;
+ALTER TABLE sessions ADD COLUMN last_used TIMESTAMP;
+ALTER TABLE sessions ADD COLUMN user_agent TEXT;
+ALTER TABLE sessions ADD COLUMN ip TEXT;
+
+-- Sessions used to last 30 minutes from creation or the last refresh.
+UPDATE sessions SET created = expiry - INTERVAL '30 minutes', last_used = expiry - INTERVAL '30 minutes';
+ALTER TABLE sessions ALTER COLUMN created SET NOT NULL;
+ALTER TABLE sessions ALTER COLUMN last_used SET NOT NULL;
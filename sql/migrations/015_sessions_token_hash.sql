@@ -0,0 +1,4 @@
+-- Tokens are stored as hex encoded SHA-256 digests from now on.
+-- Sessions with plaintext tokens cannot be told apart from digests, so everyone is logged out.
+DELETE FROM sessions;
+ALTER TABLE sessions RENAME COLUMN token TO token_hash;
@@ -0,0 +1,15 @@
+CREATE TABLE access_tokens (
+    uuid CHAR(36) NOT NULL,
+    user_uuid CHAR(36) NOT NULL,
+    name VARCHAR(64) NOT NULL,
+    token_hash CHAR(64) NOT NULL,
+    scopes TEXT[] NOT NULL,
+    created TIMESTAMP NOT NULL,
+    last_used TIMESTAMP,
+    expiry TIMESTAMP,
+    PRIMARY KEY (uuid),
+    UNIQUE (token_hash),
+    FOREIGN KEY (user_uuid) REFERENCES users(uuid)
+);
+
+CREATE INDEX access_tokens_user_idx ON access_tokens (user_uuid);
@@ -0,0 +1,6 @@
+ALTER TABLE sessions ADD COLUMN remember BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE sessions ADD COLUMN absolute_expiry TIMESTAMP;
+
+-- Existing sessions keep their current expiry as the limit.
+UPDATE sessions SET absolute_expiry = expiry;
+ALTER TABLE sessions ALTER COLUMN absolute_expiry SET NOT NULL;
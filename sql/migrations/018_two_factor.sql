@@ -0,0 +1,26 @@
+-- The secret is kept while enrollment is pending, and 'totp_enabled' is set once a code is verified.
+ALTER TABLE users ADD COLUMN totp_secret TEXT;
+ALTER TABLE users ADD COLUMN totp_enabled BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE users ADD COLUMN totp_last_step BIGINT;
+
+CREATE TABLE recovery_codes (
+    uuid CHAR(36) NOT NULL,
+    user_uuid CHAR(36) NOT NULL,
+    code_hash CHAR(64) NOT NULL,
+    used_at TIMESTAMP,
+    PRIMARY KEY (uuid),
+    UNIQUE (user_uuid, code_hash),
+    FOREIGN KEY (user_uuid) REFERENCES users(uuid)
+);
+
+CREATE TABLE login_challenges (
+    uuid CHAR(36) NOT NULL,
+    user_uuid CHAR(36) NOT NULL,
+    token_hash CHAR(64) NOT NULL,
+    remember BOOLEAN NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    expiry TIMESTAMP NOT NULL,
+    PRIMARY KEY (uuid),
+    UNIQUE (token_hash),
+    FOREIGN KEY (user_uuid) REFERENCES users(uuid)
+);
@@ -0,0 +1,26 @@
+-- The credential is kept as serialized by the WebAuthn library, including the signature counter.
+CREATE TABLE passkeys (
+    uuid CHAR(36) NOT NULL,
+    user_uuid CHAR(36) NOT NULL,
+    name VARCHAR(64) NOT NULL,
+    credential_id TEXT NOT NULL,
+    credential TEXT NOT NULL,
+    created TIMESTAMP NOT NULL,
+    last_used TIMESTAMP,
+    PRIMARY KEY (uuid),
+    UNIQUE (credential_id),
+    FOREIGN KEY (user_uuid) REFERENCES users(uuid)
+);
+
+CREATE INDEX passkeys_user_idx ON passkeys (user_uuid);
+
+-- State of registrations and logins in progress, between the two requests of a ceremony.
+CREATE TABLE passkey_ceremonies (
+    uuid CHAR(36) NOT NULL,
+    user_uuid CHAR(36) NOT NULL,
+    kind VARCHAR(16) NOT NULL,
+    state TEXT NOT NULL,
+    expiry TIMESTAMP NOT NULL,
+    PRIMARY KEY (uuid),
+    FOREIGN KEY (user_uuid) REFERENCES users(uuid)
+);
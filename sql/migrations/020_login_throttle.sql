@@ -0,0 +1,22 @@
+-- Failed logins counted per username and per IP address, which 'kind' tells apart.
+CREATE TABLE login_failures (
+    kind VARCHAR(16) NOT NULL,
+    subject TEXT NOT NULL,
+    failures INTEGER NOT NULL,
+    last_failure TIMESTAMP NOT NULL,
+    locked_until TIMESTAMP,
+    PRIMARY KEY (kind, subject)
+);
+
+-- Security events, kept for operators. Usernames are kept as given, which may not exist.
+CREATE TABLE audit_events (
+    uuid CHAR(36) NOT NULL,
+    created TIMESTAMP NOT NULL,
+    event VARCHAR(32) NOT NULL,
+    username TEXT,
+    ip TEXT,
+    detail TEXT NOT NULL,
+    PRIMARY KEY (uuid)
+);
+
+CREATE INDEX audit_events_created_idx ON audit_events (created);
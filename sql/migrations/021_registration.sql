@@ -0,0 +1,21 @@
+-- The earliest user of an existing instance becomes its administrator.
+ALTER TABLE users ADD COLUMN is_admin BOOLEAN NOT NULL DEFAULT FALSE;
+UPDATE users SET is_admin = TRUE WHERE uuid = (SELECT uuid FROM users ORDER BY created LIMIT 1);
+
+-- Users registered while approval is required cannot log in until an administrator approves.
+ALTER TABLE users ADD COLUMN approved BOOLEAN NOT NULL DEFAULT TRUE;
+
+-- Only digests of invite codes are stored, like those of session tokens.
+-- 'user_uuid' is the administrator who created the invite.
+CREATE TABLE invites (
+    uuid CHAR(36) NOT NULL,
+    user_uuid CHAR(36) NOT NULL,
+    code_hash CHAR(64) NOT NULL,
+    created TIMESTAMP NOT NULL,
+    expiry TIMESTAMP NOT NULL,
+    max_uses INTEGER NOT NULL,
+    uses INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (uuid),
+    UNIQUE (code_hash),
+    FOREIGN KEY (user_uuid) REFERENCES users(uuid)
+);
@@ -0,0 +1,5 @@
+-- Disabled users cannot log in or use their access tokens, but keep everything they have.
+ALTER TABLE users ADD COLUMN disabled BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Users whose password was reset by an administrator have to set a new one before logging in.
+ALTER TABLE users ADD COLUMN password_reset_required BOOLEAN NOT NULL DEFAULT FALSE;
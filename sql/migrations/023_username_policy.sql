@@ -0,0 +1,30 @@
+-- Usernames are unique by their key, the NFKC form in lower case, which the server computes
+-- for new users. Existing usernames are normalized and keyed here. ASCII ones are lowered
+-- by hand, so that the collation cannot fold them differently than the server does.
+ALTER TABLE users ADD COLUMN username_key TEXT;
+
+UPDATE users SET username = btrim(username);
+UPDATE users SET username = normalize(username, NFKC) WHERE username !~ '^[ -~]*$';
+
+UPDATE users SET username_key = CASE
+        WHEN username ~ '^[ -~]*$'
+            THEN translate(username, 'ABCDEFGHIJKLMNOPQRSTUVWXYZ', 'abcdefghijklmnopqrstuvwxyz')
+        ELSE lower(username)
+    END;
+
+-- Users whose usernames become the same have to be renamed by the operator before upgrading,
+-- since either user may be logging in with theirs.
+DO $$
+DECLARE
+    duplicates TEXT;
+BEGIN
+    SELECT string_agg(username, ', ' ORDER BY username) INTO duplicates FROM users
+    WHERE username_key IN (SELECT username_key FROM users GROUP BY username_key HAVING COUNT(*) > 1);
+
+    IF duplicates IS NOT NULL THEN
+        RAISE EXCEPTION 'Usernames which are the same once normalized should be renamed first: %', duplicates;
+    END IF;
+END $$;
+
+ALTER TABLE users ALTER COLUMN username_key SET NOT NULL;
+CREATE UNIQUE INDEX users_username_key_idx ON users (username_key);
@@ -0,0 +1,6 @@
+-- Secrets of the server which have to outlive restarts, named after what they are for.
+CREATE TABLE server_keys (
+    name TEXT NOT NULL,
+    value BYTEA NOT NULL,
+    PRIMARY KEY (name)
+);
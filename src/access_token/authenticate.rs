@@ -0,0 +1,85 @@
+use actix_web::{Error, error::{ErrorForbidden, ErrorInternalServerError, ErrorUnauthorized}};
+use chrono::{NaiveDateTime, Utc};
+use sqlx::{PgPool, Row};
+use subtle::ConstantTimeEq;
+use crate::session::hash_token;
+use super::Scope;
+
+// An access token which has been checked against the database.
+pub struct Authenticated {
+    pub uuid: String,
+    pub user_uuid: String,
+    pub scopes: Vec<Scope>,
+}
+
+async fn by_secret_sqlx(
+    db_pool: &PgPool,
+    secret: &str) -> Result<(Authenticated, Option<NaiveDateTime>, bool), sqlx::Error>
+{
+    let token_hash = hash_token(secret);
+
+    let row = sqlx::query(
+        "SELECT access_tokens.uuid, access_tokens.user_uuid, access_tokens.scopes, access_tokens.expiry, \
+             access_tokens.token_hash, users.disabled \
+         FROM access_tokens JOIN users ON users.uuid = access_tokens.user_uuid \
+         WHERE access_tokens.token_hash = $1")
+        .bind(&token_hash)
+        .fetch_one(db_pool)
+        .await?;
+
+    let stored_hash: String = row.try_get("token_hash")?;
+    if !bool::from(stored_hash.as_bytes().ct_eq(token_hash.as_bytes())) {
+        return Err(sqlx::Error::RowNotFound);
+    }
+
+    // Scopes were validated when the token was created.
+    let scopes: Vec<String> = row.try_get("scopes")?;
+    let token = Authenticated {
+        uuid: row.try_get("uuid")?,
+        user_uuid: row.try_get("user_uuid")?,
+        scopes: scopes.iter().filter_map(|scope| Scope::parse(scope).ok()).collect(),
+    };
+
+    Ok((token, row.try_get("expiry")?, row.try_get("disabled")?))
+}
+
+// Like sessions, the use of a token is recorded at most once a minute.
+async fn touch_sqlx(db_pool: &PgPool, token_uuid: &str) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE access_tokens SET last_used = $1 \
+         WHERE uuid = $2 AND (last_used IS NULL OR last_used < $1 - INTERVAL '1 minute')")
+        .bind(Utc::now().naive_utc())
+        .bind(token_uuid)
+        .execute(db_pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn authenticate(db_pool: &PgPool, secret: &str) -> Result<Authenticated, Error> {
+    let (token, expiry, disabled) = match by_secret_sqlx(db_pool, secret).await {
+        Ok(value) => value,
+        Err(sqlx::Error::RowNotFound) => return Err(ErrorUnauthorized("Access token cannot be verified.")),
+        Err(error) => {
+            println!("{error}");
+            return Err(ErrorInternalServerError("Database error."));
+        }
+    };
+
+    if let Some(expiry) = expiry {
+        if expiry < Utc::now().naive_utc() {
+            return Err(ErrorUnauthorized("Access token has expired."));
+        }
+    }
+
+    if disabled {
+        return Err(ErrorForbidden("Account is disabled."));
+    }
+
+    if let Err(error) = touch_sqlx(db_pool, &token.uuid).await {
+        println!("{error}");
+        return Err(ErrorInternalServerError("Database error."));
+    }
+
+    Ok(token)
+}
@@ -0,0 +1,114 @@
+use rand::{thread_rng, Rng};
+use rand::distributions::Alphanumeric;
+use actix_web::{Error, error::{ErrorBadRequest, ErrorInternalServerError}};
+use chrono::{Duration, NaiveDateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::session::hash_token;
+use super::{AccessToken, Scope, MAX_LIFETIME_DAYS, MAX_NAME_LENGTH, TOKEN_PREFIX};
+
+fn validate_name(name: &str) -> Result<String, Error> {
+    let name = name.trim();
+
+    if name.is_empty() {
+        return Err(ErrorBadRequest("Access token name should not be empty."));
+    }
+
+    if name.chars().count() > MAX_NAME_LENGTH {
+        return Err(ErrorBadRequest("Access token name is too long."));
+    }
+
+    Ok(name.to_string())
+}
+
+// Parses scopes, keeping the order they were given in and dropping duplicates.
+fn normalize_scopes(scopes: &[String]) -> Result<Vec<String>, Error> {
+    let mut normalized: Vec<String> = Vec::new();
+
+    for scope in scopes {
+        let scope = Scope::parse(scope)?.as_str().to_string();
+        if !normalized.contains(&scope) {
+            normalized.push(scope);
+        }
+    }
+
+    if normalized.is_empty() {
+        return Err(ErrorBadRequest("Access token should have at least one scope."));
+    }
+
+    Ok(normalized)
+}
+
+async fn insert_token_sqlx(
+    db_pool: &PgPool,
+    token: &AccessToken,
+    token_hash: &str,
+    created: NaiveDateTime,
+    expiry: Option<NaiveDateTime>) -> Result<(), sqlx::Error>
+{
+    sqlx::query(
+        "INSERT INTO access_tokens (uuid, user_uuid, name, token_hash, scopes, created, expiry) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7)")
+        .bind(&token.uuid)
+        .bind(&token.user_uuid)
+        .bind(&token.name)
+        .bind(token_hash)
+        .bind(&token.scopes)
+        .bind(created)
+        .bind(expiry)
+        .execute(db_pool)
+        .await?;
+
+    Ok(())
+}
+
+impl AccessToken {
+    /*
+    Returns the access token along with its secret. Only a digest of the secret is stored,
+    so this is the only time it can be seen. Tokens without 'lifetime_days' never expire.
+    */
+    pub async fn create(
+        db_pool: &PgPool,
+        user_uuid: &str,
+        name: &str,
+        scopes: &[String],
+        lifetime_days: Option<i64>) -> Result<(Self, String), Error>
+    {
+        let name = validate_name(name)?;
+        let scopes = normalize_scopes(scopes)?;
+
+        if let Some(days) = lifetime_days {
+            if !(1..=MAX_LIFETIME_DAYS).contains(&days) {
+                return Err(ErrorBadRequest("Lifetime should be between 1 and 3650 days."));
+            }
+        }
+
+        let secret: String = thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(48)
+            .map(char::from)
+            .collect();
+        let secret = format!("{TOKEN_PREFIX}{secret}");
+
+        let created = Utc::now().naive_utc();
+        let expiry = lifetime_days.map(|days| created + Duration::days(days));
+
+        let token = AccessToken {
+            uuid: Uuid::new_v4().to_string(),
+            user_uuid: user_uuid.to_string(),
+            name,
+            scopes,
+            created: created.and_utc().to_rfc3339(),
+            last_used: None,
+            expiry: expiry.map(|expiry| expiry.and_utc().to_rfc3339()),
+        };
+
+        match insert_token_sqlx(db_pool, &token, &hash_token(&secret), created, expiry).await {
+            Ok(_) => Ok((token, secret)),
+            Err(error) => {
+                println!("{error}");
+                Err(ErrorInternalServerError("Database error."))
+            }
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use actix_web::{Error, error::{ErrorInternalServerError, ErrorNotFound}};
+use sqlx::PgPool;
+use super::AccessToken;
+
+impl AccessToken {
+    pub async fn revoke(
+        db_pool: &PgPool,
+        token_uuid: &str,
+        user_uuid: &str) -> Result<(), Error>
+    {
+        let delete_result = sqlx::query("DELETE FROM access_tokens WHERE uuid = $1 AND user_uuid = $2")
+            .bind(token_uuid)
+            .bind(user_uuid)
+            .execute(db_pool)
+            .await;
+
+        let query_result = match delete_result {
+            Ok(value) => value,
+            Err(error) => {
+                println!("{error}");
+                return Err(ErrorInternalServerError("Database error."));
+            }
+        };
+
+        if query_result.rows_affected() == 0 {
+            return Err(ErrorNotFound("Access token not found."));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+use actix_web::{Error, error::ErrorInternalServerError};
+use chrono::NaiveDateTime;
+use sqlx::{PgPool, Row, postgres::PgRow};
+use super::AccessToken;
+
+fn token_from_row(row: &PgRow) -> Result<AccessToken, sqlx::Error> {
+    let created: NaiveDateTime = row.try_get("created")?;
+    let last_used: Option<NaiveDateTime> = row.try_get("last_used")?;
+    let expiry: Option<NaiveDateTime> = row.try_get("expiry")?;
+
+    Ok(AccessToken {
+        uuid: row.try_get("uuid")?,
+        user_uuid: row.try_get("user_uuid")?,
+        name: row.try_get("name")?,
+        scopes: row.try_get("scopes")?,
+        created: created.and_utc().to_rfc3339(),
+        last_used: last_used.map(|value| value.and_utc().to_rfc3339()),
+        expiry: expiry.map(|value| value.and_utc().to_rfc3339()),
+    })
+}
+
+async fn by_user_sqlx(
+    db_pool: &PgPool,
+    user_uuid: &str) -> Result<Vec<AccessToken>, sqlx::Error>
+{
+    let rows = sqlx::query(
+        "SELECT uuid, user_uuid, name, scopes, created, last_used, expiry FROM access_tokens \
+         WHERE user_uuid = $1 ORDER BY created DESC")
+        .bind(user_uuid)
+        .fetch_all(db_pool)
+        .await?;
+
+    rows.iter().map(token_from_row).collect()
+}
+
+impl AccessToken {
+    // Returns every access token of the user, including expired ones, recently created first.
+    pub async fn list_by_user(
+        db_pool: &PgPool,
+        user_uuid: &str) -> Result<Vec<Self>, Error>
+    {
+        match by_user_sqlx(db_pool, user_uuid).await {
+            Ok(value) => Ok(value),
+            Err(error) => {
+                println!("{error}");
+                Err(ErrorInternalServerError("Database error."))
+            }
+        }
+    }
+}
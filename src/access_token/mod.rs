@@ -0,0 +1,75 @@
+pub mod authenticate;
+pub mod create;
+pub mod delete;
+pub mod list;
+
+use actix_web::{Error, error::ErrorBadRequest, http::Method};
+
+pub const MAX_NAME_LENGTH: usize = 64;
+pub const MAX_LIFETIME_DAYS: i64 = 3650;
+
+// Personal access tokens are told apart from other secrets by this prefix.
+pub const TOKEN_PREFIX: &str = "cnpat_";
+
+/*
+What an access token is allowed to do.
+ entries:read: Reading entries, tags, attachments and exports.
+ entries:write: Creating, changing and deleting those, reading included.
+ admin: Everything, including the account itself, sessions and access tokens.
+*/
+#[derive(Clone, Copy, PartialEq)]
+pub enum Scope {
+    ReadEntries,
+    WriteEntries,
+    Admin,
+}
+
+impl Scope {
+    pub fn parse(value: &str) -> Result<Self, Error> {
+        match value {
+            "entries:read" => Ok(Scope::ReadEntries),
+            "entries:write" => Ok(Scope::WriteEntries),
+            "admin" => Ok(Scope::Admin),
+            _ => Err(ErrorBadRequest("Scope should be one of 'entries:read', 'entries:write' and 'admin'."))
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::ReadEntries => "entries:read",
+            Scope::WriteEntries => "entries:write",
+            Scope::Admin => "admin",
+        }
+    }
+
+    // Scope needed for a request to an API path under /api/users/{user_uuid} or /api/admin.
+    pub fn required_for(method: &Method, path: &str) -> Self {
+        let resource = path.split('/').nth(4).unwrap_or("");
+        let reading = method == Method::GET || method == Method::HEAD;
+
+        match (resource, reading) {
+            ("entries" | "tags" | "export", true) => Scope::ReadEntries,
+            ("entries" | "tags" | "import", _) => Scope::WriteEntries,
+            _ => Scope::Admin
+        }
+    }
+
+    // Whether a token with this scope may be used where 'required' is needed.
+    pub fn covers(&self, required: Scope) -> bool {
+        match self {
+            Scope::Admin => true,
+            Scope::WriteEntries => required != Scope::Admin,
+            Scope::ReadEntries => required == Scope::ReadEntries,
+        }
+    }
+}
+
+pub struct AccessToken {
+    pub uuid: String,
+    pub user_uuid: String,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub created: String,
+    pub last_used: Option<String>,
+    pub expiry: Option<String>,
+}
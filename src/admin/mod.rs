@@ -0,0 +1,37 @@
+pub mod stats;
+pub mod users;
+
+/*
+What administrators see of a user. Counts and sizes of entries are included,
+but never their contents.
+*/
+pub struct UserSummary {
+    pub uuid: String,
+    pub username: String,
+    pub display_name: Option<String>,
+    pub created: String,
+    pub is_admin: bool,
+    pub approved: bool,
+    pub disabled: bool,
+    pub password_reset_required: bool,
+    pub two_factor: bool,
+    pub entry_count: i64,
+    pub attachment_count: i64,
+    pub attachment_bytes: i64,
+    pub last_active: Option<String>,
+}
+
+// Totals of the whole instance.
+pub struct Stats {
+    pub users: i64,
+    pub admins: i64,
+    pub pending_users: i64,
+    pub disabled_users: i64,
+    pub entries: i64,
+    pub trashed_entries: i64,
+    pub revisions: i64,
+    pub attachments: i64,
+    pub attachment_bytes: i64,
+    pub active_sessions: i64,
+    pub database_bytes: i64,
+}
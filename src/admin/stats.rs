@@ -0,0 +1,47 @@
+use actix_web::{Error, error::ErrorInternalServerError};
+use chrono::Utc;
+use sqlx::{PgPool, Row};
+use super::Stats;
+
+async fn stats_sqlx(db_pool: &PgPool) -> Result<Stats, sqlx::Error> {
+    let row = sqlx::query(
+        "SELECT \
+             (SELECT COUNT(*) FROM users) AS users, \
+             (SELECT COUNT(*) FROM users WHERE is_admin) AS admins, \
+             (SELECT COUNT(*) FROM users WHERE NOT approved) AS pending_users, \
+             (SELECT COUNT(*) FROM users WHERE disabled) AS disabled_users, \
+             (SELECT COUNT(*) FROM journals WHERE deleted_at IS NULL) AS entries, \
+             (SELECT COUNT(*) FROM journals WHERE deleted_at IS NOT NULL) AS trashed_entries, \
+             (SELECT COUNT(*) FROM journal_revisions) AS revisions, \
+             (SELECT COUNT(*) FROM attachments) AS attachments, \
+             (SELECT COALESCE(SUM(size), 0)::BIGINT FROM attachments) AS attachment_bytes, \
+             (SELECT COUNT(*) FROM sessions WHERE expiry > $1) AS active_sessions, \
+             pg_database_size(current_database()) AS database_bytes")
+        .bind(Utc::now().naive_utc())
+        .fetch_one(db_pool)
+        .await?;
+
+    Ok(Stats {
+        users: row.try_get("users")?,
+        admins: row.try_get("admins")?,
+        pending_users: row.try_get("pending_users")?,
+        disabled_users: row.try_get("disabled_users")?,
+        entries: row.try_get("entries")?,
+        trashed_entries: row.try_get("trashed_entries")?,
+        revisions: row.try_get("revisions")?,
+        attachments: row.try_get("attachments")?,
+        attachment_bytes: row.try_get("attachment_bytes")?,
+        active_sessions: row.try_get("active_sessions")?,
+        database_bytes: row.try_get("database_bytes")?,
+    })
+}
+
+pub async fn stats(db_pool: &PgPool) -> Result<Stats, Error> {
+    match stats_sqlx(db_pool).await {
+        Ok(value) => Ok(value),
+        Err(error) => {
+            println!("{error}");
+            Err(ErrorInternalServerError("Database error."))
+        }
+    }
+}
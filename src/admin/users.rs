@@ -0,0 +1,53 @@
+use actix_web::{Error, error::ErrorInternalServerError};
+use chrono::NaiveDateTime;
+use sqlx::{PgPool, Row, postgres::PgRow};
+use super::UserSummary;
+
+fn summary_from_row(row: &PgRow) -> Result<UserSummary, sqlx::Error> {
+    let created: NaiveDateTime = row.try_get("created")?;
+    let last_active: Option<NaiveDateTime> = row.try_get("last_active")?;
+
+    Ok(UserSummary {
+        uuid: row.try_get("uuid")?,
+        username: row.try_get("username")?,
+        display_name: row.try_get("display_name")?,
+        created: created.and_utc().to_rfc3339(),
+        is_admin: row.try_get("is_admin")?,
+        approved: row.try_get("approved")?,
+        disabled: row.try_get("disabled")?,
+        password_reset_required: row.try_get("password_reset_required")?,
+        two_factor: row.try_get("totp_enabled")?,
+        entry_count: row.try_get("entry_count")?,
+        attachment_count: row.try_get("attachment_count")?,
+        attachment_bytes: row.try_get("attachment_bytes")?,
+        last_active: last_active.map(|value| value.and_utc().to_rfc3339()),
+    })
+}
+
+async fn list_sqlx(db_pool: &PgPool) -> Result<Vec<UserSummary>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT uuid, username, display_name, created, is_admin, approved, disabled, \
+             password_reset_required, totp_enabled, \
+             (SELECT COUNT(*) FROM journals \
+              WHERE journals.user_uuid = users.uuid AND journals.deleted_at IS NULL) AS entry_count, \
+             (SELECT COUNT(*) FROM attachments WHERE attachments.user_uuid = users.uuid) AS attachment_count, \
+             (SELECT COALESCE(SUM(size), 0)::BIGINT FROM attachments \
+              WHERE attachments.user_uuid = users.uuid) AS attachment_bytes, \
+             (SELECT MAX(last_used) FROM sessions WHERE sessions.user_uuid = users.uuid) AS last_active \
+         FROM users ORDER BY created, username")
+        .fetch_all(db_pool)
+        .await?;
+
+    rows.iter().map(summary_from_row).collect()
+}
+
+// Returns every user, earliest registered first.
+pub async fn list(db_pool: &PgPool) -> Result<Vec<UserSummary>, Error> {
+    match list_sqlx(db_pool).await {
+        Ok(value) => Ok(value),
+        Err(error) => {
+            println!("{error}");
+            Err(ErrorInternalServerError("Database error."))
+        }
+    }
+}
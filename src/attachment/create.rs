@@ -0,0 +1,84 @@
+use actix_web::{Error, error::{ErrorInternalServerError, ErrorPayloadTooLarge}};
+use chrono::Utc;
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+use crate::entry::Entry;
+use super::Attachment;
+use super::storage::Storage;
+
+fn database_error(error: sqlx::Error) -> Error {
+    println!("{error}");
+    ErrorInternalServerError("Database error.")
+}
+
+impl Attachment {
+    /*
+    Stores 'data' as a new attachment of the entry.
+    The user's row is locked while checking the quota, so concurrent uploads cannot exceed it.
+    */
+    pub async fn create(
+        db_pool: &PgPool,
+        storage: &Storage,
+        entry: &Entry,
+        filename: &str,
+        content_type: &str,
+        data: Vec<u8>) -> Result<Self, Error>
+    {
+        let size = data.len() as i64;
+        if size > storage.max_size {
+            return Err(ErrorPayloadTooLarge("Attachment is too large."));
+        }
+
+        let mut transaction = db_pool.begin().await.map_err(database_error)?;
+
+        sqlx::query("SELECT uuid FROM users WHERE uuid = $1 FOR UPDATE")
+            .bind(&entry.user_uuid)
+            .execute(&mut transaction)
+            .await
+            .map_err(database_error)?;
+
+        let usage_row = 
+            sqlx::query("SELECT COALESCE(SUM(size), 0)::BIGINT AS usage FROM attachments WHERE user_uuid = $1")
+            .bind(&entry.user_uuid)
+            .fetch_one(&mut transaction)
+            .await
+            .map_err(database_error)?;
+
+        let usage: i64 = usage_row.try_get("usage").map_err(database_error)?;
+        if usage + size > storage.quota {
+            return Err(ErrorPayloadTooLarge("Attachment quota exceeded."));
+        }
+
+        let uuid = Uuid::new_v4().to_string();
+        let created = Utc::now().naive_utc();
+
+        sqlx::query("INSERT INTO attachments VALUES ($1, $2, $3, $4, $5, $6, $7)")
+            .bind(&uuid)
+            .bind(&entry.uuid)
+            .bind(&entry.user_uuid)
+            .bind(filename)
+            .bind(content_type)
+            .bind(size)
+            .bind(created)
+            .execute(&mut transaction)
+            .await
+            .map_err(database_error)?;
+
+        storage.put(&uuid, data).await?;
+
+        if let Err(error) = transaction.commit().await {
+            storage.delete_all(vec![uuid]).await;
+            return Err(database_error(error));
+        }
+
+        Ok(Attachment {
+            uuid,
+            entry_uuid: entry.uuid.clone(),
+            user_uuid: entry.user_uuid.clone(),
+            filename: filename.to_string(),
+            content_type: content_type.to_string(),
+            size,
+            created: created.and_utc().to_rfc3339(),
+        })
+    }
+}
@@ -0,0 +1,38 @@
+use actix_web::{Error, error::{ErrorInternalServerError, ErrorNotFound}};
+use sqlx::PgPool;
+use super::Attachment;
+use super::storage::Storage;
+
+async fn delete_attachment(
+    db_pool: &PgPool,
+    attachment_uuid: &str,
+    user_uuid: &str) -> Result<(), Error>
+{
+    let delete_result = sqlx::query("DELETE FROM attachments WHERE uuid = $1 AND user_uuid = $2")
+        .bind(attachment_uuid)
+        .bind(user_uuid)
+        .execute(db_pool)
+        .await;
+
+    let query_result = match delete_result {
+        Ok(value) => value,
+        Err(error) => {
+            println!("{error}");
+            return Err(ErrorInternalServerError("Database error."));
+        }
+    };
+
+    if query_result.rows_affected() == 0 {
+        return Err(ErrorNotFound("Attachment not found."));
+    }
+
+    Ok(())
+}
+
+impl Attachment {
+    pub async fn delete(self, db_pool: &PgPool, storage: &Storage) -> Result<Self, Error> {
+        delete_attachment(db_pool, &self.uuid, &self.user_uuid).await?;
+        storage.delete_all(vec![self.uuid.clone()]).await;
+        Ok(self)
+    }
+}
@@ -0,0 +1,82 @@
+use actix_web::{Error, error::{ErrorInternalServerError, ErrorNotFound}};
+use chrono::NaiveDateTime;
+use sqlx::{PgPool, Row, postgres::PgRow};
+use crate::entry::Entry;
+use super::Attachment;
+
+fn attachment_from_row(row: &PgRow) -> Result<Attachment, sqlx::Error> {
+    let created: NaiveDateTime = row.try_get("created")?;
+
+    Ok(Attachment {
+        uuid: row.try_get("uuid")?,
+        entry_uuid: row.try_get("journal_uuid")?,
+        user_uuid: row.try_get("user_uuid")?,
+        filename: row.try_get("filename")?,
+        content_type: row.try_get("content_type")?,
+        size: row.try_get("size")?,
+        created: created.and_utc().to_rfc3339(),
+    })
+}
+
+async fn by_entry_sqlx(
+    db_pool: &PgPool,
+    entry_uuid: &str,
+    user_uuid: &str) -> Result<Vec<Attachment>, sqlx::Error>
+{
+    let rows = 
+        sqlx::query("SELECT * FROM attachments WHERE journal_uuid = $1 AND user_uuid = $2 ORDER BY created")
+        .bind(entry_uuid)
+        .bind(user_uuid)
+        .fetch_all(db_pool)
+        .await?;
+
+    rows.iter().map(attachment_from_row).collect()
+}
+
+async fn by_uuid_sqlx(
+    db_pool: &PgPool,
+    attachment_uuid: &str,
+    entry_uuid: &str,
+    user_uuid: &str) -> Result<Attachment, sqlx::Error>
+{
+    let row = 
+        sqlx::query("SELECT * FROM attachments WHERE uuid = $1 AND journal_uuid = $2 AND user_uuid = $3")
+        .bind(attachment_uuid)
+        .bind(entry_uuid)
+        .bind(user_uuid)
+        .fetch_one(db_pool)
+        .await?;
+
+    attachment_from_row(&row)
+}
+
+impl Attachment {
+    pub async fn by_entry(db_pool: &PgPool, entry: &Entry) -> Result<Vec<Self>, Error> {
+        match by_entry_sqlx(db_pool, &entry.uuid, &entry.user_uuid).await {
+            Ok(value) => Ok(value),
+            Err(error) => {
+                println!("{error}");
+                Err(ErrorInternalServerError("Database error."))
+            }
+        }
+    }
+
+    pub async fn by_uuid(
+        db_pool: &PgPool,
+        attachment_uuid: &str,
+        entry: &Entry) -> Result<Self, Error>
+    {
+        match by_uuid_sqlx(db_pool, attachment_uuid, &entry.uuid, &entry.user_uuid).await {
+            Ok(value) => Ok(value),
+            Err(error) => {
+                match error {
+                    sqlx::Error::RowNotFound => Err(ErrorNotFound("Attachment not found.")),
+                    _ => {
+                        println!("{error}");
+                        Err(ErrorInternalServerError("Database error."))
+                    }
+                }
+            }
+        }
+    }
+}
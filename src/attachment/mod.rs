@@ -0,0 +1,59 @@
+pub mod create;
+pub mod delete;
+pub mod fetch;
+pub mod storage;
+
+pub struct Attachment {
+    pub uuid: String,
+    pub entry_uuid: String,
+    pub user_uuid: String,
+    pub filename: String,
+    pub content_type: String,
+    pub size: i64,
+    pub created: String,
+}
+
+// Image types which browsers never run scripts from. Every other type is served as a download.
+const INLINE_CONTENT_TYPES: [&str; 5] = ["image/png", "image/jpeg", "image/gif", "image/webp", "image/avif"];
+
+impl Attachment {
+    pub fn is_inline(&self) -> bool {
+        let essence = match self.content_type.split_once(';') {
+            Some((essence, _)) => essence,
+            None => &self.content_type
+        };
+
+        INLINE_CONTENT_TYPES.contains(&essence.trim().to_ascii_lowercase().as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Attachment;
+
+    fn attachment(content_type: &str) -> Attachment {
+        Attachment {
+            uuid: String::new(),
+            entry_uuid: String::new(),
+            user_uuid: String::new(),
+            filename: String::new(),
+            content_type: content_type.to_string(),
+            size: 0,
+            created: String::new(),
+        }
+    }
+
+    #[test]
+    fn images_are_inline() {
+        assert!(attachment("image/png").is_inline());
+        assert!(attachment("IMAGE/JPEG").is_inline());
+        assert!(attachment("image/webp; charset=binary").is_inline());
+    }
+
+    #[test]
+    fn active_content_is_not_inline() {
+        for content_type in ["text/html", "image/svg+xml", "application/xhtml+xml", "text/xml", "application/pdf", ""] {
+            assert!(!attachment(content_type).is_inline(), "{content_type:?} should not be inline");
+        }
+    }
+}
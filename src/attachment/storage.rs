@@ -0,0 +1,106 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+use actix_web::{web, Error, error::ErrorInternalServerError};
+
+/*
+Backends store attachment contents by key. Keys are attachment UUIDs.
+Calls may block, so they are run on the blocking thread pool by Storage.
+*/
+pub trait StorageBackend: Send + Sync {
+    fn put(&self, key: &str, data: &[u8]) -> io::Result<()>;
+    fn get(&self, key: &str) -> io::Result<Vec<u8>>;
+    fn delete(&self, key: &str) -> io::Result<()>;
+}
+
+// Stores each attachment as a file named by its key in a directory.
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: PathBuf) -> io::Result<Self> {
+        fs::create_dir_all(&root)?;
+        Ok(LocalStorage { root })
+    }
+}
+
+impl StorageBackend for LocalStorage {
+    fn put(&self, key: &str, data: &[u8]) -> io::Result<()> {
+        fs::write(self.root.join(key), data)
+    }
+
+    fn get(&self, key: &str) -> io::Result<Vec<u8>> {
+        fs::read(self.root.join(key))
+    }
+
+    fn delete(&self, key: &str) -> io::Result<()> {
+        match fs::remove_file(self.root.join(key)) {
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result
+        }
+    }
+}
+
+/*
+Attachment storage shared by the handlers.
+'quota' is the total size of attachments a user can have, and 'max_size' is the size
+limit of a single attachment, both in bytes.
+*/
+#[derive(Clone)]
+pub struct Storage {
+    backend: Arc<dyn StorageBackend>,
+    pub quota: i64,
+    pub max_size: i64,
+}
+
+fn blocking_error(error: impl std::fmt::Display) -> Error {
+    println!("{error}");
+    ErrorInternalServerError("Storage error.")
+}
+
+impl Storage {
+    pub fn new(backend: Arc<dyn StorageBackend>, quota: i64, max_size: i64) -> Self {
+        Storage { backend, quota, max_size }
+    }
+
+    pub async fn put(&self, key: &str, data: Vec<u8>) -> Result<(), Error> {
+        let backend = self.backend.clone();
+        let key = key.to_string();
+
+        match web::block(move || backend.put(&key, &data)).await {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(error)) => Err(blocking_error(error)),
+            Err(error) => Err(blocking_error(error))
+        }
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Vec<u8>, Error> {
+        let backend = self.backend.clone();
+        let key = key.to_string();
+
+        match web::block(move || backend.get(&key)).await {
+            Ok(Ok(data)) => Ok(data),
+            Ok(Err(error)) => Err(blocking_error(error)),
+            Err(error) => Err(blocking_error(error))
+        }
+    }
+
+    // Failures are only logged, as the metadata is already gone by the time this is called.
+    pub async fn delete_all(&self, keys: Vec<String>) {
+        let backend = self.backend.clone();
+
+        let delete_result = web::block(move || {
+            for key in keys {
+                if let Err(error) = backend.delete(&key) {
+                    println!("Failed to delete attachment {key}: {error}");
+                }
+            }
+        }).await;
+
+        if let Err(error) = delete_result {
+            println!("{error}");
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use chrono::Utc;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+// Security events worth keeping for operators.
+#[derive(Clone, Copy)]
+pub enum Event {
+    LoginLockout,
+}
+
+impl Event {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Event::LoginLockout => "login_lockout",
+        }
+    }
+}
+
+async fn record_sqlx(
+    db_pool: &PgPool,
+    event: Event,
+    username: Option<&str>,
+    ip: Option<&str>,
+    detail: &str) -> Result<(), sqlx::Error>
+{
+    sqlx::query(
+        "INSERT INTO audit_events (uuid, created, event, username, ip, detail) VALUES ($1, $2, $3, $4, $5, $6)")
+        .bind(Uuid::new_v4().to_string())
+        .bind(Utc::now().naive_utc())
+        .bind(event.as_str())
+        .bind(username)
+        .bind(ip)
+        .bind(detail)
+        .execute(db_pool)
+        .await?;
+
+    Ok(())
+}
+
+/*
+Records an event, and prints it for the server log as well.
+Failing to record is only printed, since it should not fail the request which caused it.
+*/
+pub async fn record(
+    db_pool: &PgPool,
+    event: Event,
+    username: Option<&str>,
+    ip: Option<&str>,
+    detail: &str)
+{
+    println!(
+        "Audit: {} username={} ip={}: {detail}",
+        event.as_str(),
+        username.unwrap_or("-"),
+        ip.unwrap_or("-"));
+
+    if let Err(error) = record_sqlx(db_pool, event, username, ip, detail).await {
+        println!("Failed to record an audit event: {error}");
+    }
+}
@@ -1,56 +1,81 @@
 use actix_web::{Error, error::{ErrorInternalServerError}};
-use chrono::Utc;
+use chrono::{NaiveDateTime, Utc};
 use sqlx::PgPool;
 use uuid::Uuid;
 use super::Entry;
-use super::utils;
+use super::{tag, utils};
 
 async fn create_entry_sqlx(
     db_pool: &PgPool,
-    timezone_offset: i32,
+    created_naive: NaiveDateTime,
+    timezone: utils::Timezone,
     user_uuid: &str,
     title: &str,
-    body: &str) -> Result<Entry, sqlx::Error>
+    body: &str,
+    tags: Vec<String>) -> Result<Entry, sqlx::Error>
 {
     let entry_uuid = Uuid::new_v4().to_string();
-    let current_timestamp_naive = Utc::now().naive_utc();
-    let insert_result = sqlx::query("INSERT INTO journals VALUES ($1, $2, $3, $4, $5, $6)")
+    let inserted_at = Utc::now().naive_utc();
+
+    let mut transaction = db_pool.begin().await?;
+
+    sqlx::query(
+        "INSERT INTO journals (uuid, user_uuid, created, timezone_offset, timezone, title, body, inserted_at) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)")
         .bind(&entry_uuid)
-        .bind(&user_uuid)
-        .bind(&current_timestamp_naive)
-        .bind(&timezone_offset)
+        .bind(user_uuid)
+        .bind(created_naive)
+        .bind(timezone.offset)
+        .bind(&timezone.name)
         .bind(title)
         .bind(body)
-        .execute(db_pool)
-        .await;
-
-    let current_timestamp_offset = utils::naive_to_offset(current_timestamp_naive, timezone_offset);
-
-    match insert_result {
-        Ok(_) => {
-            let entry = Entry {
-                created: current_timestamp_offset.to_rfc3339(),
-                title: title.to_string(),
-                body: body.to_string(),
-                uuid: entry_uuid,
-                user_uuid: user_uuid.to_string(),
-            };
-
-            Ok(entry)
-        },
-        Err(error) => return Err(error)
-    }
+        .bind(inserted_at)
+        .execute(&mut transaction)
+        .await?;
+
+    tag::set_tags_sqlx(&mut transaction, &entry_uuid, user_uuid, &tags).await?;
+
+    transaction.commit().await?;
+
+    Ok(Entry {
+        created: timezone.render(created_naive).to_rfc3339(),
+        inserted_at: timezone.render(inserted_at).to_rfc3339(),
+        timezone,
+        title: title.to_string(),
+        body: body.to_string(),
+        tags,
+        uuid: entry_uuid,
+        user_uuid: user_uuid.to_string(),
+    })
 }
 
 impl Entry {
+    /*
+    'created_utc' may be in the past for backdated or imported entries.
+    The actual time of creation is kept in 'inserted_at'.
+    */
     pub async fn create(
         db_pool: &PgPool,
-        timezone_offset: i32,
+        created_utc: NaiveDateTime,
+        timezone: utils::Timezone,
         user_uuid: &str,
         title: &str,
-        body: &str) -> Result<Self, Error>
+        body: &str,
+        tags: &[String]) -> Result<Self, Error>
     {
-        match create_entry_sqlx(db_pool, timezone_offset, user_uuid, title, body).await {
+        utils::validate_created(created_utc, &timezone)?;
+        let tags = tag::normalize(tags)?;
+
+        let create_result = create_entry_sqlx(
+            db_pool,
+            created_utc,
+            timezone,
+            user_uuid,
+            title,
+            body,
+            tags).await;
+
+        match create_result {
             Ok(value) => Ok(value),
             Err(error) => {
                 println!("{error}");
@@ -59,4 +84,3 @@ impl Entry {
         }
     }
 }
-
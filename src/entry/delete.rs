@@ -1,13 +1,19 @@
 use actix_web::{Error, error::{ErrorInternalServerError, ErrorNotFound}};
+use chrono::Utc;
 use sqlx::PgPool;
 use super::Entry;
 
+// Entries are moved to the trash first. See trash.rs for permanent deletion.
 async fn delete_entry(
     db_pool: &PgPool,
     entry_uuid: &str,
     user_uuid: &str) -> Result<(), Error>
 {
-    let delete_result = sqlx::query("DELETE FROM journals WHERE uuid = $1 AND user_uuid = $2")
+    let delete_result = 
+        sqlx::query(
+            "UPDATE journals SET deleted_at = $1 \
+             WHERE uuid = $2 AND user_uuid = $3 AND deleted_at IS NULL")
+        .bind(Utc::now().naive_utc())
         .bind(entry_uuid)
         .bind(user_uuid)
         .execute(db_pool)
@@ -2,34 +2,44 @@ use actix_web::{Error, error::{ErrorInternalServerError, ErrorNotFound}};
 use chrono::NaiveDateTime;
 use sqlx::{PgPool, Row};
 use super::Entry;
-use super::utils;
+use super::{tag, utils};
 
 async fn by_uuid_sqlx(
     db_pool: &PgPool,
     entry_uuid: &str,
     user_uuid: &str) -> Result<Entry, sqlx::Error>
 {
-    let entry_row = 
-        sqlx::query("SELECT * FROM journals WHERE uuid = $1 AND user_uuid = $2")
+    let sql = format!(
+        "SELECT *, {} FROM journals WHERE uuid = $1 AND user_uuid = $2 AND deleted_at IS NULL",
+        tag::TAGS_COLUMN);
+
+    let entry_row = sqlx::query(&sql)
         .bind(entry_uuid)
         .bind(user_uuid)
         .fetch_one(db_pool)
         .await?;
 
+    let timezone = utils::Timezone::from_row(&entry_row)?;
     let created = {
         let created_utc_naive: NaiveDateTime = entry_row.try_get("created")?;
-        let timezone_offset_minute: i32 = entry_row.try_get("timezone_offset")?;
-
-        utils::naive_to_offset(created_utc_naive, timezone_offset_minute)
+        timezone.render(created_utc_naive)
+    };
+    let inserted_at = {
+        let inserted_at_utc_naive: NaiveDateTime = entry_row.try_get("inserted_at")?;
+        timezone.render(inserted_at_utc_naive)
     };
 
     let title: String = entry_row.try_get("title")?;
     let body: String = entry_row.try_get("body")?;
+    let tags: Vec<String> = entry_row.try_get("tags")?;
 
     Ok(Entry {
         created: created.to_rfc3339(),
-        title: title,
-        body: body,
+        inserted_at: inserted_at.to_rfc3339(),
+        timezone,
+        title,
+        body,
+        tags,
         uuid: entry_uuid.to_string(),
         user_uuid: user_uuid.to_string(),
     })
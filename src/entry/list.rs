@@ -1,39 +1,305 @@
-use actix_web::{Error, error::ErrorInternalServerError};
-use sqlx::{PgPool, Row};
+use actix_web::{Error, error::{ErrorBadRequest, ErrorInternalServerError}};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime};
+use sqlx::{PgPool, Row, postgres::PgRow};
 use super::Entry;
+use super::{tag, utils};
+use super::tag::{TagFilter, TagMatch};
 
-async fn uuids_by_user_sqlx(
+pub const DEFAULT_PAGE_LIMIT: i64 = 20;
+pub const MAX_PAGE_LIMIT: i64 = 100;
+
+/*
+Cursors point at a single entry by its (created, uuid) pair, which is unique and
+matches the ordering of the listing. They are encoded as "{created}_{uuid}",
+where 'created' is the UTC timestamp in microseconds.
+*/
+pub struct Cursor {
+    created: NaiveDateTime,
+    uuid: String,
+}
+
+impl Cursor {
+    pub fn parse(value: &str) -> Result<Self, Error> {
+        let (created, uuid) = match value.split_once('_') {
+            Some(value) => value,
+            None => return Err(ErrorBadRequest("Malformed cursor."))
+        };
+
+        let created_micros = match created.parse::<i64>() {
+            Ok(value) => value,
+            Err(_) => return Err(ErrorBadRequest("Malformed cursor."))
+        };
+
+        let created = match DateTime::from_timestamp_micros(created_micros) {
+            Some(value) => value.naive_utc(),
+            None => return Err(ErrorBadRequest("Malformed cursor."))
+        };
+
+        Ok(Cursor {
+            created,
+            uuid: uuid.to_string(),
+        })
+    }
+
+    // Points before every entry. Walk with Direction::After to list all entries from the oldest.
+    pub fn beginning() -> Self {
+        Cursor {
+            created: NaiveDate::from_ymd_opt(1, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap(),
+            uuid: String::new(),
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        format!("{}_{}", self.created.and_utc().timestamp_micros(), self.uuid)
+    }
+}
+
+pub enum Direction {
+    Before,
+    After,
+}
+
+pub struct Page<T> {
+    pub entries: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+// Body is replaced by its first 200 characters and some statistics.
+pub struct Summary {
+    pub created: String,
+    pub title: String,
+    pub excerpt: String,
+    pub body_length: i32,
+    pub word_count: i32,
+    pub tags: Vec<String>,
+    pub uuid: String,
+}
+
+const FULL_COLUMNS: &str = "uuid, user_uuid, created, inserted_at, timezone_offset, timezone, title, body";
+
+const SUMMARY_COLUMNS: &str = 
+    "uuid, created, timezone_offset, timezone, title, \
+     CASE WHEN char_length(body) > 200 \
+         THEN rtrim(left(body, 200)) || '…' ELSE body END AS excerpt, \
+     char_length(body) AS body_length, \
+     COALESCE(array_length(regexp_split_to_array(NULLIF(btrim(body, E' \\t\\r\\n'), ''), '\\s+'), 1), 0) \
+         AS word_count";
+
+fn cursor_from_row(row: &PgRow) -> Result<(Cursor, DateTime<FixedOffset>), sqlx::Error> {
+    let created_utc_naive: NaiveDateTime = row.try_get("created")?;
+    let timezone = utils::Timezone::from_row(row)?;
+
+    let cursor = Cursor {
+        created: created_utc_naive,
+        uuid: row.try_get("uuid")?,
+    };
+
+    Ok((cursor, timezone.render(created_utc_naive)))
+}
+
+fn entry_from_row(row: &PgRow) -> Result<(Entry, Cursor), sqlx::Error> {
+    let (cursor, created) = cursor_from_row(row)?;
+
+    let inserted_at_utc_naive: NaiveDateTime = row.try_get("inserted_at")?;
+    let timezone = utils::Timezone::from_row(row)?;
+
+    let entry = Entry {
+        created: created.to_rfc3339(),
+        inserted_at: timezone.render(inserted_at_utc_naive).to_rfc3339(),
+        timezone,
+        title: row.try_get("title")?,
+        body: row.try_get("body")?,
+        tags: row.try_get("tags")?,
+        uuid: cursor.uuid.clone(),
+        user_uuid: row.try_get("user_uuid")?,
+    };
+
+    Ok((entry, cursor))
+}
+
+fn summary_from_row(row: &PgRow) -> Result<(Summary, Cursor), sqlx::Error> {
+    let (cursor, created) = cursor_from_row(row)?;
+
+    let summary = Summary {
+        created: created.to_rfc3339(),
+        title: row.try_get("title")?,
+        excerpt: row.try_get("excerpt")?,
+        body_length: row.try_get("body_length")?,
+        word_count: row.try_get("word_count")?,
+        tags: row.try_get("tags")?,
+        uuid: cursor.uuid.clone(),
+    };
+
+    Ok((summary, cursor))
+}
+
+async fn page_by_user_sqlx<T>(
     db_pool: &PgPool,
-    user_uuid: &str) -> Result<Vec<String>, sqlx::Error>
+    columns: &str,
+    from_row: fn(&PgRow) -> Result<(T, Cursor), sqlx::Error>,
+    user_uuid: &str,
+    limit: i64,
+    cursor: Option<(Direction, Cursor)>,
+    tag_filter: Option<&TagFilter>) -> Result<Page<T>, sqlx::Error>
 {
-    let rows = 
-        sqlx::query("SELECT uuid FROM journals WHERE user_uuid = $1 ORDER BY created DESC")
+    let mut conditions = vec![
+        "user_uuid = $1".to_string(),
+        "deleted_at IS NULL".to_string(),
+    ];
+    let mut order = "DESC";
+
+    if let Some((direction, _)) = &cursor {
+        let comparison = match direction {
+            Direction::Before => "<",
+            Direction::After => ">"
+        };
+
+        conditions.push(format!("(created, uuid) {comparison} ($3, $4)"));
+        if let Direction::After = direction {
+            order = "ASC";
+        }
+    }
+
+    if let Some(tag_filter) = tag_filter {
+        let names = format!("${}", if cursor.is_some() { 5 } else { 3 });
+        let matching = format!(
+            "SELECT COUNT(*) FROM entry_tags et JOIN tags t ON t.uuid = et.tag_uuid \
+             WHERE et.journal_uuid = journals.uuid AND t.name = ANY({names})");
+
+        conditions.push(match tag_filter.mode {
+            TagMatch::All => format!("({matching}) = cardinality({names}::TEXT[])"),
+            TagMatch::Any => format!("({matching}) > 0")
+        });
+    }
+
+    // One extra row is fetched to tell whether another page exists.
+    let sql = format!(
+        "SELECT {columns}, {} FROM journals WHERE {} \
+         ORDER BY created {order}, uuid {order} LIMIT $2",
+        tag::TAGS_COLUMN,
+        conditions.join(" AND "));
+
+    let mut query = sqlx::query(&sql)
         .bind(user_uuid)
-        .fetch_all(db_pool)
-        .await?;
+        .bind(limit + 1);
 
-    let uuid_results: Vec<Result<String, sqlx::Error>> 
-        = rows.iter().map(|row| row.try_get("uuid")).collect();
+    if let Some((_, cursor)) = cursor {
+        query = query.bind(cursor.created).bind(cursor.uuid);
+    }
 
-    let uuids_result: Result<Vec<String>, sqlx::Error> 
-        = uuid_results.into_iter().collect();
+    if let Some(tag_filter) = tag_filter {
+        query = query.bind(&tag_filter.names);
+    }
 
-    let uuids: Vec<String> = uuids_result?;
+    let rows = query.fetch_all(db_pool).await?;
 
-    Ok(uuids)
+    let has_more = rows.len() as i64 > limit;
+
+    let mut entries = Vec::new();
+    let mut last_cursor = None;
+    for row in rows.iter().take(limit as usize) {
+        let (entry, cursor) = from_row(row)?;
+        entries.push(entry);
+        last_cursor = Some(cursor);
+    }
+
+    let next_cursor = match has_more {
+        true => last_cursor.map(|cursor| cursor.encode()),
+        false => None
+    };
+
+    Ok(Page { entries, next_cursor })
+}
+
+async fn page_by_user<T>(
+    db_pool: &PgPool,
+    columns: &str,
+    from_row: fn(&PgRow) -> Result<(T, Cursor), sqlx::Error>,
+    user_uuid: &str,
+    limit: i64,
+    cursor: Option<(Direction, Cursor)>,
+    tag_filter: Option<&TagFilter>) -> Result<Page<T>, Error>
+{
+    let after = matches!(cursor, Some((Direction::After, _)));
+
+    match page_by_user_sqlx(db_pool, columns, from_row, user_uuid, limit, cursor, tag_filter).await {
+        Ok(mut page) => {
+            if after {
+                page.entries.reverse();
+            }
+            Ok(page)
+        },
+        Err(error) => {
+            println!("{error}");
+            Err(ErrorInternalServerError("Database error."))
+        }
+    }
 }
 
 impl Entry {
-    pub async fn uuids_by_user(
+    /*
+    Returns at most 'limit' entries of the user, recent first.
+    With Direction::After, entries are walked from older to newer instead
+    and 'next_cursor' continues in that direction.
+    When 'tag_filter' is given, only entries with all or any of the tags are listed.
+    */
+    pub async fn page_by_user(
         db_pool: &PgPool,
-        user_uuid: &str) -> Result<Vec<String>, Error> 
+        user_uuid: &str,
+        limit: i64,
+        cursor: Option<(Direction, Cursor)>,
+        tag_filter: Option<&TagFilter>) -> Result<Page<Self>, Error>
     {
-        match uuids_by_user_sqlx(db_pool, user_uuid).await {
-            Ok(value) => Ok(value),
-            Err(error) => {
-                println!("{error}");
-                Err(ErrorInternalServerError("Database error."))
-            }
+        page_by_user(db_pool, FULL_COLUMNS, entry_from_row, user_uuid, limit, cursor, tag_filter).await
+    }
+
+    // Same as page_by_user(), but without loading full bodies.
+    pub async fn summary_page_by_user(
+        db_pool: &PgPool,
+        user_uuid: &str,
+        limit: i64,
+        cursor: Option<(Direction, Cursor)>,
+        tag_filter: Option<&TagFilter>) -> Result<Page<Summary>, Error>
+    {
+        page_by_user(db_pool, SUMMARY_COLUMNS, summary_from_row, user_uuid, limit, cursor, tag_filter).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cursor;
+
+    const UUID: &str = "7a1f0c2e-2d0b-4c55-9f5e-3c1b2a4d5e6f";
+
+    #[test]
+    fn cursor_round_trip() {
+        let encoded = format!("1700000000123456_{UUID}");
+        let cursor = Cursor::parse(&encoded).unwrap();
+
+        assert_eq!(cursor.created.and_utc().timestamp_micros(), 1700000000123456);
+        assert_eq!(cursor.uuid, UUID);
+        assert_eq!(cursor.encode(), encoded);
+    }
+
+    #[test]
+    fn cursor_negative_timestamp() {
+        let encoded = format!("-1000_{UUID}");
+        assert_eq!(Cursor::parse(&encoded).unwrap().encode(), encoded);
+    }
+
+    #[test]
+    fn cursor_beginning_round_trip() {
+        let encoded = Cursor::beginning().encode();
+        let cursor = Cursor::parse(&encoded).unwrap();
+
+        assert_eq!(cursor.created, Cursor::beginning().created);
+        assert_eq!(cursor.uuid, "");
+    }
+
+    #[test]
+    fn cursor_malformed() {
+        for value in ["", "1700000000", "abc_def", "_", &format!("1.5_{UUID}"), &format!("{}_{UUID}", i64::MAX)] {
+            assert!(Cursor::parse(value).is_err(), "{value:?} should be rejected");
         }
     }
 }
@@ -2,13 +2,20 @@ pub mod create;
 pub mod delete;
 pub mod fetch;
 pub mod list;
+pub mod revision;
+pub mod search;
+pub mod tag;
+pub mod trash;
 pub mod update;
-mod utils;
+pub mod utils;
 
 pub struct Entry {
     pub created: String,
+    pub inserted_at: String,
+    pub timezone: utils::Timezone,
     pub title: String,
     pub body: String,
+    pub tags: Vec<String>,
     pub uuid: String,
     pub user_uuid: String,
 }
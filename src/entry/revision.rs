@@ -0,0 +1,135 @@
+use actix_web::{Error, error::{ErrorInternalServerError, ErrorNotFound}};
+use chrono::NaiveDateTime;
+use similar::{ChangeTag, TextDiff};
+use sqlx::{PgPool, Row, postgres::PgRow};
+use super::Entry;
+use super::utils;
+
+/*
+A revision is a previous content of an entry, recorded each time the entry is updated.
+'created' is when the content was replaced, in the entry's timezone.
+*/
+pub struct Revision {
+    pub uuid: String,
+    pub created: String,
+    pub title: String,
+    pub body: String,
+}
+
+pub struct DiffChunk {
+    pub tag: &'static str,
+    pub text: String,
+}
+
+fn revision_from_row(row: &PgRow) -> Result<Revision, sqlx::Error> {
+    let created_utc_naive: NaiveDateTime = row.try_get("created")?;
+    let timezone = utils::Timezone::from_row(row)?;
+
+    Ok(Revision {
+        uuid: row.try_get("uuid")?,
+        created: timezone.render(created_utc_naive).to_rfc3339(),
+        title: row.try_get("title")?,
+        body: row.try_get("body")?,
+    })
+}
+
+async fn revisions_sqlx(
+    db_pool: &PgPool,
+    entry_uuid: &str,
+    user_uuid: &str) -> Result<Vec<Revision>, sqlx::Error>
+{
+    let rows = sqlx::query(
+        "SELECT r.uuid, r.created, j.timezone_offset, j.timezone, r.title, r.body \
+         FROM journal_revisions r JOIN journals j ON j.uuid = r.journal_uuid \
+         WHERE r.journal_uuid = $1 AND j.user_uuid = $2 AND j.deleted_at IS NULL \
+         ORDER BY r.created DESC")
+        .bind(entry_uuid)
+        .bind(user_uuid)
+        .fetch_all(db_pool)
+        .await?;
+
+    rows.iter().map(revision_from_row).collect()
+}
+
+async fn revision_sqlx(
+    db_pool: &PgPool,
+    revision_uuid: &str,
+    entry_uuid: &str,
+    user_uuid: &str) -> Result<Revision, sqlx::Error>
+{
+    let row = sqlx::query(
+        "SELECT r.uuid, r.created, j.timezone_offset, j.timezone, r.title, r.body \
+         FROM journal_revisions r JOIN journals j ON j.uuid = r.journal_uuid \
+         WHERE r.uuid = $1 AND r.journal_uuid = $2 AND j.user_uuid = $3 AND j.deleted_at IS NULL")
+        .bind(revision_uuid)
+        .bind(entry_uuid)
+        .bind(user_uuid)
+        .fetch_one(db_pool)
+        .await?;
+
+    revision_from_row(&row)
+}
+
+// Line based diff from 'old' to 'new'. Adjacent lines with the same tag are merged.
+pub fn diff(old: &str, new: &str) -> Vec<DiffChunk> {
+    let mut chunks: Vec<DiffChunk> = Vec::new();
+
+    for change in TextDiff::from_lines(old, new).iter_all_changes() {
+        let tag = match change.tag() {
+            ChangeTag::Equal => "equal",
+            ChangeTag::Delete => "delete",
+            ChangeTag::Insert => "insert",
+        };
+
+        match chunks.last_mut() {
+            Some(chunk) if chunk.tag == tag => chunk.text.push_str(change.value()),
+            _ => chunks.push(DiffChunk { tag, text: change.value().to_string() })
+        }
+    }
+
+    chunks
+}
+
+impl Entry {
+    // Returns previous contents of this entry, recent first.
+    pub async fn revisions(&self, db_pool: &PgPool) -> Result<Vec<Revision>, Error> {
+        match revisions_sqlx(db_pool, &self.uuid, &self.user_uuid).await {
+            Ok(value) => Ok(value),
+            Err(error) => {
+                println!("{error}");
+                Err(ErrorInternalServerError("Database error."))
+            }
+        }
+    }
+
+    pub async fn revision(
+        &self,
+        db_pool: &PgPool,
+        revision_uuid: &str) -> Result<Revision, Error>
+    {
+        match revision_sqlx(db_pool, revision_uuid, &self.uuid, &self.user_uuid).await {
+            Ok(value) => Ok(value),
+            Err(error) => {
+                match error {
+                    sqlx::Error::RowNotFound => Err(ErrorNotFound("Revision not found.")),
+                    _ => {
+                        println!("{error}");
+                        Err(ErrorInternalServerError("Database error."))
+                    }
+                }
+            }
+        }
+    }
+
+    /*
+    Replaces content of this entry with the revision.
+    Like any other update, the current content is kept as a new revision.
+    */
+    pub async fn restore(
+        self,
+        db_pool: &PgPool,
+        revision: &Revision) -> Result<Self, Error>
+    {
+        self.update(db_pool, &revision.title, &revision.body, None, None).await
+    }
+}
@@ -0,0 +1,124 @@
+use actix_web::{Error, error::{ErrorBadRequest, ErrorInternalServerError}};
+use chrono::NaiveDateTime;
+use sqlx::{PgPool, Row};
+use super::Entry;
+use super::utils;
+
+pub struct SearchResult {
+    pub created: String,
+    pub title: String,
+    pub title_highlight: String,
+    pub snippet: String,
+    pub rank: f32,
+    pub uuid: String,
+}
+
+/*
+ts_headline marks matches with these private use characters, which are stripped
+from the text beforehand. The result is HTML escaped and only then are the
+markers turned into <mark> tags, so entry content never reaches clients as markup.
+*/
+const START_SEL: char = '\u{E000}';
+const STOP_SEL: char = '\u{E001}';
+
+fn highlight(text: &str) -> String {
+    let mut highlighted = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            START_SEL => highlighted.push_str("<mark>"),
+            STOP_SEL => highlighted.push_str("</mark>"),
+            '&' => highlighted.push_str("&amp;"),
+            '<' => highlighted.push_str("&lt;"),
+            '>' => highlighted.push_str("&gt;"),
+            '"' => highlighted.push_str("&quot;"),
+            '\'' => highlighted.push_str("&#39;"),
+            c => highlighted.push(c),
+        }
+    }
+    highlighted
+}
+
+async fn search_sqlx(
+    db_pool: &PgPool,
+    user_uuid: &str,
+    query: &str,
+    limit: i64,
+    offset: i64) -> Result<Vec<SearchResult>, sqlx::Error>
+{
+    let rows = sqlx::query(
+        "SELECT uuid, created, timezone_offset, timezone, title, \
+             ts_rank(search_vector, query) AS rank, \
+             ts_headline('simple', replace(replace(title, $5, ''), $6, ''), query, \
+                 'StartSel=' || $5 || ', StopSel=' || $6 || ', HighlightAll=true') AS title_highlight, \
+             ts_headline('simple', replace(replace(body, $5, ''), $6, ''), query, \
+                 'StartSel=' || $5 || ', StopSel=' || $6 || ', MaxWords=35, MinWords=15, MaxFragments=3') AS snippet \
+         FROM journals, websearch_to_tsquery('simple', $2) query \
+         WHERE user_uuid = $1 AND deleted_at IS NULL AND search_vector @@ query \
+         ORDER BY rank DESC, created DESC LIMIT $3 OFFSET $4")
+        .bind(user_uuid)
+        .bind(query)
+        .bind(limit)
+        .bind(offset)
+        .bind(START_SEL.to_string())
+        .bind(STOP_SEL.to_string())
+        .fetch_all(db_pool)
+        .await?;
+
+    let mut results = Vec::new();
+    for row in rows.iter() {
+        let created_utc_naive: NaiveDateTime = row.try_get("created")?;
+        let timezone = utils::Timezone::from_row(row)?;
+
+        results.push(SearchResult {
+            created: timezone.render(created_utc_naive).to_rfc3339(),
+            title: row.try_get("title")?,
+            title_highlight: highlight(row.try_get("title_highlight")?),
+            snippet: highlight(row.try_get("snippet")?),
+            rank: row.try_get("rank")?,
+            uuid: row.try_get("uuid")?,
+        });
+    }
+
+    Ok(results)
+}
+
+impl Entry {
+    // Returns entries of the user matching the query, most relevant first.
+    pub async fn search(
+        db_pool: &PgPool,
+        user_uuid: &str,
+        query: &str,
+        limit: i64,
+        offset: i64) -> Result<Vec<SearchResult>, Error>
+    {
+        if query.trim().is_empty() {
+            return Err(ErrorBadRequest("Search query is empty."));
+        }
+
+        match search_sqlx(db_pool, user_uuid, query, limit, offset).await {
+            Ok(value) => Ok(value),
+            Err(error) => {
+                println!("{error}");
+                Err(ErrorInternalServerError("Database error."))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{START_SEL, STOP_SEL, highlight};
+
+    #[test]
+    fn highlight_escapes_markup() {
+        let text = format!("<script>alert('x')</script> {START_SEL}beach{STOP_SEL} & \"sea\"");
+        assert_eq!(
+            highlight(&text),
+            "&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt; <mark>beach</mark> &amp; &quot;sea&quot;");
+    }
+
+    #[test]
+    fn highlight_plain_text() {
+        assert_eq!(highlight("Went to the beach."), "Went to the beach.");
+    }
+}
@@ -0,0 +1,177 @@
+use actix_web::{Error, error::{ErrorBadRequest, ErrorInternalServerError}};
+use sqlx::{PgPool, Postgres, Row, Transaction};
+use uuid::Uuid;
+use super::Entry;
+
+pub const MAX_TAG_LENGTH: usize = 64;
+pub const MAX_TAGS_PER_ENTRY: usize = 20;
+
+// Selects names of the tags on each row of 'journals' as a 'tags' column.
+pub const TAGS_COLUMN: &str = 
+    "ARRAY(SELECT t.name FROM entry_tags et JOIN tags t ON t.uuid = et.tag_uuid \
+           WHERE et.journal_uuid = journals.uuid ORDER BY t.name) AS tags";
+
+pub enum TagMatch {
+    All,
+    Any,
+}
+
+pub struct TagFilter {
+    pub names: Vec<String>,
+    pub mode: TagMatch,
+}
+
+pub struct TagUsage {
+    pub name: String,
+    pub count: i64,
+}
+
+// Trims surrounding whitespaces and removes duplicates.
+pub fn normalize(names: &[String]) -> Result<Vec<String>, Error> {
+    let mut normalized: Vec<String> = Vec::new();
+
+    for name in names {
+        let name = name.trim();
+
+        if name.is_empty() {
+            return Err(ErrorBadRequest("Tag name is empty."));
+        }
+
+        if name.contains(',') {
+            return Err(ErrorBadRequest("Tag name cannot contain commas."));
+        }
+
+        if name.chars().count() > MAX_TAG_LENGTH {
+            return Err(ErrorBadRequest("Tag name is too long."));
+        }
+
+        if !normalized.iter().any(|existing| existing == name) {
+            normalized.push(name.to_string());
+        }
+    }
+
+    if normalized.len() > MAX_TAGS_PER_ENTRY {
+        return Err(ErrorBadRequest("Too many tags."));
+    }
+
+    Ok(normalized)
+}
+
+// Replaces tags of the entry. Tags that do not exist yet are created.
+pub async fn set_tags_sqlx(
+    transaction: &mut Transaction<'_, Postgres>,
+    entry_uuid: &str,
+    user_uuid: &str,
+    names: &[String]) -> Result<(), sqlx::Error>
+{
+    for name in names {
+        sqlx::query("INSERT INTO tags VALUES ($1, $2, $3) ON CONFLICT (user_uuid, name) DO NOTHING")
+            .bind(Uuid::new_v4().to_string())
+            .bind(user_uuid)
+            .bind(name)
+            .execute(&mut *transaction)
+            .await?;
+    }
+
+    sqlx::query("DELETE FROM entry_tags WHERE journal_uuid = $1")
+        .bind(entry_uuid)
+        .execute(&mut *transaction)
+        .await?;
+
+    sqlx::query(
+        "INSERT INTO entry_tags SELECT $1, uuid FROM tags WHERE user_uuid = $2 AND name = ANY($3)")
+        .bind(entry_uuid)
+        .bind(user_uuid)
+        .bind(names)
+        .execute(&mut *transaction)
+        .await?;
+
+    sqlx::query(
+        "DELETE FROM tags WHERE user_uuid = $1 \
+         AND NOT EXISTS (SELECT 1 FROM entry_tags WHERE tag_uuid = tags.uuid)")
+        .bind(user_uuid)
+        .execute(&mut *transaction)
+        .await?;
+
+    Ok(())
+}
+
+async fn usages_by_user_sqlx(
+    db_pool: &PgPool,
+    user_uuid: &str) -> Result<Vec<TagUsage>, sqlx::Error>
+{
+    let rows = sqlx::query(
+        "SELECT t.name, COUNT(*) AS count FROM tags t \
+         JOIN entry_tags et ON et.tag_uuid = t.uuid \
+         JOIN journals j ON j.uuid = et.journal_uuid AND j.deleted_at IS NULL \
+         WHERE t.user_uuid = $1 \
+         GROUP BY t.name ORDER BY count DESC, t.name")
+        .bind(user_uuid)
+        .fetch_all(db_pool)
+        .await?;
+
+    let mut usages = Vec::new();
+    for row in rows.iter() {
+        usages.push(TagUsage {
+            name: row.try_get("name")?,
+            count: row.try_get("count")?,
+        });
+    }
+
+    Ok(usages)
+}
+
+impl Entry {
+    // Returns tags of the user with number of entries using each, most used first.
+    pub async fn tag_usages_by_user(
+        db_pool: &PgPool,
+        user_uuid: &str) -> Result<Vec<TagUsage>, Error>
+    {
+        match usages_by_user_sqlx(db_pool, user_uuid).await {
+            Ok(value) => Ok(value),
+            Err(error) => {
+                println!("{error}");
+                Err(ErrorInternalServerError("Database error."))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MAX_TAG_LENGTH, MAX_TAGS_PER_ENTRY, normalize};
+
+    fn names(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn normalize_trims_and_deduplicates() {
+        let normalized = normalize(&names(&[" travel ", "family", "travel", "Travel"])).unwrap();
+        assert_eq!(normalized, names(&["travel", "family", "Travel"]));
+    }
+
+    #[test]
+    fn normalize_empty_list() {
+        assert!(normalize(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn normalize_rejects_invalid_names() {
+        assert!(normalize(&names(&["  "])).is_err());
+        assert!(normalize(&names(&["a,b"])).is_err());
+        assert!(normalize(&names(&[&"é".repeat(MAX_TAG_LENGTH + 1)])).is_err());
+        assert!(normalize(&names(&[&"é".repeat(MAX_TAG_LENGTH)])).is_ok());
+    }
+
+    #[test]
+    fn normalize_limits_tag_count() {
+        let tags: Vec<String> = (0..=MAX_TAGS_PER_ENTRY).map(|i| format!("tag{i}")).collect();
+        assert!(normalize(&tags).is_err());
+        assert!(normalize(&tags[..MAX_TAGS_PER_ENTRY]).is_ok());
+
+        let mut duplicates = tags[..MAX_TAGS_PER_ENTRY].to_vec();
+        duplicates.push("tag0".to_string());
+        assert!(normalize(&duplicates).is_ok());
+    }
+}
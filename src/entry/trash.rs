@@ -0,0 +1,231 @@
+use actix_web::{Error, error::{ErrorInternalServerError, ErrorNotFound}};
+use chrono::{Duration, NaiveDateTime, Utc};
+use sqlx::{PgPool, Postgres, Row, postgres::{PgArguments, PgRow}, query::Query};
+use crate::attachment::storage::Storage;
+use super::Entry;
+use super::{tag, utils};
+
+pub struct TrashedEntry {
+    pub entry: Entry,
+    pub deleted_at: String,
+}
+
+fn trashed_from_row(row: &PgRow) -> Result<TrashedEntry, sqlx::Error> {
+    let created_utc_naive: NaiveDateTime = row.try_get("created")?;
+    let inserted_at_utc_naive: NaiveDateTime = row.try_get("inserted_at")?;
+    let deleted_at_utc_naive: NaiveDateTime = row.try_get("deleted_at")?;
+    let timezone = utils::Timezone::from_row(row)?;
+    let deleted_at = timezone.render(deleted_at_utc_naive).to_rfc3339();
+
+    let entry = Entry {
+        created: timezone.render(created_utc_naive).to_rfc3339(),
+        inserted_at: timezone.render(inserted_at_utc_naive).to_rfc3339(),
+        timezone,
+        title: row.try_get("title")?,
+        body: row.try_get("body")?,
+        tags: row.try_get("tags")?,
+        uuid: row.try_get("uuid")?,
+        user_uuid: row.try_get("user_uuid")?,
+    };
+
+    Ok(TrashedEntry {
+        entry,
+        deleted_at,
+    })
+}
+
+async fn trashed_by_user_sqlx(
+    db_pool: &PgPool,
+    user_uuid: &str) -> Result<Vec<TrashedEntry>, sqlx::Error>
+{
+    let sql = format!(
+        "SELECT *, {} FROM journals WHERE user_uuid = $1 AND deleted_at IS NOT NULL \
+         ORDER BY deleted_at DESC",
+        tag::TAGS_COLUMN);
+
+    let rows = sqlx::query(&sql)
+        .bind(user_uuid)
+        .fetch_all(db_pool)
+        .await?;
+
+    rows.iter().map(trashed_from_row).collect()
+}
+
+async fn trashed_by_uuid_sqlx(
+    db_pool: &PgPool,
+    entry_uuid: &str,
+    user_uuid: &str) -> Result<TrashedEntry, sqlx::Error>
+{
+    let sql = format!(
+        "SELECT *, {} FROM journals WHERE uuid = $1 AND user_uuid = $2 AND deleted_at IS NOT NULL",
+        tag::TAGS_COLUMN);
+
+    let row = sqlx::query(&sql)
+        .bind(entry_uuid)
+        .bind(user_uuid)
+        .fetch_one(db_pool)
+        .await?;
+
+    trashed_from_row(&row)
+}
+
+async fn recover_entry(
+    db_pool: &PgPool,
+    entry_uuid: &str,
+    user_uuid: &str) -> Result<(), Error>
+{
+    let update_result = sqlx::query(
+        "UPDATE journals SET deleted_at = NULL \
+         WHERE uuid = $1 AND user_uuid = $2 AND deleted_at IS NOT NULL")
+        .bind(entry_uuid)
+        .bind(user_uuid)
+        .execute(db_pool)
+        .await;
+
+    let query_result = match update_result {
+        Ok(value) => value,
+        Err(error) => {
+            println!("{error}");
+            return Err(ErrorInternalServerError("Database error."));
+        }
+    };
+
+    if query_result.rows_affected() == 0 {
+        return Err(ErrorNotFound("Entry not found."));
+    }
+
+    Ok(())
+}
+
+enum PurgeTarget<'a> {
+    Entry { entry_uuid: &'a str, user_uuid: &'a str },
+    DeletedBefore(NaiveDateTime),
+}
+
+impl<'a> PurgeTarget<'a> {
+    fn condition(&self) -> &'static str {
+        match self {
+            PurgeTarget::Entry { .. } => "uuid = $1 AND user_uuid = $2 AND deleted_at IS NOT NULL",
+            PurgeTarget::DeletedBefore(_) => "deleted_at < $1"
+        }
+    }
+
+    fn bind(&self, query: Query<'a, Postgres, PgArguments>) -> Query<'a, Postgres, PgArguments> {
+        match self {
+            PurgeTarget::Entry { entry_uuid, user_uuid } => query.bind(*entry_uuid).bind(*user_uuid),
+            PurgeTarget::DeletedBefore(timestamp) => query.bind(*timestamp)
+        }
+    }
+}
+
+/*
+Deletes trashed entries along with their attachments.
+Returns number of the entries deleted and keys of the attachments to be removed from storage.
+*/
+async fn purge_sqlx(
+    db_pool: &PgPool,
+    target: PurgeTarget<'_>) -> Result<(u64, Vec<String>), sqlx::Error>
+{
+    let mut transaction = db_pool.begin().await?;
+
+    let attachments_sql = format!(
+        "DELETE FROM attachments WHERE journal_uuid IN (SELECT uuid FROM journals WHERE {}) \
+         RETURNING uuid",
+        target.condition());
+
+    let attachment_rows = target.bind(sqlx::query(&attachments_sql))
+        .fetch_all(&mut transaction)
+        .await?;
+
+    let attachment_keys = attachment_rows.iter()
+        .map(|row| row.try_get("uuid"))
+        .collect::<Result<Vec<String>, sqlx::Error>>()?;
+
+    let journals_sql = format!("DELETE FROM journals WHERE {}", target.condition());
+    let query_result = target.bind(sqlx::query(&journals_sql))
+        .execute(&mut transaction)
+        .await?;
+
+    transaction.commit().await?;
+    Ok((query_result.rows_affected(), attachment_keys))
+}
+
+// Permanently deletes entries that have been in the trash for longer than 'retention'.
+pub async fn purge_expired(
+    db_pool: &PgPool,
+    storage: &Storage,
+    retention: Duration) -> Result<u64, sqlx::Error>
+{
+    let cutoff = Utc::now().naive_utc() - retention;
+    let (count, attachment_keys) = purge_sqlx(db_pool, PurgeTarget::DeletedBefore(cutoff)).await?;
+
+    storage.delete_all(attachment_keys).await;
+    Ok(count)
+}
+
+impl Entry {
+    // Returns entries in the trash of the user, recently deleted first.
+    pub async fn trashed_by_user(
+        db_pool: &PgPool,
+        user_uuid: &str) -> Result<Vec<TrashedEntry>, Error>
+    {
+        match trashed_by_user_sqlx(db_pool, user_uuid).await {
+            Ok(value) => Ok(value),
+            Err(error) => {
+                println!("{error}");
+                Err(ErrorInternalServerError("Database error."))
+            }
+        }
+    }
+
+    pub async fn trashed_by_uuid(
+        db_pool: &PgPool,
+        entry_uuid: &str,
+        user_uuid: &str) -> Result<TrashedEntry, Error>
+    {
+        match trashed_by_uuid_sqlx(db_pool, entry_uuid, user_uuid).await {
+            Ok(value) => Ok(value),
+            Err(error) => {
+                match error {
+                    sqlx::Error::RowNotFound => Err(ErrorNotFound("Entry not found.")),
+                    _ => {
+                        println!("{error}");
+                        Err(ErrorInternalServerError("Database error."))
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl TrashedEntry {
+    // Moves the entry out of the trash.
+    pub async fn recover(self, db_pool: &PgPool) -> Result<Entry, Error> {
+        recover_entry(db_pool, &self.entry.uuid, &self.entry.user_uuid).await?;
+
+        Ok(self.entry)
+    }
+
+    // Permanently deletes the entry and its attachments.
+    pub async fn purge(self, db_pool: &PgPool, storage: &Storage) -> Result<Entry, Error> {
+        let target = PurgeTarget::Entry {
+            entry_uuid: &self.entry.uuid,
+            user_uuid: &self.entry.user_uuid,
+        };
+
+        let (count, attachment_keys) = match purge_sqlx(db_pool, target).await {
+            Ok(value) => value,
+            Err(error) => {
+                println!("{error}");
+                return Err(ErrorInternalServerError("Database error."));
+            }
+        };
+
+        if count == 0 {
+            return Err(ErrorNotFound("Entry not found."));
+        }
+
+        storage.delete_all(attachment_keys).await;
+        Ok(self.entry)
+    }
+}
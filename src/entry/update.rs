@@ -1,49 +1,129 @@
 use actix_web::{Error, error::{ErrorInternalServerError, ErrorNotFound}};
+use chrono::{NaiveDateTime, Utc};
 use sqlx::PgPool;
+use uuid::Uuid;
 use super::Entry;
+use super::{tag, utils};
 
-async fn update_entry(
+enum UpdateError {
+    NotFound,
+    Sqlx(sqlx::Error),
+}
+
+impl From<sqlx::Error> for UpdateError {
+    fn from(error: sqlx::Error) -> Self {
+        UpdateError::Sqlx(error)
+    }
+}
+
+// The content being replaced is kept in journal_revisions.
+async fn update_entry_sqlx(
     db_pool: &PgPool,
     entry_uuid: &str,
     user_uuid: &str,
     title: &str,
-    body: &str) -> Result<(), Error>
+    body: &str,
+    tags: Option<&[String]>,
+    created: Option<&(NaiveDateTime, utils::Timezone)>) -> Result<(), UpdateError>
 {
-    let update_result = 
-        sqlx::query("UPDATE journals SET title = $1, body = $2 WHERE uuid = $3 AND user_uuid = $4")
+    let mut transaction = db_pool.begin().await?;
+
+    let insert_result = sqlx::query(
+        "INSERT INTO journal_revisions (uuid, journal_uuid, created, title, body) \
+         SELECT $1, uuid, $2, title, body FROM journals \
+         WHERE uuid = $3 AND user_uuid = $4 AND deleted_at IS NULL")
+        .bind(Uuid::new_v4().to_string())
+        .bind(Utc::now().naive_utc())
+        .bind(entry_uuid)
+        .bind(user_uuid)
+        .execute(&mut transaction)
+        .await?;
+
+    if insert_result.rows_affected() == 0 {
+        return Err(UpdateError::NotFound);
+    }
+
+    sqlx::query("UPDATE journals SET title = $1, body = $2 WHERE uuid = $3 AND user_uuid = $4")
         .bind(title)
         .bind(body)
         .bind(entry_uuid)
         .bind(user_uuid)
-        .execute(db_pool)
-        .await;
+        .execute(&mut transaction)
+        .await?;
 
-    let query_result = match update_result {
-        Ok(value) => value,
-        Err(error) => {
-            println!("{error}");
-            return Err(ErrorInternalServerError("Database error."));
-        }
-    };
+    if let Some((created_utc, timezone)) = created {
+        sqlx::query(
+            "UPDATE journals SET created = $1, timezone_offset = $2, timezone = $3 \
+             WHERE uuid = $4 AND user_uuid = $5")
+            .bind(*created_utc)
+            .bind(timezone.offset)
+            .bind(&timezone.name)
+            .bind(entry_uuid)
+            .bind(user_uuid)
+            .execute(&mut transaction)
+            .await?;
+    }
 
-    if query_result.rows_affected() == 0 {
-        return Err(ErrorNotFound("Entry not found."));
+    if let Some(tags) = tags {
+        tag::set_tags_sqlx(&mut transaction, entry_uuid, user_uuid, tags).await?;
     }
 
+    transaction.commit().await?;
     Ok(())
 }
 
+async fn update_entry(
+    db_pool: &PgPool,
+    entry_uuid: &str,
+    user_uuid: &str,
+    title: &str,
+    body: &str,
+    tags: Option<&[String]>,
+    created: Option<&(NaiveDateTime, utils::Timezone)>) -> Result<(), Error>
+{
+    match update_entry_sqlx(db_pool, entry_uuid, user_uuid, title, body, tags, created).await {
+        Ok(value) => Ok(value),
+        Err(UpdateError::NotFound) => Err(ErrorNotFound("Entry not found.")),
+        Err(UpdateError::Sqlx(error)) => {
+            println!("{error}");
+            Err(ErrorInternalServerError("Database error."))
+        }
+    }
+}
+
 impl Entry {
+    /*
+    Tags are left untouched when 'tags' is None. Likewise, 'created' is an optional new
+    UTC creation timestamp with its timezone.
+    */
     pub async fn update(
         mut self,
         db_pool: &PgPool,
         title: &str,
-        body: &str) -> Result<Self, Error>
+        body: &str,
+        tags: Option<&[String]>,
+        created: Option<(NaiveDateTime, utils::Timezone)>) -> Result<Self, Error>
     {
-        update_entry(db_pool, &self.uuid, &self.user_uuid, title, body).await?;
+        if let Some((created_utc, timezone)) = &created {
+            utils::validate_created(*created_utc, timezone)?;
+        }
+
+        let tags = match tags {
+            Some(tags) => Some(tag::normalize(tags)?),
+            None => None
+        };
+
+        update_entry(db_pool, &self.uuid, &self.user_uuid, title, body, tags.as_deref(), created.as_ref()).await?;
 
         self.title = title.to_string();
         self.body = body.to_string();
+        if let Some(tags) = tags {
+            self.tags = tags;
+        }
+        if let Some((created_utc, timezone)) = created {
+            self.created = timezone.render(created_utc).to_rfc3339();
+            self.timezone = timezone;
+        }
 
         Ok(self)
     }
@@ -1,14 +1,123 @@
-use chrono::{DateTime, NaiveDateTime, FixedOffset, TimeZone};
+use actix_web::{Error, error::ErrorBadRequest};
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, FixedOffset, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+use sqlx::{Row, postgres::PgRow};
 
-pub fn naive_to_offset(
-    naive: NaiveDateTime,
-    timezone_offset: i32) -> DateTime<FixedOffset> 
+/*
+Timezone an entry was written in.
+'offset' is the JS getTimezoneOffset() style value at the creation date of the entry.
+'name' is the IANA time zone name, such as "Asia/Seoul". It is unknown for entries which
+were written with only an offset.
+*/
+#[derive(Clone)]
+pub struct Timezone {
+    pub offset: i32,
+    pub name: Option<String>,
+}
+
+impl Timezone {
+    pub fn from_offset(offset: i32) -> Self {
+        Timezone { offset, name: None }
+    }
+
+    // Resolves the offset of the zone at the UTC timestamp.
+    pub fn from_name(name: &str, at_utc: NaiveDateTime) -> Result<Self, Error> {
+        let zone: Tz = match name.parse() {
+            Ok(value) => value,
+            Err(_) => return Err(ErrorBadRequest("Timezone should be an IANA time zone name."))
+        };
+
+        let offset = zone.offset_from_utc_datetime(&at_utc).fix();
+
+        Ok(Timezone {
+            offset: -offset.local_minus_utc() / 60,
+            name: Some(zone.name().to_string()),
+        })
+    }
+
+    // Reads the 'timezone_offset' and 'timezone' columns of a journals row.
+    pub fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        Ok(Timezone {
+            offset: row.try_get("timezone_offset")?,
+            name: row.try_get("timezone")?,
+        })
+    }
+
+    /*
+    Renders a UTC timestamp in the zone, which gives the right offset for timestamps on
+    the other side of a DST change. Falls back to the fixed offset when the zone is unknown.
+    */
+    pub fn render(&self, naive: NaiveDateTime) -> DateTime<FixedOffset> {
+        if let Some(Ok(zone)) = self.name.as_deref().map(str::parse::<Tz>) {
+            return zone.from_utc_datetime(&naive).fixed_offset();
+        }
+
+        // Offsets are validated on the way in, but rows from older versions were not.
+        let offset = match FixedOffset::west_opt(self.offset * 60) {
+            Some(value) => value,
+            None => FixedOffset::west_opt(0).unwrap()
+        };
+
+        offset.from_utc_datetime(&naive)
+    }
+}
+
+/*
+Parses an RFC 3339 timestamp given by a client as the creation date of an entry.
+Returns the UTC timestamp and the timezone offset in JS getTimezoneOffset() style.
+*/
+pub fn parse_created(value: &str) -> Result<(NaiveDateTime, i32), Error> {
+    match DateTime::parse_from_rfc3339(value) {
+        Ok(created) => Ok((created.naive_utc(), -created.offset().local_minus_utc() / 60)),
+        Err(_) => Err(ErrorBadRequest("Creation date should be an RFC 3339 timestamp."))
+    }
+}
+
+/*
+Resolves the creation date and timezone requested by a client.
+'created' defaults to the current time. The zone named by 'timezone' takes precedence over
+the offset of 'created', which in turn takes precedence over 'timezone_offset'.
+*/
+pub fn resolve_created(
+    created: Option<&str>,
+    timezone: Option<&str>,
+    timezone_offset: Option<i32>) -> Result<(NaiveDateTime, Timezone), Error>
 {
-    let offset = match FixedOffset::west_opt(timezone_offset * 60) {
-        Some(value) => value,
-        None => FixedOffset::west_opt(0).unwrap()
+    let (created_utc, created_offset) = match created {
+        Some(value) => {
+            let (created_utc, offset) = parse_created(value)?;
+            (created_utc, Some(offset))
+        },
+        None => (Utc::now().naive_utc(), None)
+    };
+
+    let timezone = match (timezone, created_offset.or(timezone_offset)) {
+        (Some(name), _) => Timezone::from_name(name, created_utc)?,
+        (None, Some(offset)) => Timezone::from_offset(offset),
+        (None, None) => return Err(ErrorBadRequest("Either 'timezone' or 'timezone_offset' is required."))
     };
 
-    offset.from_utc_datetime(&naive)
+    Ok((created_utc, timezone))
 }
 
+// Entries can be dated back to 1900, but not more than a day into the future.
+pub fn validate_created(created_utc: NaiveDateTime, timezone: &Timezone) -> Result<(), Error> {
+    if FixedOffset::west_opt(timezone.offset * 60).is_none() {
+        return Err(ErrorBadRequest("Timezone offset is out of range."));
+    }
+
+    if let Some(name) = &timezone.name {
+        if name.parse::<Tz>().is_err() {
+            return Err(ErrorBadRequest("Timezone should be an IANA time zone name."));
+        }
+    }
+
+    let earliest = NaiveDate::from_ymd_opt(1900, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+    let latest = Utc::now().naive_utc() + Duration::days(1);
+
+    if created_utc < earliest || created_utc > latest {
+        return Err(ErrorBadRequest("Creation date is out of range."));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use chrono::DateTime;
+use crate::entry::Entry;
+use super::Encoder;
+
+// Single HTML page with every entry, readable without Centinote.
+pub struct HtmlEncoder;
+
+impl HtmlEncoder {
+    pub fn new() -> Self {
+        HtmlEncoder
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(character)
+        }
+    }
+    escaped
+}
+
+impl Encoder for HtmlEncoder {
+    fn begin(&mut self) -> Vec<u8> {
+        concat!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n",
+            "<title>Centinote export</title>\n<style>\n",
+            "body { max-width: 750px; margin: 12px auto; font-family: sans-serif; }\n",
+            "article { margin: 24px 0; padding-left: 11px; border-left: 3px solid #ccc; }\n",
+            ".body { white-space: pre-wrap; }\n",
+            ".tags { color: #666; }\n",
+            "</style>\n</head>\n<body>\n<h1>Centinote</h1>\n"
+        ).as_bytes().to_vec()
+    }
+
+    fn entry(&mut self, entry: &Entry) -> Vec<u8> {
+        let displayed = match DateTime::parse_from_rfc3339(&entry.created) {
+            Ok(value) => value.format("%B %d %Y %H:%M").to_string(),
+            Err(_) => entry.created.clone()
+        };
+
+        let tags = match entry.tags.is_empty() {
+            true => String::new(),
+            false => format!("<p class=\"tags\">{}</p>\n", escape(&entry.tags.join(", ")))
+        };
+
+        format!(
+            "<article>\n<h2>{}</h2>\n<time datetime=\"{}\">{}</time>\n{}<p class=\"body\">{}</p>\n</article>\n",
+            escape(&entry.title),
+            escape(&entry.created),
+            escape(&displayed),
+            tags,
+            escape(&entry.body)).into_bytes()
+    }
+
+    fn end(&mut self) -> Vec<u8> {
+        b"</body>\n</html>\n".to_vec()
+    }
+}
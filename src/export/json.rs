@@ -0,0 +1,62 @@
+use serde_json::json;
+use crate::entry::Entry;
+use super::Encoder;
+
+/*
+Single JSON document, where 'timezone' is null for entries written without a time zone name:
+{
+    "format": "centinote",
+    "version": 1,
+    "entries": [
+        {
+            "uuid": "5315486c-02ee-4712-9793-b002193d0275",
+            "created": "2023-01-07T06:29:16.035754+09:00",
+            "timezone_offset": -540,
+            "timezone": "Asia/Seoul",
+            "title": "My Title",
+            "body": "I did nothing today.",
+            "tags": ["family"]
+        }
+    ]
+}
+*/
+pub struct JsonEncoder {
+    first: bool,
+}
+
+impl JsonEncoder {
+    pub fn new() -> Self {
+        JsonEncoder { first: true }
+    }
+}
+
+impl Encoder for JsonEncoder {
+    fn begin(&mut self) -> Vec<u8> {
+        b"{\"format\":\"centinote\",\"version\":1,\"entries\":[".to_vec()
+    }
+
+    fn entry(&mut self, entry: &Entry) -> Vec<u8> {
+        let mut bytes = match self.first {
+            true => Vec::new(),
+            false => b",".to_vec()
+        };
+        self.first = false;
+
+        let value = json!({
+            "uuid": entry.uuid,
+            "created": entry.created,
+            "timezone_offset": entry.timezone.offset,
+            "timezone": entry.timezone.name,
+            "title": entry.title,
+            "body": entry.body,
+            "tags": entry.tags,
+        });
+
+        bytes.extend(value.to_string().into_bytes());
+        bytes
+    }
+
+    fn end(&mut self) -> Vec<u8> {
+        b"]}".to_vec()
+    }
+}
@@ -0,0 +1,70 @@
+use chrono::DateTime;
+use serde_json::Value;
+use crate::entry::Entry;
+use super::Encoder;
+use super::zip::ZipWriter;
+
+/*
+ZIP archive with an entry per Markdown file, named "{date}-{uuid}.md".
+Metadata goes to YAML front matter. Strings are written as JSON strings,
+which are valid YAML double-quoted scalars. The 'timezone' key is left out for entries
+written without a time zone name.
+
+---
+uuid: 5315486c-02ee-4712-9793-b002193d0275
+title: "My Title"
+created: 2023-01-07T06:29:16.035754+09:00
+timezone_offset: -540
+timezone: "Asia/Seoul"
+tags: ["family"]
+---
+
+I did nothing today.
+*/
+pub struct MarkdownEncoder {
+    zip: ZipWriter,
+}
+
+impl MarkdownEncoder {
+    pub fn new() -> Self {
+        MarkdownEncoder { zip: ZipWriter::new() }
+    }
+}
+
+pub fn render(entry: &Entry) -> String {
+    let tags: Vec<String> = entry.tags.iter().map(|tag| Value::from(tag.as_str()).to_string()).collect();
+    let timezone = match &entry.timezone.name {
+        Some(name) => format!("timezone: {}\n", Value::from(name.as_str())),
+        None => String::new()
+    };
+
+    format!(
+        "---\nuuid: {}\ntitle: {}\ncreated: {}\ntimezone_offset: {}\n{}tags: [{}]\n---\n\n{}\n",
+        entry.uuid,
+        Value::from(entry.title.as_str()),
+        entry.created,
+        entry.timezone.offset,
+        timezone,
+        tags.join(", "),
+        entry.body)
+}
+
+impl Encoder for MarkdownEncoder {
+    fn begin(&mut self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn entry(&mut self, entry: &Entry) -> Vec<u8> {
+        let created = match DateTime::parse_from_rfc3339(&entry.created) {
+            Ok(value) => value.naive_local(),
+            Err(_) => chrono::Utc::now().naive_utc()
+        };
+
+        let name = format!("{}-{}.md", created.format("%Y-%m-%d"), entry.uuid);
+        self.zip.file(&name, &created, render(entry).as_bytes())
+    }
+
+    fn end(&mut self) -> Vec<u8> {
+        self.zip.finish()
+    }
+}
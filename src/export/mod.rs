@@ -0,0 +1,141 @@
+pub mod html;
+pub mod json;
+pub mod markdown;
+mod zip;
+
+use actix_web::{web::Bytes, Error, error::ErrorBadRequest};
+use futures::{Stream, stream};
+use sqlx::PgPool;
+use crate::entry::{Entry, list::{Cursor, Direction}};
+
+// Number of entries loaded from the database at once while exporting.
+const PAGE_SIZE: i64 = 100;
+
+pub enum Format {
+    Json,
+    Markdown,
+    Html,
+}
+
+impl Format {
+    pub fn parse(value: &str) -> Result<Self, Error> {
+        match value {
+            "json" => Ok(Format::Json),
+            "markdown" => Ok(Format::Markdown),
+            "html" => Ok(Format::Html),
+            _ => Err(ErrorBadRequest("Format should be one of 'json', 'markdown' and 'html'."))
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::Markdown => "application/zip",
+            Format::Html => "text/html; charset=utf-8",
+        }
+    }
+
+    pub fn filename(&self) -> &'static str {
+        match self {
+            Format::Json => "centinote-export.json",
+            Format::Markdown => "centinote-export.zip",
+            Format::Html => "centinote-export.html",
+        }
+    }
+
+    fn encoder(&self) -> Box<dyn Encoder> {
+        match self {
+            Format::Json => Box::new(json::JsonEncoder::new()),
+            Format::Markdown => Box::new(markdown::MarkdownEncoder::new()),
+            Format::Html => Box::new(html::HtmlEncoder::new()),
+        }
+    }
+}
+
+/*
+Encoders turn entries into the output format piece by piece, so that only a page of
+entries needs to be in memory at a time.
+*/
+pub trait Encoder {
+    fn begin(&mut self) -> Vec<u8>;
+    fn entry(&mut self, entry: &Entry) -> Vec<u8>;
+    fn end(&mut self) -> Vec<u8>;
+}
+
+enum Step {
+    Begin,
+    Page(Cursor),
+    End,
+    Done,
+}
+
+struct State {
+    db_pool: PgPool,
+    user_uuid: String,
+    encoder: Box<dyn Encoder>,
+    step: Step,
+}
+
+async fn next_chunk(mut state: State) -> Option<(Result<Bytes, Error>, State)> {
+    let chunk = match state.step {
+        Step::Begin => {
+            state.step = Step::Page(Cursor::beginning());
+            state.encoder.begin()
+        },
+        Step::Page(cursor) => {
+            let page_result = Entry::page_by_user(
+                &state.db_pool,
+                &state.user_uuid,
+                PAGE_SIZE,
+                Some((Direction::After, cursor)),
+                None).await;
+
+            let page = match page_result {
+                Ok(value) => value,
+                Err(error) => {
+                    state.step = Step::Done;
+                    return Some((Err(error), state));
+                }
+            };
+
+            state.step = match page.next_cursor.as_deref().map(Cursor::parse) {
+                Some(Ok(cursor)) => Step::Page(cursor),
+                Some(Err(error)) => {
+                    state.step = Step::Done;
+                    return Some((Err(error), state));
+                },
+                None => Step::End
+            };
+
+            // Pages are recent first even when walking forwards.
+            let mut chunk = Vec::new();
+            for entry in page.entries.iter().rev() {
+                chunk.extend(state.encoder.entry(entry));
+            }
+            chunk
+        },
+        Step::End => {
+            state.step = Step::Done;
+            state.encoder.end()
+        },
+        Step::Done => return None
+    };
+
+    Some((Ok(Bytes::from(chunk)), state))
+}
+
+// Streams every entry of the user in the format, oldest first.
+pub fn stream(
+    db_pool: PgPool,
+    user_uuid: String,
+    format: &Format) -> impl Stream<Item = Result<Bytes, Error>>
+{
+    let state = State {
+        db_pool,
+        user_uuid,
+        encoder: format.encoder(),
+        step: Step::Begin,
+    };
+
+    stream::unfold(state, next_chunk)
+}
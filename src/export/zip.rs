@@ -0,0 +1,263 @@
+use chrono::{Datelike, NaiveDateTime, Timelike};
+
+/*
+Minimal ZIP writer which emits the archive as it goes, without seeking back.
+Files are stored without compression. Zip64 end records are written when there are
+too many files or the archive grows past 4 GiB.
+*/
+pub struct ZipWriter {
+    records: Vec<Record>,
+    offset: u64,
+}
+
+struct Record {
+    name: String,
+    crc: u32,
+    size: u32,
+    time: u16,
+    date: u16,
+    offset: u64,
+}
+
+fn dos_time(timestamp: &NaiveDateTime) -> (u16, u16) {
+    let year = timestamp.year().clamp(1980, 2107) as u16;
+    let time = (timestamp.hour() << 11 | timestamp.minute() << 5 | (timestamp.second() / 2)) as u16;
+    let date = (year - 1980) << 9 | (timestamp.month() << 5 | timestamp.day()) as u16;
+    (time, date)
+}
+
+impl ZipWriter {
+    pub fn new() -> Self {
+        ZipWriter {
+            records: Vec::new(),
+            offset: 0,
+        }
+    }
+
+    // Returns bytes of a local file header followed by the data.
+    pub fn file(&mut self, name: &str, modified: &NaiveDateTime, data: &[u8]) -> Vec<u8> {
+        let (time, date) = dos_time(modified);
+        let record = Record {
+            name: name.to_string(),
+            crc: crc32fast::hash(data),
+            size: data.len() as u32,
+            time,
+            date,
+            offset: self.offset,
+        };
+
+        let mut bytes = Vec::with_capacity(30 + name.len() + data.len());
+        bytes.extend(0x04034b50u32.to_le_bytes());
+        bytes.extend(20u16.to_le_bytes());
+        bytes.extend(0x0800u16.to_le_bytes()); // File names are in UTF-8.
+        bytes.extend(0u16.to_le_bytes());
+        bytes.extend(record.time.to_le_bytes());
+        bytes.extend(record.date.to_le_bytes());
+        bytes.extend(record.crc.to_le_bytes());
+        bytes.extend(record.size.to_le_bytes());
+        bytes.extend(record.size.to_le_bytes());
+        bytes.extend((name.len() as u16).to_le_bytes());
+        bytes.extend(0u16.to_le_bytes());
+        bytes.extend(name.as_bytes());
+        bytes.extend(data);
+
+        self.offset += bytes.len() as u64;
+        self.records.push(record);
+        bytes
+    }
+
+    // Returns bytes of the central directory and the end records.
+    pub fn finish(&mut self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let directory_offset = self.offset;
+
+        for record in self.records.iter() {
+            let zip64_offset = record.offset >= u32::MAX as u64;
+            let version = if zip64_offset { 45u16 } else { 20u16 };
+
+            bytes.extend(0x02014b50u32.to_le_bytes());
+            bytes.extend(version.to_le_bytes());
+            bytes.extend(version.to_le_bytes());
+            bytes.extend(0x0800u16.to_le_bytes());
+            bytes.extend(0u16.to_le_bytes());
+            bytes.extend(record.time.to_le_bytes());
+            bytes.extend(record.date.to_le_bytes());
+            bytes.extend(record.crc.to_le_bytes());
+            bytes.extend(record.size.to_le_bytes());
+            bytes.extend(record.size.to_le_bytes());
+            bytes.extend((record.name.len() as u16).to_le_bytes());
+            bytes.extend(if zip64_offset { 12u16 } else { 0u16 }.to_le_bytes());
+            bytes.extend(0u16.to_le_bytes());
+            bytes.extend(0u16.to_le_bytes());
+            bytes.extend(0u16.to_le_bytes());
+            bytes.extend(0u32.to_le_bytes());
+            bytes.extend(if zip64_offset { u32::MAX } else { record.offset as u32 }.to_le_bytes());
+            bytes.extend(record.name.as_bytes());
+
+            if zip64_offset {
+                bytes.extend(0x0001u16.to_le_bytes());
+                bytes.extend(8u16.to_le_bytes());
+                bytes.extend(record.offset.to_le_bytes());
+            }
+        }
+
+        let directory_size = bytes.len() as u64;
+        let count = self.records.len() as u64;
+        let zip64 = count >= u16::MAX as u64
+            || directory_offset >= u32::MAX as u64
+            || directory_size >= u32::MAX as u64;
+
+        if zip64 {
+            let record_offset = directory_offset + directory_size;
+
+            bytes.extend(0x06064b50u32.to_le_bytes());
+            bytes.extend(44u64.to_le_bytes());
+            bytes.extend(45u16.to_le_bytes());
+            bytes.extend(45u16.to_le_bytes());
+            bytes.extend(0u32.to_le_bytes());
+            bytes.extend(0u32.to_le_bytes());
+            bytes.extend(count.to_le_bytes());
+            bytes.extend(count.to_le_bytes());
+            bytes.extend(directory_size.to_le_bytes());
+            bytes.extend(directory_offset.to_le_bytes());
+
+            bytes.extend(0x07064b50u32.to_le_bytes());
+            bytes.extend(0u32.to_le_bytes());
+            bytes.extend(record_offset.to_le_bytes());
+            bytes.extend(1u32.to_le_bytes());
+        }
+
+        bytes.extend(0x06054b50u32.to_le_bytes());
+        bytes.extend(0u16.to_le_bytes());
+        bytes.extend(0u16.to_le_bytes());
+        bytes.extend((count.min(u16::MAX as u64) as u16).to_le_bytes());
+        bytes.extend((count.min(u16::MAX as u64) as u16).to_le_bytes());
+        bytes.extend((directory_size.min(u32::MAX as u64) as u32).to_le_bytes());
+        bytes.extend((directory_offset.min(u32::MAX as u64) as u32).to_le_bytes());
+        bytes.extend(0u16.to_le_bytes());
+
+        self.offset += bytes.len() as u64;
+        self.records.clear();
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read, Seek, SeekFrom};
+    use chrono::{NaiveDate, NaiveDateTime};
+    use zip::ZipArchive;
+    use super::ZipWriter;
+
+    fn modified() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2023, 1, 7).unwrap().and_hms_opt(6, 29, 16).unwrap()
+    }
+
+    // Reads like 'padding' zero bytes followed by 'data', without allocating the padding.
+    struct Padded {
+        padding: u64,
+        data: Vec<u8>,
+        position: u64,
+    }
+
+    impl Read for Padded {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let length = self.padding + self.data.len() as u64;
+            let count = (buf.len() as u64).min(length.saturating_sub(self.position)) as usize;
+
+            for byte in buf[..count].iter_mut() {
+                *byte = match self.position.checked_sub(self.padding) {
+                    Some(index) => self.data[index as usize],
+                    None => 0
+                };
+                self.position += 1;
+            }
+
+            Ok(count)
+        }
+    }
+
+    impl Seek for Padded {
+        fn seek(&mut self, position: SeekFrom) -> std::io::Result<u64> {
+            let length = self.padding + self.data.len() as u64;
+            self.position = match position {
+                SeekFrom::Start(offset) => offset,
+                SeekFrom::End(offset) => length.checked_add_signed(offset).unwrap(),
+                SeekFrom::Current(offset) => self.position.checked_add_signed(offset).unwrap(),
+            };
+            Ok(self.position)
+        }
+    }
+
+    fn read_all<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Vec<(String, Vec<u8>)> {
+        let mut files = Vec::new();
+        for index in 0..archive.len() {
+            let mut file = archive.by_index(index).unwrap();
+            let mut data = Vec::new();
+            file.read_to_end(&mut data).unwrap();
+            files.push((file.name().to_string(), data));
+        }
+        files
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut writer = ZipWriter::new();
+        let mut bytes = writer.file("2023-01-07 Beach.md", &modified(), b"# Beach\n\nWent to the beach.");
+        bytes.extend(writer.file("attachments/사진.txt", &modified(), b""));
+        bytes.extend(writer.finish());
+
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(read_all(&mut archive), vec![
+            ("2023-01-07 Beach.md".to_string(), b"# Beach\n\nWent to the beach.".to_vec()),
+            ("attachments/사진.txt".to_string(), Vec::new()),
+        ]);
+
+        let file = archive.by_index(0).unwrap();
+        let time = file.last_modified().unwrap();
+        assert_eq!((time.year(), time.month(), time.day()), (2023, 1, 7));
+        assert_eq!((time.hour(), time.minute(), time.second()), (6, 29, 16));
+    }
+
+    #[test]
+    fn empty_archive() {
+        let bytes = ZipWriter::new().finish();
+        assert_eq!(ZipArchive::new(Cursor::new(bytes)).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn zip64_offsets() {
+        // Files start past 4 GiB, so their offsets only fit in zip64 extra fields.
+        let padding = u32::MAX as u64 + 1;
+        let mut writer = ZipWriter::new();
+        writer.offset = padding;
+
+        let mut data = writer.file("first.md", &modified(), b"first");
+        data.extend(writer.file("second.md", &modified(), b"second"));
+        data.extend(writer.finish());
+
+        let mut archive = ZipArchive::new(Padded { padding, data, position: 0 }).unwrap();
+        assert_eq!(read_all(&mut archive), vec![
+            ("first.md".to_string(), b"first".to_vec()),
+            ("second.md".to_string(), b"second".to_vec()),
+        ]);
+    }
+
+    #[test]
+    fn zip64_file_count() {
+        let count = u16::MAX as usize + 1;
+        let mut writer = ZipWriter::new();
+        let mut bytes = Vec::new();
+        for index in 0..count {
+            bytes.extend(writer.file(&index.to_string(), &modified(), index.to_string().as_bytes()));
+        }
+        bytes.extend(writer.finish());
+
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(archive.len(), count);
+
+        let mut last = String::new();
+        archive.by_name(&(count - 1).to_string()).unwrap().read_to_string(&mut last).unwrap();
+        assert_eq!(last, (count - 1).to_string());
+    }
+}
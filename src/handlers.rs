@@ -1,342 +1,2821 @@
 use actix_web::{
-    get, post, patch, delete, web, HttpRequest, HttpResponse, Responder, Error, 
-    cookie::{Cookie, SameSite},
-    error::ErrorUnauthorized
+    get, post, patch, put, delete, web, HttpRequest, HttpResponse, Responder, Error, 
+    cookie::{Cookie, SameSite, time},
+    error::{ErrorBadRequest, ErrorConflict, ErrorPayloadTooLarge, ErrorUnauthorized},
+    http::header::{ContentDisposition, DispositionParam, DispositionType}
 };
+use actix_multipart::Multipart;
+use futures::TryStreamExt;
 use serde::{Serialize, Deserialize};
 use sqlx::PgPool;
+use webauthn_rs::Webauthn;
+use webauthn_rs::prelude::{
+    CreationChallengeResponse, PublicKeyCredential, RegisterPublicKeyCredential, RequestChallengeResponse
+};
+
+use crate::access_token::AccessToken;
+use crate::admin;
+use crate::attachment::{Attachment, storage::Storage};
+use crate::entry::{self, Entry, list, revision, tag};
+use crate::{export, import};
+use crate::passkey::{Passkey, decoy::Decoys};
+use crate::session::{Client, Lifetimes, Session, admin::Admin, challenge::Challenge, throttle::Throttle};
+use crate::invite::Invite;
+use crate::user::{User, password::PasswordPolicy, registration::Registration, update::ProfileUpdate};
+
+/*
+===== GET /api/registration =====
+
+This handler responds with who may register, so that the registration page can ask for
+an invite code or tell that registration is closed.
+
+Response JSON example: { "mode": "invite" }
+
+Modes:
+ open: Anyone may register.
+ closed: No one may register.
+ invite: An invite code from an administrator is required.
+ approval: An administrator has to approve new users before they can log in.
+
+The first user can register in any mode, so the mode is 'open' until then.
+*/
+
+#[derive(Serialize)]
+struct RegistrationDetail {
+    mode: &'static str,
+}
 
-use crate::entry::Entry;
-use crate::session::Session;
-use crate::user::User;
+#[get("/api/registration")]
+async fn registration_detail(
+    db_pool: web::Data<PgPool>,
+    registration: web::Data<Registration>) -> Result<HttpResponse, Error>
+{
+    let mode = registration.effective(&db_pool).await?;
+    Ok(HttpResponse::Ok().json(RegistrationDetail { mode: mode.as_str() }))
+}
 
 /*
 ===== POST /api/users =====
 
-This handler creates a user on success.
+This handler creates a user on success, as the registration mode allows.
+The first user becomes the administrator.
+
+Request JSON example: { "username": "myusername", "password": "mypassword", "invite": "Xq3Lr8..." }
 
-Request JSON example: { "username": "myusername", "password": "mypassword" }
+The 'invite' field is only needed when registration requires an invite code.
+
+Usernames have 3 to 32 letters, digits, '.', '-' and '_', and start and end with a letter
+or digit. They are unique regardless of case, and stored in Unicode NFKC form.
+Passwords have at least 8 characters, or more if the server asks for more, and at most 128.
+They should not be the same as the username, nor known from data breaches.
+
+Response JSON example, when a rule is broken:
+{
+    "field": "password",
+    "rule": "too_short",
+    "message": "Password should be at least 8 characters long."
+}
+
+Rules:
+ username: empty, too_short, too_long, invalid_character, invalid_edge
+ password: too_short, too_long, same_as_username, breached
 
 Notable HTTP status codes:
+ 201 Created: User is created, and can log in.
+ 202 Accepted: User is created, but cannot log in until an administrator approves.
+ 403 Forbidden: Registration is closed, or invite code is missing, wrong, expired or used up.
  409 Conflict: Username is already taken.
+ 422 Unprocessable Entity: Username or password breaks a rule.
 */
 
 #[derive(Deserialize)]
 struct UserCreate {
     username: String,
     password: String,
+    invite: Option<String>,
 }
 
 #[post("/api/users")]
 async fn user_create(
     db_pool: web::Data<PgPool>,
+    registration: web::Data<Registration>,
+    policy: web::Data<PasswordPolicy>,
     info: web::Json<UserCreate>) -> Result<HttpResponse, Error> 
 {
-    let user = User::create(&db_pool, &info.username, &info.password).await?;
+    let user = User::create(
+        &db_pool,
+        &info.username,
+        &info.password,
+        **registration,
+        info.invite.as_deref(),
+        &policy).await?;
 
     let user_path = format!("/api/users/{}", &user.uuid);
-    Ok(HttpResponse::Created().insert_header(("Location", user_path)).finish())
+    if user.approved {
+        Ok(HttpResponse::Created().insert_header(("Location", user_path)).finish())
+    } else {
+        Ok(HttpResponse::Accepted().insert_header(("Location", user_path)).finish())
+    }
+}
+
+/*
+===== GET /api/users/{user_uuid} =====
+
+This handler responds with the profile and settings of a user on success.
+
+Response JSON example:
+{
+    "username": "myusername",
+    "display_name": "My Name",
+    "created": "2023-01-06T12:00:00+00:00",
+    "entry_count": 42,
+    "timezone": "Asia/Seoul",
+    "week_start": "monday",
+    "theme": "system",
+    "two_factor": false
+}
+
+The 'display_name' and 'timezone' fields are null when not set.
+The 'two_factor' field tells whether TOTP two-factor authentication is enabled.
+The 'entry_count' field does not count entries in the trash.
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session is not authorized for this user.
+*/
+
+#[derive(Serialize)]
+struct UserDetail {
+    username: String,
+    display_name: Option<String>,
+    created: String,
+    entry_count: i64,
+    timezone: Option<String>,
+    week_start: String,
+    theme: String,
+    two_factor: bool,
+}
+
+#[get("/api/users/{user_uuid}")]
+async fn user_detail(
+    session: Session,
+    req: HttpRequest,
+    db_pool: web::Data<PgPool>) -> Result<HttpResponse, Error>
+{
+    let user = User::by_uuid(&db_pool, &session.user_uuid).await?;
+    let profile = user.profile(&db_pool).await?;
+    let settings = user.settings(&db_pool).await?;
+
+    let response = web::Json(UserDetail {
+        username: profile.username,
+        display_name: profile.display_name,
+        created: profile.created,
+        entry_count: profile.entry_count,
+        timezone: settings.timezone,
+        week_start: settings.week_start,
+        theme: settings.theme,
+        two_factor: user.totp_enabled,
+    }).respond_to(&req).map_into_boxed_body();
+
+    Ok(response)
+}
+
+/*
+===== PATCH /api/users/{user_uuid} =====
+
+This handler updates the profile and settings of a user on success.
+
+Request JSON example:
+{
+    "display_name": "My Name",
+    "timezone": "Asia/Seoul",
+    "week_start": "sunday",
+    "theme": "dark"
+}
+
+Every field is optional, and fields which are omitted are left untouched.
+An empty 'display_name' or 'timezone' clears it.
+The 'timezone' field is an IANA time zone name, used for new entries which are created
+without a timezone. The 'week_start' field is one of 'monday', 'sunday' and 'saturday'.
+The 'theme' field is one of 'system', 'light' and 'dark'.
+
+Notable HTTP status codes:
+ 400 Bad Request: Display name is longer than 64 characters, or a setting has an invalid value.
+ 401 Unauthorized: Session is not authorized for this user.
+*/
+
+#[derive(Deserialize)]
+struct UserUpdate {
+    display_name: Option<String>,
+    timezone: Option<String>,
+    week_start: Option<String>,
+    theme: Option<String>,
+}
+
+#[patch("/api/users/{user_uuid}")]
+async fn user_update(
+    session: Session,
+    db_pool: web::Data<PgPool>,
+    info: web::Json<UserUpdate>) -> Result<HttpResponse, Error>
+{
+    let user = User::by_uuid(&db_pool, &session.user_uuid).await?;
+
+    let update = ProfileUpdate {
+        display_name: info.display_name.as_deref(),
+        timezone: info.timezone.as_deref(),
+        week_start: info.week_start.as_deref(),
+        theme: info.theme.as_deref(),
+    };
+    user.update_profile(&db_pool, &update).await?;
+
+    Ok(HttpResponse::Ok().finish())
 }
 
 /* 
 ===== POST /api/login =====
 
-Request JSON example: { "username": "myusername", "password": "mypassword" }
+Request JSON example: { "username": "myusername", "password": "mypassword", "remember": true }
 
 Three cookies are sent on a successful login attempt.
  auth: Authentication token for this session. HTTP only.
  session_uuid: Contains UUID for the session just created.
  user_uuid: Contains UUID of the user.
 
+The 'remember' field is optional, false by default. Remembered sessions use the longer
+lifetimes, and their cookies outlive the browser until the absolute lifetime has passed.
+Otherwise the cookies last until the browser is closed.
+Every authenticated request extends the session by its idle lifetime, up to the absolute one.
+
+Users with two-factor authentication get no cookies yet, but a challenge to be completed
+through POST /api/login/totp within 5 minutes.
+
+Response JSON example, with two-factor authentication:
+{
+    "challenge": "Xb2K8qLmR4vT9wYz1nC6pJ3sD7fH0gA5eU2iO8kM4lN6rQ1tV3xZ9yB7cW5aE0dF",
+    "expiry": "2023-01-07T06:34:16.035754+00:00"
+}
+
+Failed attempts are counted per username and per IP address. After a few of them, attempts
+have to wait longer and longer, and too many lock out logins for a while. Either is told by
+429 Too Many Requests, along with a Retry-After header in seconds. Passkeys still work meanwhile.
+
 Notable HTTP status codes:
- 401 Unauthorized: Username and/or password is wrong.
+ 201 Created: Session is created, and cookies are sent.
+ 202 Accepted: Password is right, but a code is required to complete the challenge.
+ 401 Unauthorized: Username and/or password is wrong. Either gets the same response.
+ 403 Forbidden: Account is waiting for approval or disabled, or password has to be reset
+  through POST /api/password-reset. Only told when the password is right.
+ 429 Too Many Requests: Too many failed attempts for the username or from the IP address.
 */
 
 #[derive(Deserialize)]
 struct Login {
     username: String,
     password: String,
+    #[serde(default)]
+    remember: bool,
 }
 
 fn build_cookie<'a>(
     name: &'a str,
     value: &'a str,
-    http_only: bool) -> Cookie<'a> 
+    http_only: bool,
+    max_age: Option<time::Duration>) -> Cookie<'a> 
 {
-    Cookie::build(name, value).same_site(SameSite::Strict).path("/").http_only(http_only).finish()
+    let mut cookie = Cookie::build(name, value).same_site(SameSite::Strict).path("/").http_only(http_only);
+    if let Some(max_age) = max_age {
+        cookie = cookie.max_age(max_age);
+    }
+
+    cookie.finish()
+}
+
+// Responds to a completed login with the cookies of the new session.
+fn login_response(session: &Session, remember: bool, lifetimes: &Lifetimes) -> HttpResponse {
+    let max_age = match remember {
+        true => Some(time::Duration::seconds(lifetimes.remember_absolute.num_seconds())),
+        false => None
+    };
+
+    let session_uuid = build_cookie("session_uuid", &session.uuid, false, max_age);
+    let user_uuid = build_cookie("user_uuid", &session.user_uuid, false, max_age);
+    let auth = build_cookie("auth", &session.token, true, max_age);
+
+    let session_path = format!("/api/users/{}/sessions/{}", session.user_uuid, session.uuid);
+    HttpResponse::Created()
+       .insert_header(("Location", session_path))
+       .cookie(session_uuid)
+       .cookie(user_uuid)
+       .cookie(auth)
+       .finish()
+}
+
+#[derive(Serialize)]
+struct LoginChallenge {
+    challenge: String,
+    expiry: String,
 }
 
 #[post("/api/login")]
 async fn login(
+    req: HttpRequest,
     db_pool: web::Data<PgPool>,
+    lifetimes: web::Data<Lifetimes>,
+    throttle: web::Data<Throttle>,
     info: web::Json<Login>) -> Result<HttpResponse, Error> 
 {
-    let user = match User::by_username(&db_pool, &info.username).await {
+    let client = Client::from_request(&req);
+    throttle.attempt(&db_pool, &info.username, client.ip.as_deref()).await?;
+
+    let user = match User::authenticate(&db_pool, &info.username, &info.password).await {
         Ok(value) => value,
         Err(error) => {
-            if error.as_response_error().status_code().as_u16() == 404 {
-                return Err(ErrorUnauthorized("User not found."));
-            } else {
-                return Err(error);
+            match error.as_response_error().status_code().as_u16() {
+                401 => throttle.record_failure(&db_pool, &info.username, client.ip.as_deref()).await?,
+                _ => throttle.release(&db_pool, &info.username, client.ip.as_deref()).await?
             }
+            return Err(error);
         }
     };
-    user.verify_password(&info.password)?;
-    
-    let session = Session::create(&db_pool, &user.uuid).await?;
 
-    let session_uuid = build_cookie("session_uuid", &session.uuid, false);
-    let user_uuid = build_cookie("user_uuid", &session.user_uuid, false);
-    let auth = build_cookie("auth", &session.token, true);
+    // Failures of the username are only cleared once the second factor is verified too.
+    if user.totp_enabled {
+        throttle.release(&db_pool, &user.username, client.ip.as_deref()).await?;
+        let (challenge, token) = Challenge::create(&db_pool, &user.uuid, info.remember).await?;
+        return Ok(HttpResponse::Accepted().json(LoginChallenge {
+            challenge: token,
+            expiry: challenge.expiry,
+        }));
+    }
 
-    let session_path = format!("/api/users/{}/sessions/{}", session.user_uuid, session.uuid);
-    let response = HttpResponse::Created()
-       .insert_header(("Location", session_path))
-       .cookie(session_uuid)
-       .cookie(user_uuid)
-       .cookie(auth)
-       .finish();
+    throttle.record_success(&db_pool, &user.username, client.ip.as_deref()).await?;
+    let session = Session::create(&db_pool, &user.uuid, &client, info.remember, &lifetimes).await?;
 
-    Ok(response)
+    Ok(login_response(&session, info.remember, &lifetimes))
 }
 
 /*
-===== POST /api/users/{user_uuid}/sessions/{session_uuid} =====
+===== POST /api/login/totp =====
 
-Refresh the specified session's lifetime. 
-Session's expiry timestamp will be 30 minutes from now on success.
-A session is only authorized for it's own refresh.
+This handler completes a login of a user with two-factor authentication, which was
+started by POST /api/login. Cookies are sent on success, just like POST /api/login.
+
+Request JSON example: { "challenge": "Xb2K8qLmR4vT9wYz...", "code": "123456" }
+
+The 'code' field is either the current code of the authenticator app, or one of the
+recovery codes. Each code can only be used once.
+A challenge allows 5 attempts, after which the password has to be entered again.
+Wrong codes count as failed attempts of POST /api/login as well.
 
 Notable HTTP status codes:
- 401 Unauthorized: Session is not authorized for refreshing the specified session.
+ 401 Unauthorized: Code is wrong, or challenge has expired, was used up or does not exist.
+ 403 Forbidden: Account is disabled.
+ 429 Too Many Requests: Too many failed attempts for the username or from the IP address.
 */
 
-#[post("/api/users/{user_uuid}/sessions/{session_uuid}")]
-async fn session_refresh(
-    session: Session,
+#[derive(Deserialize)]
+struct LoginTotp {
+    challenge: String,
+    code: String,
+}
+
+#[post("/api/login/totp")]
+async fn login_totp(
+    req: HttpRequest,
     db_pool: web::Data<PgPool>,
-    path: web::Path<(String, String)>) -> Result<HttpResponse, Error>
+    lifetimes: web::Data<Lifetimes>,
+    throttle: web::Data<Throttle>,
+    info: web::Json<LoginTotp>) -> Result<HttpResponse, Error>
 {
-    let (_, target_session_uuid) = path.into_inner();
+    let challenge = Challenge::attempt(&db_pool, &info.challenge).await?;
 
-    if session.uuid == target_session_uuid {
-        session.refresh(&db_pool).await?;
-        Ok(HttpResponse::Ok().finish())
-    } else {
-        Err(ErrorUnauthorized("Session UUIDs does not match."))
-    }
-}
+    let user = User::by_uuid(&db_pool, &challenge.user_uuid).await?;
+    user.check_active()?;
 
-/*
-===== DELETE /api/users/{user_uuid}/sessions/{session_uuid} =====
+    let client = Client::from_request(&req);
+    throttle.attempt(&db_pool, &user.username, client.ip.as_deref()).await?;
 
-Delete the specified session on success. Useful for logging out.
+    if let Err(error) = user.verify_second_factor(&db_pool, &info.code).await {
+        match error.as_response_error().status_code().as_u16() {
+            401 => throttle.record_failure(&db_pool, &user.username, client.ip.as_deref()).await?,
+            _ => throttle.release(&db_pool, &user.username, client.ip.as_deref()).await?
+        }
+        return Err(error);
+    }
 
-Notable HTTP status codes:
- 401 Unauthorized: Session is not authorized for deletion of specified session.
-*/
+    let remember = challenge.remember;
+    challenge.complete(&db_pool).await?;
 
-#[delete("/api/users/{user_uuid}/sessions/{session_uuid}")]
-async fn session_delete(
-    session: Session,
-    db_pool: web::Data<PgPool>) -> Result<HttpResponse, Error>
-{
-    session.delete(&db_pool).await?;
-    Ok(HttpResponse::Ok().finish())
+    throttle.record_success(&db_pool, &user.username, client.ip.as_deref()).await?;
+    let session = Session::create(&db_pool, &user.uuid, &client, remember, &lifetimes).await?;
+
+    Ok(login_response(&session, remember, &lifetimes))
 }
 
 /*
-===== GET /api/users/{user_uuid}/entries?full_content={true/false} =====
+===== DELETE /api/users/{user_uuid} =====
 
-This handler on success, responds with content of requested entries, recent first.
+This handler permanently deletes a user on success, along with their sessions, entries,
+revisions, tags, attachments and settings. Entries in the trash are deleted too.
+Clients should offer an export through GET /api/users/{user_uuid}/export beforehand.
 
-Response JSON examples: 
+Request JSON example: { "password": "mypassword" }
+
+Response JSON example, telling what was removed:
 {
-    "uuid": ["5315486c-02ee-4712-9793-b002193d0275", "2deffb77-b215-47b5-a074-ddd4127cc4b5"],
-    "created": ["2023-01-07T06:29:16.035754+09:00", "2023-01-07T07:36:24.014244+09:00"],
-    "title": ["Title 1", "Title 2"],
-    "body": ["Some text here.", "Another text here."]
+    "username": "myusername",
+    "entries": 42,
+    "revisions": 7,
+    "tags": 5,
+    "attachments": 3,
+    "attachment_bytes": 1048576,
+    "sessions": 2
 }
 
 Notable HTTP status codes:
- 401 Unauthorized: Session is not authorized for the requested entry.
+ 401 Unauthorized: Session is not authorized for this user, or password is wrong.
 */
 
+#[derive(Deserialize)]
+struct UserDelete {
+    password: String,
+}
+
 #[derive(Serialize)]
-struct EntryList {
-    uuid: Vec<String>,
-    created: Vec<String>,
-    title: Vec<String>,
-    body: Vec<String>,
+struct UserDeleteReceipt {
+    username: String,
+    entries: u64,
+    revisions: u64,
+    tags: u64,
+    attachments: u64,
+    attachment_bytes: i64,
+    sessions: u64,
 }
 
-#[get("/api/users/{user_uuid}/entries")]
-async fn entry_list(
+#[delete("/api/users/{user_uuid}")]
+async fn user_delete(
     session: Session,
     req: HttpRequest,
-    db_pool: web::Data<PgPool>) -> Result<HttpResponse, Error> 
+    db_pool: web::Data<PgPool>,
+    storage: web::Data<Storage>,
+    info: web::Json<UserDelete>) -> Result<HttpResponse, Error>
 {
-    let uuids = Entry::uuids_by_user(&db_pool, &session.user_uuid).await?;
-
-    let entries_map = uuids.iter().map(|uuid| Entry::by_uuid(&db_pool, &uuid, &session.user_uuid));
-    let entries = futures::future::try_join_all(entries_map).await?;
-
-    let response = web::Json(EntryList {
-        uuid: uuids,
-        created: entries.iter().map(|entry| entry.created.clone()).collect(),
-        title: entries.iter().map(|entry| entry.title.clone()).collect(),
-        body: entries.iter().map(|entry| entry.body.clone()).collect(),
+    let user = User::by_uuid(&db_pool, &session.user_uuid).await?;
+    let receipt = user.delete(&db_pool, &storage, &info.password).await?;
+
+    let response = web::Json(UserDeleteReceipt {
+        username: receipt.username,
+        entries: receipt.entries,
+        revisions: receipt.revisions,
+        tags: receipt.tags,
+        attachments: receipt.attachments,
+        attachment_bytes: receipt.attachment_bytes,
+        sessions: receipt.sessions,
     }).respond_to(&req).map_into_boxed_body();
 
     Ok(response)
 }
 
 /*
-===== GET /api/users/{user_uuid}/entries/{entry_uuid} =====
+===== PUT /api/users/{user_uuid}/password =====
 
-This handler responds with content of an entry on success.
+This handler changes the password of a user on success.
+Every other session of the user is logged out and every access token is revoked, while the
+session making the request is kept.
 
-Response JSON example:
-{
-    "created": "2023-01-07T06:29:16.035754+09:00",
-    "title": "My Title",
-    "body": "I did nothing today."
-}
+Request JSON example: { "current_password": "mypassword", "new_password": "mynewpassword" }
 
-The 'created' field is formated in ISO 8601.
+New passwords follow the rules of POST /api/users, and break them likewise.
 
 Notable HTTP status codes:
- 401 Unauthorized: Session is not authorized for the requested entry.
- 404 Not Found: Requested entry does not exist.
+ 401 Unauthorized: Session is not authorized for this user, or current password is wrong.
+ 422 Unprocessable Entity: New password breaks a rule.
 */
 
-#[derive(Serialize)]
-struct EntryDetail {
-    created: String,
-    title: String,
-    body: String,
+#[derive(Deserialize)]
+struct PasswordChange {
+    current_password: String,
+    new_password: String,
 }
 
-#[get("/api/users/{user_uuid}/entries/{entry_uuid}")]
-async fn entry_detail(
+#[put("/api/users/{user_uuid}/password")]
+async fn password_change(
     session: Session,
-    req: HttpRequest,
     db_pool: web::Data<PgPool>,
-    path: web::Path<(String, String)>) -> Result<HttpResponse, Error>
+    policy: web::Data<PasswordPolicy>,
+    info: web::Json<PasswordChange>) -> Result<HttpResponse, Error>
 {
-    let (_, entry_uuid) = path.into_inner();
-    let entry = Entry::by_uuid(&db_pool, &entry_uuid, &session.user_uuid).await?;
-
-    let response = web::Json(EntryDetail {
-        created: entry.created,
-        title: entry.title,
-        body: entry.body,
-    }).respond_to(&req).map_into_boxed_body();
+    let user = User::by_uuid(&db_pool, &session.user_uuid).await?;
+    user.change_password(&db_pool, &info.current_password, &info.new_password, &session.uuid, &policy).await?;
 
-    Ok(response)
+    Ok(HttpResponse::Ok().finish())
 }
 
 /*
-===== POST /api/users/{user_uuid}/entries =====
+===== POST /api/login/passkey/options =====
 
-This handler creates an entry on success.
+This handler starts a login with a passkey, instead of a password. The 'options' field is
+given to navigator.credentials.get() of the browser as 'publicKey', after decoding its
+base64url fields, and the result is sent to POST /api/login/passkey along with 'ceremony'.
+Passkeys verify the user by themselves, so two-factor authentication is not asked for.
 
-Request JSON example:
+Request JSON example: { "username": "myusername" }
+
+Usernames which do not exist or have no passkeys get decoy options, which look the same
+but can never be answered. Asking for the options is refused while POST /api/login is locked
+out or backing off, without being a failed attempt by itself. Only the latest few logins of
+a user stay open, so older options stop working once more are asked for.
+
+Response JSON example:
 {
-    "title": "My Title",
-    "body": "I did nothing today.",
-    "timezone_offset": -540
+    "ceremony": "0d9f6a3e-54b1-4c0a-9c1d-3c4e8a1b2f70",
+    "options": {
+        "publicKey": {
+            "challenge": "q1Zg...",
+            "timeout": 300000,
+            "rpId": "localhost",
+            "allowCredentials": [{ "type": "public-key", "id": "xR8v..." }],
+            "userVerification": "required"
+        }
+    }
 }
 
-Value of the 'timezone_offset' field is defined with JS getTimezoneOffset() in mind.
-
 Notable HTTP status codes:
- 401 Unauthorized: Session is not authorized for this user.
- 404 Not Found: Requested entry does not exist.
+ 429 Too Many Requests: Too many failed attempts for the username or from the IP address.
 */
 
 #[derive(Deserialize)]
-struct EntryCreate {
-    timezone_offset: i32,
-    title: String,
-    body: String,
+struct PasskeyLoginStart {
+    username: String,
 }
 
-#[post("/api/users/{user_uuid}/entries")]
-async fn entry_create(
-    session: Session,
+#[derive(Serialize)]
+struct PasskeyLoginOptions {
+    ceremony: String,
+    options: RequestChallengeResponse,
+}
+
+#[post("/api/login/passkey/options")]
+async fn login_passkey_options(
+    req: HttpRequest,
     db_pool: web::Data<PgPool>,
-    info: web::Json<EntryCreate>) -> Result<HttpResponse, Error>
+    throttle: web::Data<Throttle>,
+    webauthn: web::Data<Webauthn>,
+    decoys: web::Data<Decoys>,
+    info: web::Json<PasskeyLoginStart>) -> Result<HttpResponse, Error>
 {
-    let entry = Entry::create(
-        &db_pool,
-        info.timezone_offset,
-        &session.user_uuid,
-        &info.title,
-        &info.body).await?;
+    let client = Client::from_request(&req);
+    throttle.check(&db_pool, &info.username, client.ip.as_deref()).await?;
 
-    let entry_path = format!("/api/users/{}/entries/{}", &session.user_uuid, &entry.uuid);
-    Ok(HttpResponse::Created().insert_header(("Location", entry_path)).finish())
+    let (ceremony, options) = Passkey::start_authentication(&db_pool, &webauthn, &decoys, &info.username).await?;
+    Ok(HttpResponse::Ok().json(PasskeyLoginOptions { ceremony, options }))
 }
 
 /*
-===== PATCH /api/users/{user_uuid}/entries/{entry_uuid} =====
+===== POST /api/login/passkey =====
 
-This handler replaces content of an existing entry on success. 
-The 'created' timestamp will not be modified.
+This handler completes a login with a passkey, started by POST /api/login/passkey/options.
+Cookies are sent on success, just like POST /api/login.
 
 Request JSON example:
 {
-    "title": "New Title",
-    "body": "I did nothing yesterday."
+    "ceremony": "0d9f6a3e-54b1-4c0a-9c1d-3c4e8a1b2f70",
+    "credential": {
+        "id": "xR8v...",
+        "rawId": "xR8v...",
+        "type": "public-key",
+        "response": {
+            "authenticatorData": "SZYN...",
+            "clientDataJSON": "eyJ0...",
+            "signature": "MEUC...",
+            "userHandle": "Xoa7..."
+        }
+    },
+    "remember": true
 }
 
+The 'credential' field is the result of navigator.credentials.get(), with binary fields
+encoded in base64url. The 'remember' field works like that of POST /api/login.
+A ceremony can only be used once, within 5 minutes.
+
 Notable HTTP status codes:
- 401 Unauthorized: Session is not authorized for the requested entry.
- 404 Not Found: Requested entry does not exist.
+ 401 Unauthorized: Passkey cannot be verified, or ceremony has expired or does not exist.
+                   Either gets the same response.
+ 403 Forbidden: Account is disabled.
 */
 
 #[derive(Deserialize)]
-struct EntryUpdate {
-    title: String,
-    body: String,
+struct PasskeyLogin {
+    ceremony: String,
+    credential: PublicKeyCredential,
+    #[serde(default)]
+    remember: bool,
 }
 
-#[patch("/api/users/{user_uuid}/entries/{entry_uuid}")]
-async fn entry_update(
-    session: Session,
+#[post("/api/login/passkey")]
+async fn login_passkey(
+    req: HttpRequest,
     db_pool: web::Data<PgPool>,
-    info: web::Json<EntryUpdate>,
-    path: web::Path<(String, String)>) -> Result<HttpResponse, Error>
+    lifetimes: web::Data<Lifetimes>,
+    webauthn: web::Data<Webauthn>,
+    info: web::Json<PasskeyLogin>) -> Result<HttpResponse, Error>
 {
-    let (_, entry_uuid) = path.into_inner();
+    let user_uuid = Passkey::finish_authentication(&db_pool, &webauthn, &info.ceremony, &info.credential).await?;
 
-    let entry = Entry::by_uuid(&db_pool, &entry_uuid, &session.user_uuid).await?;
-    entry.update(&db_pool, &info.title, &info.body).await?;
+    let client = Client::from_request(&req);
+    let session = Session::create(&db_pool, &user_uuid, &client, info.remember, &lifetimes).await?;
 
-    Ok(HttpResponse::Ok().finish())
+    Ok(login_response(&session, info.remember, &lifetimes))
 }
 
 /*
-===== DELETE /api/users/{user_uuid}/entries/{entry_uuid} =====
+===== POST /api/password-reset =====
+
+This handler sets a new password on success, after an administrator has forced a reset
+through POST /api/admin/users/{user_uuid}/password-reset. The user proves the current
+password, and can then log in with the new one. Two-factor authentication is still asked for then.
 
-This handler deletes an entry on success.
+Request JSON example: { "username": "myusername", "current_password": "mypassword", "password": "mynewpassword" }
+
+Wrong passwords count as failed attempts of POST /api/login as well. New passwords follow
+the rules of POST /api/users, break them likewise, and should differ from the current one.
 
 Notable HTTP status codes:
- 401 Unauthorized: Session is not authorized for the requested entry.
- 404 Not Found: Requested entry does not exist.
+ 401 Unauthorized: Username and/or current password is wrong. Either gets the same response.
+ 403 Forbidden: Account is waiting for approval or disabled. Only told when the password is right.
+ 409 Conflict: Password does not have to be reset. Only told when the password is right.
+ 422 Unprocessable Entity: New password breaks a rule.
+ 429 Too Many Requests: Too many failed attempts for the username or from the IP address.
 */
 
-#[delete("/api/users/{user_uuid}/entries/{entry_uuid}")]
-async fn entry_delete(
-    session: Session,
+#[derive(Deserialize)]
+struct PasswordReset {
+    username: String,
+    current_password: String,
+    password: String,
+}
+
+#[post("/api/password-reset")]
+async fn password_reset(
+    req: HttpRequest,
     db_pool: web::Data<PgPool>,
-    path: web::Path<(String, String)>) -> Result<HttpResponse, Error>
+    throttle: web::Data<Throttle>,
+    policy: web::Data<PasswordPolicy>,
+    info: web::Json<PasswordReset>) -> Result<HttpResponse, Error>
 {
-    let (_, entry_uuid) = path.into_inner();
+    let client = Client::from_request(&req);
+    throttle.attempt(&db_pool, &info.username, client.ip.as_deref()).await?;
+
+    let reset_result = User::reset_password(&db_pool, &info.username, &info.current_password, &info.password, &policy).await;
+    if let Err(error) = reset_result {
+        match error.as_response_error().status_code().as_u16() {
+            401 => throttle.record_failure(&db_pool, &info.username, client.ip.as_deref()).await?,
+            _ => throttle.release(&db_pool, &info.username, client.ip.as_deref()).await?
+        }
+        return Err(error);
+    }
+
+    throttle.record_success(&db_pool, &info.username, client.ip.as_deref()).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/*
+===== POST /api/users/{user_uuid}/totp =====
+
+This handler starts setting up TOTP two-factor authentication on success. The secret is
+given to an authenticator app, usually by showing 'uri' as a QR code. It takes effect once
+a code from the app is verified through PUT /api/users/{user_uuid}/totp.
+Starting over replaces a secret which has not been verified yet.
+
+Request JSON example: { "password": "mypassword" }
+
+Response JSON example:
+{
+    "secret": "JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP",
+    "uri": "otpauth://totp/Centinote:myusername?secret=JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP&issuer=Centinote&algorithm=SHA1&digits=6&period=30"
+}
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session is not authorized for this user, or password is wrong.
+ 409 Conflict: Two-factor authentication is already enabled.
+*/
+
+#[derive(Deserialize)]
+struct TotpStart {
+    password: String,
+}
+
+#[derive(Serialize)]
+struct TotpEnrollment {
+    secret: String,
+    uri: String,
+}
+
+#[post("/api/users/{user_uuid}/totp")]
+async fn totp_start(
+    session: Session,
+    db_pool: web::Data<PgPool>,
+    info: web::Json<TotpStart>) -> Result<HttpResponse, Error>
+{
+    let user = User::by_uuid(&db_pool, &session.user_uuid).await?;
+    let enrollment = user.start_totp(&db_pool, &info.password).await?;
+
+    Ok(HttpResponse::Ok().json(TotpEnrollment {
+        secret: enrollment.secret,
+        uri: enrollment.uri,
+    }))
+}
+
+/*
+===== PUT /api/users/{user_uuid}/totp =====
+
+This handler enables TOTP two-factor authentication on success, once the first code from
+the authenticator app is verified. Recovery codes are given, which can be used instead of
+a code once each, when the app is lost. They are only ever shown in this response.
+
+Request JSON example: { "code": "123456" }
+
+Response JSON example:
+{
+    "recovery_codes": ["k3fm-9xqa-7tbe-2wnp", "..."]
+}
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session is not authorized for this user, or code is wrong.
+ 409 Conflict: Two-factor authentication is already enabled, or has not been set up.
+*/
+
+#[derive(Deserialize)]
+struct TotpEnable {
+    code: String,
+}
+
+#[derive(Serialize)]
+struct TotpEnabled {
+    recovery_codes: Vec<String>,
+}
+
+#[put("/api/users/{user_uuid}/totp")]
+async fn totp_enable(
+    session: Session,
+    db_pool: web::Data<PgPool>,
+    info: web::Json<TotpEnable>) -> Result<HttpResponse, Error>
+{
+    let user = User::by_uuid(&db_pool, &session.user_uuid).await?;
+    let recovery_codes = user.enable_totp(&db_pool, &info.code).await?;
+
+    Ok(HttpResponse::Ok().json(TotpEnabled { recovery_codes }))
+}
+
+/*
+===== DELETE /api/users/{user_uuid}/totp =====
+
+This handler disables TOTP two-factor authentication on success, along with the recovery codes.
+
+Request JSON example: { "password": "mypassword", "code": "123456" }
+
+The 'code' field is either the current code of the authenticator app, or a recovery code.
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session is not authorized for this user, or password or code is wrong.
+ 409 Conflict: Two-factor authentication is not enabled.
+*/
+
+#[derive(Deserialize)]
+struct TotpDisable {
+    password: String,
+    code: String,
+}
+
+#[delete("/api/users/{user_uuid}/totp")]
+async fn totp_disable(
+    session: Session,
+    db_pool: web::Data<PgPool>,
+    info: web::Json<TotpDisable>) -> Result<HttpResponse, Error>
+{
+    let user = User::by_uuid(&db_pool, &session.user_uuid).await?;
+    user.disable_totp(&db_pool, &info.password, &info.code).await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/*
+===== POST /api/users/{user_uuid}/passkeys/options =====
+
+This handler starts registering a passkey. The 'options' field is given to
+navigator.credentials.create() of the browser as 'publicKey', after decoding its base64url
+fields, and the result is sent to POST /api/users/{user_uuid}/passkeys along with 'ceremony'.
+
+Request JSON example: { "password": "mypassword" }
+
+Response JSON example:
+{
+    "ceremony": "5c3e1f0a-7b2d-4e9a-8f61-2d0c9b4a7e13",
+    "options": {
+        "publicKey": {
+            "rp": { "id": "localhost", "name": "Centinote" },
+            "user": { "id": "Xoa7...", "name": "myusername", "displayName": "myusername" },
+            "challenge": "k9Tq...",
+            "pubKeyCredParams": [{ "type": "public-key", "alg": -7 }, { "type": "public-key", "alg": -257 }],
+            "timeout": 300000,
+            "excludeCredentials": [],
+            "authenticatorSelection": {
+                "residentKey": "discouraged",
+                "requireResidentKey": false,
+                "userVerification": "required"
+            },
+            "attestation": "none"
+        }
+    }
+}
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session is not authorized for this user, or password is wrong.
+*/
+
+#[derive(Deserialize)]
+struct PasskeyRegistrationStart {
+    password: String,
+}
+
+#[derive(Serialize)]
+struct PasskeyRegistrationOptions {
+    ceremony: String,
+    options: CreationChallengeResponse,
+}
+
+#[post("/api/users/{user_uuid}/passkeys/options")]
+async fn passkey_options(
+    session: Session,
+    db_pool: web::Data<PgPool>,
+    webauthn: web::Data<Webauthn>,
+    info: web::Json<PasskeyRegistrationStart>) -> Result<HttpResponse, Error>
+{
+    let user = User::by_uuid(&db_pool, &session.user_uuid).await?;
+    let (ceremony, options) = Passkey::start_registration(&db_pool, &webauthn, &user, &info.password).await?;
+
+    Ok(HttpResponse::Ok().json(PasskeyRegistrationOptions { ceremony, options }))
+}
+
+/*
+===== POST /api/users/{user_uuid}/passkeys =====
+
+This handler registers a passkey on success, started by POST /api/users/{user_uuid}/passkeys/options.
+
+Request JSON example:
+{
+    "ceremony": "5c3e1f0a-7b2d-4e9a-8f61-2d0c9b4a7e13",
+    "name": "My phone",
+    "credential": {
+        "id": "xR8v...",
+        "rawId": "xR8v...",
+        "type": "public-key",
+        "response": {
+            "attestationObject": "o2Nm...",
+            "clientDataJSON": "eyJ0..."
+        }
+    }
+}
+
+The 'credential' field is the result of navigator.credentials.create(), with binary fields
+encoded in base64url. A ceremony can only be used once, within 5 minutes.
+
+Notable HTTP status codes:
+ 400 Bad Request: Name is empty or too long, passkey cannot be verified, or ceremony belongs to another user.
+ 401 Unauthorized: Session is not authorized for this user, or ceremony has expired or does not exist.
+ 409 Conflict: Passkey is already registered.
+*/
+
+#[derive(Deserialize)]
+struct PasskeyCreate {
+    ceremony: String,
+    name: String,
+    credential: RegisterPublicKeyCredential,
+}
+
+#[post("/api/users/{user_uuid}/passkeys")]
+async fn passkey_create(
+    session: Session,
+    db_pool: web::Data<PgPool>,
+    webauthn: web::Data<Webauthn>,
+    info: web::Json<PasskeyCreate>) -> Result<HttpResponse, Error>
+{
+    let passkey = Passkey::finish_registration(
+        &db_pool,
+        &webauthn,
+        &session.user_uuid,
+        &info.ceremony,
+        &info.name,
+        &info.credential).await?;
+
+    let passkey_path = format!("/api/users/{}/passkeys/{}", passkey.user_uuid, passkey.uuid);
+    Ok(HttpResponse::Created().insert_header(("Location", passkey_path)).finish())
+}
+
+/*
+===== GET /api/users/{user_uuid}/passkeys =====
+
+This handler responds with every passkey of the user on success, recently registered first.
+
+Response JSON example:
+{
+    "uuid": ["7c1b2e4d-9a3f-4d6e-8b5a-1f0e2d3c4b5a"],
+    "name": ["My phone"],
+    "created": ["2023-01-07T06:29:16.035754+00:00"],
+    "last_used": [null]
+}
+
+The 'last_used' field is null for passkeys never used to log in.
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session is not authorized for this user.
+*/
+
+#[derive(Serialize)]
+struct PasskeyList {
+    uuid: Vec<String>,
+    name: Vec<String>,
+    created: Vec<String>,
+    last_used: Vec<Option<String>>,
+}
+
+#[get("/api/users/{user_uuid}/passkeys")]
+async fn passkey_list(
+    session: Session,
+    req: HttpRequest,
+    db_pool: web::Data<PgPool>) -> Result<HttpResponse, Error>
+{
+    let passkeys = Passkey::list_by_user(&db_pool, &session.user_uuid).await?;
+
+    let response = web::Json(PasskeyList {
+        uuid: passkeys.iter().map(|passkey| passkey.uuid.clone()).collect(),
+        name: passkeys.iter().map(|passkey| passkey.name.clone()).collect(),
+        created: passkeys.iter().map(|passkey| passkey.created.clone()).collect(),
+        last_used: passkeys.iter().map(|passkey| passkey.last_used.clone()).collect(),
+    }).respond_to(&req).map_into_boxed_body();
+
+    Ok(response)
+}
+
+/*
+===== DELETE /api/users/{user_uuid}/passkeys/{passkey_uuid} =====
+
+This handler removes a passkey on success. It can no longer be used to log in.
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session is not authorized for this user.
+ 404 Not Found: Passkey does not exist.
+*/
+
+#[delete("/api/users/{user_uuid}/passkeys/{passkey_uuid}")]
+async fn passkey_delete(
+    session: Session,
+    db_pool: web::Data<PgPool>,
+    path: web::Path<(String, String)>) -> Result<HttpResponse, Error>
+{
+    let (_, passkey_uuid) = path.into_inner();
+
+    Passkey::delete(&db_pool, &passkey_uuid, &session.user_uuid).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/*
+===== POST /api/users/{user_uuid}/sessions/{session_uuid} =====
+
+Refresh the specified session's lifetime. 
+Session's expiry timestamp will be its idle lifetime from now on success, but never later than
+its absolute expiry. Every authenticated request does the same, so this is only needed to keep
+an otherwise unused session alive, or to check whether it is still valid.
+A session is only authorized for it's own refresh.
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session is not authorized for refreshing the specified session.
+*/
+
+#[post("/api/users/{user_uuid}/sessions/{session_uuid}")]
+async fn session_refresh(
+    session: Session,
+    db_pool: web::Data<PgPool>,
+    lifetimes: web::Data<Lifetimes>,
+    path: web::Path<(String, String)>) -> Result<HttpResponse, Error>
+{
+    let (_, target_session_uuid) = path.into_inner();
+
+    if session.uuid == target_session_uuid {
+        session.refresh(&db_pool, &lifetimes).await?;
+        Ok(HttpResponse::Ok().finish())
+    } else {
+        Err(ErrorUnauthorized("Session UUIDs does not match."))
+    }
+}
+
+/*
+===== POST /api/users/{user_uuid}/tokens =====
+
+This handler creates a personal access token on success, for scripts and other clients
+which cannot log in. Requests with 'Authorization: Bearer {token}' header are authenticated
+with the token instead of the 'auth' cookie.
+
+Request JSON example:
+{
+    "name": "Daily summary script",
+    "scopes": ["entries:read", "entries:write"],
+    "lifetime_days": 90
+}
+
+Scopes:
+ entries:read: Reading entries, tags, attachments and exports.
+ entries:write: Creating, changing and deleting those, along with importing. Implies reading.
+ admin: Everything, including the profile, password, sessions and access tokens.
+
+The 'lifetime_days' field is optional, between 1 and 3650. Tokens without it never expire.
+
+Response JSON example:
+{
+    "uuid": "8b0c3e39-5f2e-4a39-a0f5-0c1e0e5a8c11",
+    "token": "cnpat_Q2xpZW50U2VjcmV0U2hvd25Pbmx5T25jZVRvVGhlVXNlcg"
+}
+
+The 'token' field is only ever shown in this response.
+
+Notable HTTP status codes:
+ 400 Bad Request: Name is empty or too long, a scope is unknown or there is none, or lifetime is out of range.
+ 401 Unauthorized: Session is not authorized for this user.
+ 403 Forbidden: Access token making the request lacks the 'admin' scope.
+*/
+
+#[derive(Deserialize)]
+struct TokenCreate {
+    name: String,
+    scopes: Vec<String>,
+    lifetime_days: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct TokenCreated {
+    uuid: String,
+    token: String,
+}
+
+#[post("/api/users/{user_uuid}/tokens")]
+async fn token_create(
+    session: Session,
+    db_pool: web::Data<PgPool>,
+    info: web::Json<TokenCreate>) -> Result<HttpResponse, Error>
+{
+    let (access_token, secret) = AccessToken::create(
+        &db_pool,
+        &session.user_uuid,
+        &info.name,
+        &info.scopes,
+        info.lifetime_days).await?;
+
+    let token_path = format!("/api/users/{}/tokens/{}", access_token.user_uuid, access_token.uuid);
+    let response = HttpResponse::Created()
+        .insert_header(("Location", token_path))
+        .json(TokenCreated {
+            uuid: access_token.uuid,
+            token: secret,
+        });
+
+    Ok(response)
+}
+
+/*
+===== GET /api/users/{user_uuid}/tokens =====
+
+This handler responds with every personal access token of the user on success,
+recently created first. Secrets of the tokens are not included.
+
+Response JSON example:
+{
+    "uuid": ["8b0c3e39-5f2e-4a39-a0f5-0c1e0e5a8c11"],
+    "name": ["Daily summary script"],
+    "scopes": [["entries:read", "entries:write"]],
+    "created": ["2023-01-07T06:29:16.035754+00:00"],
+    "last_used": [null],
+    "expiry": ["2023-04-07T06:29:16.035754+00:00"]
+}
+
+The 'last_used' field is null for tokens never used, and 'expiry' for tokens which never expire.
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session is not authorized for this user.
+ 403 Forbidden: Access token making the request lacks the 'admin' scope.
+*/
+
+#[derive(Serialize)]
+struct TokenList {
+    uuid: Vec<String>,
+    name: Vec<String>,
+    scopes: Vec<Vec<String>>,
+    created: Vec<String>,
+    last_used: Vec<Option<String>>,
+    expiry: Vec<Option<String>>,
+}
+
+#[get("/api/users/{user_uuid}/tokens")]
+async fn token_list(
+    session: Session,
+    req: HttpRequest,
+    db_pool: web::Data<PgPool>) -> Result<HttpResponse, Error>
+{
+    let tokens = AccessToken::list_by_user(&db_pool, &session.user_uuid).await?;
+
+    let response = web::Json(TokenList {
+        uuid: tokens.iter().map(|token| token.uuid.clone()).collect(),
+        name: tokens.iter().map(|token| token.name.clone()).collect(),
+        scopes: tokens.iter().map(|token| token.scopes.clone()).collect(),
+        created: tokens.iter().map(|token| token.created.clone()).collect(),
+        last_used: tokens.iter().map(|token| token.last_used.clone()).collect(),
+        expiry: tokens.iter().map(|token| token.expiry.clone()).collect(),
+    }).respond_to(&req).map_into_boxed_body();
+
+    Ok(response)
+}
+
+/*
+===== DELETE /api/users/{user_uuid}/tokens/{token_uuid} =====
+
+This handler revokes a personal access token on success.
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session is not authorized for this user.
+ 403 Forbidden: Access token making the request lacks the 'admin' scope.
+ 404 Not Found: Access token does not exist.
+*/
+
+#[delete("/api/users/{user_uuid}/tokens/{token_uuid}")]
+async fn token_delete(
+    session: Session,
+    db_pool: web::Data<PgPool>,
+    path: web::Path<(String, String)>) -> Result<HttpResponse, Error>
+{
+    let (_, token_uuid) = path.into_inner();
+
+    AccessToken::revoke(&db_pool, &token_uuid, &session.user_uuid).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/*
+===== GET /api/users/{user_uuid}/sessions =====
+
+This handler responds with every session of the user on success, recently used first.
+
+Response JSON example:
+{
+    "uuid": ["c1d4a3a4-04c4-4bd3-8e8c-3b0ba4c2c6a0", "0f6b2c1e-93b7-4a52-9df3-6a4c59b3f1d2"],
+    "created": ["2023-01-07T06:29:16.035754+00:00", "2023-01-05T11:02:40.120044+00:00"],
+    "last_used": ["2023-01-07T07:12:03.501021+00:00", "2023-01-05T11:20:13.004211+00:00"],
+    "expiry": ["2023-01-07T07:42:03.501021+00:00", "2023-01-05T11:50:13.004211+00:00"],
+    "user_agent": ["Mozilla/5.0 (X11; Linux x86_64) ...", null],
+    "ip": ["203.0.113.7", "198.51.100.20"],
+    "current": [true, false]
+}
+
+The 'current' field tells which session made the request.
+The 'last_used' field is updated at most once a minute.
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session is not authorized for this user.
+*/
+
+#[derive(Serialize)]
+struct SessionList {
+    uuid: Vec<String>,
+    created: Vec<String>,
+    last_used: Vec<String>,
+    expiry: Vec<String>,
+    user_agent: Vec<Option<String>>,
+    ip: Vec<Option<String>>,
+    current: Vec<bool>,
+}
+
+#[get("/api/users/{user_uuid}/sessions")]
+async fn session_list(
+    session: Session,
+    req: HttpRequest,
+    db_pool: web::Data<PgPool>) -> Result<HttpResponse, Error>
+{
+    let sessions = Session::list_by_user(&db_pool, &session.user_uuid).await?;
+
+    let response = web::Json(SessionList {
+        current: sessions.iter().map(|detail| detail.uuid == session.uuid).collect(),
+        uuid: sessions.iter().map(|detail| detail.uuid.clone()).collect(),
+        created: sessions.iter().map(|detail| detail.created.clone()).collect(),
+        last_used: sessions.iter().map(|detail| detail.last_used.clone()).collect(),
+        expiry: sessions.iter().map(|detail| detail.expiry.clone()).collect(),
+        user_agent: sessions.iter().map(|detail| detail.user_agent.clone()).collect(),
+        ip: sessions.iter().map(|detail| detail.ip.clone()).collect(),
+    }).respond_to(&req).map_into_boxed_body();
+
+    Ok(response)
+}
+
+/*
+===== DELETE /api/users/{user_uuid}/sessions =====
+
+This handler deletes every session of the user except the one making the request.
+Useful for logging out of other devices.
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session is not authorized for this user.
+*/
+
+#[delete("/api/users/{user_uuid}/sessions")]
+async fn session_delete_others(
+    session: Session,
+    db_pool: web::Data<PgPool>) -> Result<HttpResponse, Error>
+{
+    session.revoke_others(&db_pool).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/*
+===== DELETE /api/users/{user_uuid}/sessions/{session_uuid} =====
+
+Delete the specified session on success. Useful for logging out, when the session
+deletes itself, or for revoking another session of the same user.
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session is not authorized for this user.
+ 404 Not Found: Specified session does not exist.
+*/
+
+#[delete("/api/users/{user_uuid}/sessions/{session_uuid}")]
+async fn session_delete(
+    session: Session,
+    db_pool: web::Data<PgPool>,
+    path: web::Path<(String, String)>) -> Result<HttpResponse, Error>
+{
+    let (_, target_session_uuid) = path.into_inner();
+
+    session.revoke(&db_pool, &target_session_uuid).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/*
+===== GET /api/users/{user_uuid}/entries?limit={n}&before={cursor}&after={cursor}&full_content={true/false}&tags={tags}&tag_mode={all/any} =====
+
+This handler on success, responds with content of a page of entries, recent first.
+
+Query parameters (all optional):
+ limit: Number of entries in a page. Defaults to 20, at most 100.
+ before: Only list entries older than the cursor.
+ after: Only list entries newer than the cursor.
+ full_content: Defaults to true. When false, bodies are replaced with a summary.
+ tags: Comma separated tag names. Only entries with the tags are listed.
+ tag_mode: Defaults to all. 'all' lists entries with every tag, 'any' with at least one of them.
+
+Pass 'next_cursor' of a response as 'before' to get the next page of older entries.
+When 'after' is used instead, 'next_cursor' should be passed as 'after' to keep going 
+towards newer entries. 'next_cursor' is null when there are no more entries.
+
+Response JSON examples: 
+{
+    "uuid": ["5315486c-02ee-4712-9793-b002193d0275", "2deffb77-b215-47b5-a074-ddd4127cc4b5"],
+    "created": ["2023-01-07T07:36:24.014244+09:00", "2023-01-07T06:29:16.035754+09:00"],
+    "title": ["Title 1", "Title 2"],
+    "body": ["Some text here.", "Another text here."],
+    "tags": [["travel", "family"], []],
+    "next_cursor": "1673040556035754_2deffb77-b215-47b5-a074-ddd4127cc4b5"
+}
+
+With full_content=false:
+{
+    "uuid": ["5315486c-02ee-4712-9793-b002193d0275", "2deffb77-b215-47b5-a074-ddd4127cc4b5"],
+    "created": ["2023-01-07T07:36:24.014244+09:00", "2023-01-07T06:29:16.035754+09:00"],
+    "title": ["Title 1", "Title 2"],
+    "excerpt": ["Some text here.", "Another text here."],
+    "body_length": [15, 18],
+    "word_count": [3, 3],
+    "tags": [["travel", "family"], []],
+    "next_cursor": null
+}
+
+The 'excerpt' field holds up to first 200 characters of the body, followed by '…' if cut.
+The 'body_length' field is counted in characters.
+
+Notable HTTP status codes:
+ 400 Bad Request: Malformed cursor, invalid limit or tag filter, or both 'before' and 'after' are given.
+ 401 Unauthorized: Session is not authorized for the requested entry.
+*/
+
+#[derive(Deserialize)]
+struct EntryListQuery {
+    limit: Option<i64>,
+    before: Option<String>,
+    after: Option<String>,
+    full_content: Option<bool>,
+    tags: Option<String>,
+    tag_mode: Option<String>,
+}
+
+#[derive(Serialize)]
+struct EntryList {
+    uuid: Vec<String>,
+    created: Vec<String>,
+    title: Vec<String>,
+    body: Vec<String>,
+    tags: Vec<Vec<String>>,
+    next_cursor: Option<String>,
+}
+
+#[derive(Serialize)]
+struct EntrySummaryList {
+    uuid: Vec<String>,
+    created: Vec<String>,
+    title: Vec<String>,
+    excerpt: Vec<String>,
+    body_length: Vec<i32>,
+    word_count: Vec<i32>,
+    tags: Vec<Vec<String>>,
+    next_cursor: Option<String>,
+}
+
+#[get("/api/users/{user_uuid}/entries")]
+async fn entry_list(
+    session: Session,
+    req: HttpRequest,
+    db_pool: web::Data<PgPool>,
+    query: web::Query<EntryListQuery>) -> Result<HttpResponse, Error> 
+{
+    let limit = query.limit.unwrap_or(list::DEFAULT_PAGE_LIMIT);
+    if !(1..=list::MAX_PAGE_LIMIT).contains(&limit) {
+        return Err(ErrorBadRequest("Limit is out of range."));
+    }
+
+    let cursor = match (&query.before, &query.after) {
+        (None, None) => None,
+        (Some(before), None) => Some((list::Direction::Before, list::Cursor::parse(before)?)),
+        (None, Some(after)) => Some((list::Direction::After, list::Cursor::parse(after)?)),
+        (Some(_), Some(_)) => return Err(ErrorBadRequest("Only one of 'before' and 'after' can be given."))
+    };
+
+    let mode = match query.tag_mode.as_deref() {
+        None | Some("all") => tag::TagMatch::All,
+        Some("any") => tag::TagMatch::Any,
+        Some(_) => return Err(ErrorBadRequest("Tag mode should be either 'all' or 'any'."))
+    };
+
+    let tag_filter = match &query.tags {
+        Some(tags) => {
+            let names: Vec<String> = tags.split(',').map(|name| name.to_string()).collect();
+            Some(tag::TagFilter { names: tag::normalize(&names)?, mode })
+        },
+        None => None
+    };
+
+    if !query.full_content.unwrap_or(true) {
+        let page = Entry::summary_page_by_user(
+            &db_pool,
+            &session.user_uuid,
+            limit,
+            cursor,
+            tag_filter.as_ref()).await?;
+
+        let response = web::Json(EntrySummaryList {
+            uuid: page.entries.iter().map(|entry| entry.uuid.clone()).collect(),
+            created: page.entries.iter().map(|entry| entry.created.clone()).collect(),
+            title: page.entries.iter().map(|entry| entry.title.clone()).collect(),
+            excerpt: page.entries.iter().map(|entry| entry.excerpt.clone()).collect(),
+            body_length: page.entries.iter().map(|entry| entry.body_length).collect(),
+            word_count: page.entries.iter().map(|entry| entry.word_count).collect(),
+            tags: page.entries.iter().map(|entry| entry.tags.clone()).collect(),
+            next_cursor: page.next_cursor,
+        }).respond_to(&req).map_into_boxed_body();
+
+        return Ok(response);
+    }
+
+    let page = Entry::page_by_user(
+        &db_pool,
+        &session.user_uuid,
+        limit,
+        cursor,
+        tag_filter.as_ref()).await?;
+
+    let response = web::Json(EntryList {
+        uuid: page.entries.iter().map(|entry| entry.uuid.clone()).collect(),
+        created: page.entries.iter().map(|entry| entry.created.clone()).collect(),
+        title: page.entries.iter().map(|entry| entry.title.clone()).collect(),
+        body: page.entries.iter().map(|entry| entry.body.clone()).collect(),
+        tags: page.entries.iter().map(|entry| entry.tags.clone()).collect(),
+        next_cursor: page.next_cursor,
+    }).respond_to(&req).map_into_boxed_body();
+
+    Ok(response)
+}
+
+/*
+===== GET /api/users/{user_uuid}/entries/search?q={query}&limit={n}&offset={n} =====
+
+This handler on success, responds with entries matching the query, most relevant first.
+
+Query parameters:
+ q: Search query. Quoted phrases, 'or' and '-' for exclusion are understood.
+ limit: Optional. Number of results. Defaults to 20, at most 100.
+ offset: Optional. Number of results to skip. Defaults to 0.
+
+Response JSON example:
+{
+    "uuid": ["5315486c-02ee-4712-9793-b002193d0275"],
+    "created": ["2023-01-07T06:29:16.035754+09:00"],
+    "title": ["Title 1"],
+    "title_highlight": ["Title 1"],
+    "snippet": ["Went to the <mark>beach</mark> with friends."],
+    "rank": [0.0607927]
+}
+
+'title_highlight' and 'snippet' are HTML. Matching words are wrapped in <mark> tags
+and the rest of the text is escaped.
+
+Notable HTTP status codes:
+ 400 Bad Request: Query is empty, or limit/offset is out of range.
+ 401 Unauthorized: Session is not authorized for this user.
+*/
+
+#[derive(Deserialize)]
+struct EntrySearchQuery {
+    q: String,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct EntrySearchResults {
+    uuid: Vec<String>,
+    created: Vec<String>,
+    title: Vec<String>,
+    title_highlight: Vec<String>,
+    snippet: Vec<String>,
+    rank: Vec<f32>,
+}
+
+#[get("/api/users/{user_uuid}/entries/search")]
+async fn entry_search(
+    session: Session,
+    req: HttpRequest,
+    db_pool: web::Data<PgPool>,
+    query: web::Query<EntrySearchQuery>) -> Result<HttpResponse, Error>
+{
+    let limit = query.limit.unwrap_or(list::DEFAULT_PAGE_LIMIT);
+    if !(1..=list::MAX_PAGE_LIMIT).contains(&limit) {
+        return Err(ErrorBadRequest("Limit is out of range."));
+    }
+
+    let offset = query.offset.unwrap_or(0);
+    if offset < 0 {
+        return Err(ErrorBadRequest("Offset is out of range."));
+    }
+
+    let results = Entry::search(&db_pool, &session.user_uuid, &query.q, limit, offset).await?;
+
+    let response = web::Json(EntrySearchResults {
+        uuid: results.iter().map(|result| result.uuid.clone()).collect(),
+        created: results.iter().map(|result| result.created.clone()).collect(),
+        title: results.iter().map(|result| result.title.clone()).collect(),
+        title_highlight: results.iter().map(|result| result.title_highlight.clone()).collect(),
+        snippet: results.iter().map(|result| result.snippet.clone()).collect(),
+        rank: results.iter().map(|result| result.rank).collect(),
+    }).respond_to(&req).map_into_boxed_body();
+
+    Ok(response)
+}
+
+/*
+===== GET /api/users/{user_uuid}/entries/{entry_uuid} =====
+
+This handler responds with content of an entry on success.
+
+Response JSON example:
+{
+    "created": "2023-01-07T06:29:16.035754+09:00",
+    "inserted_at": "2023-01-08T09:12:40.500012+09:00",
+    "timezone": "Asia/Seoul",
+    "title": "My Title",
+    "body": "I did nothing today.",
+    "tags": ["family", "travel"]
+}
+
+The 'created' field is formated in ISO 8601. It is the date the entry is about, which may
+have been set by the user. The 'inserted_at' field is when the entry was actually written.
+Both are rendered in the timezone of the entry. The 'timezone' field is the IANA time zone
+name of the entry, or null for entries written with only an offset.
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session is not authorized for the requested entry.
+ 404 Not Found: Requested entry does not exist.
+*/
+
+#[derive(Serialize)]
+struct EntryDetail {
+    created: String,
+    inserted_at: String,
+    timezone: Option<String>,
+    title: String,
+    body: String,
+    tags: Vec<String>,
+}
+
+#[get("/api/users/{user_uuid}/entries/{entry_uuid}")]
+async fn entry_detail(
+    session: Session,
+    req: HttpRequest,
+    db_pool: web::Data<PgPool>,
+    path: web::Path<(String, String)>) -> Result<HttpResponse, Error>
+{
+    let (_, entry_uuid) = path.into_inner();
+    let entry = Entry::by_uuid(&db_pool, &entry_uuid, &session.user_uuid).await?;
+
+    let response = web::Json(EntryDetail {
+        created: entry.created,
+        inserted_at: entry.inserted_at,
+        timezone: entry.timezone.name,
+        title: entry.title,
+        body: entry.body,
+        tags: entry.tags,
+    }).respond_to(&req).map_into_boxed_body();
+
+    Ok(response)
+}
+
+/*
+===== POST /api/users/{user_uuid}/entries =====
+
+This handler creates an entry on success.
+
+Request JSON example:
+{
+    "title": "My Title",
+    "body": "I did nothing today.",
+    "timezone": "Asia/Seoul",
+    "created": "2023-01-06T21:00:00+09:00",
+    "tags": ["family", "travel"]
+}
+
+The 'timezone' field is an IANA time zone name. Clients which do not know the name of their
+zone may send 'timezone_offset' instead, defined with JS getTimezoneOffset() in mind.
+When neither is given, the default timezone in the settings of the user is used.
+The 'created' field is optional and backdates the entry. It is an RFC 3339 timestamp, whose
+offset takes the place of 'timezone_offset'. When omitted, the current time is used.
+The 'tags' field is optional. Tags which do not exist yet are created.
+
+Notable HTTP status codes:
+ 400 Bad Request: The 'created' field is malformed, before 1900 or more than a day ahead.
+ 400 Bad Request: The 'timezone' field is not a known zone, 'timezone_offset' is out of range,
+                  or neither is given without a default timezone.
+ 400 Bad Request: A tag name is empty, too long or contains a comma, or there are too many tags.
+ 401 Unauthorized: Session is not authorized for this user.
+*/
+
+#[derive(Deserialize)]
+struct EntryCreate {
+    timezone: Option<String>,
+    timezone_offset: Option<i32>,
+    created: Option<String>,
+    title: String,
+    body: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[post("/api/users/{user_uuid}/entries")]
+async fn entry_create(
+    session: Session,
+    db_pool: web::Data<PgPool>,
+    info: web::Json<EntryCreate>) -> Result<HttpResponse, Error>
+{
+    let timezone_name = match (&info.timezone, info.timezone_offset) {
+        (None, None) => {
+            let user = User::by_uuid(&db_pool, &session.user_uuid).await?;
+            user.settings(&db_pool).await?.timezone
+        },
+        (timezone, _) => timezone.clone()
+    };
+
+    let (created_utc, timezone) = entry::utils::resolve_created(
+        info.created.as_deref(),
+        timezone_name.as_deref(),
+        info.timezone_offset)?;
+
+    let entry = Entry::create(
+        &db_pool,
+        created_utc,
+        timezone,
+        &session.user_uuid,
+        &info.title,
+        &info.body,
+        &info.tags).await?;
+
+    let entry_path = format!("/api/users/{}/entries/{}", &session.user_uuid, &entry.uuid);
+    Ok(HttpResponse::Created().insert_header(("Location", entry_path)).finish())
+}
+
+/*
+===== PATCH /api/users/{user_uuid}/entries/{entry_uuid} =====
+
+This handler replaces content of an existing entry on success. 
+
+Request JSON example:
+{
+    "title": "New Title",
+    "body": "I did nothing yesterday.",
+    "created": "2023-01-05T08:30:00+09:00",
+    "timezone": "Asia/Seoul",
+    "tags": ["family"]
+}
+
+The 'created' and 'timezone' fields are optional, in the same format as when creating an entry.
+When both are omitted, the date and timezone of the entry are left untouched.
+With only 'created', the entry takes the offset of the timestamp and loses its zone name.
+With only 'timezone', the entry keeps its date and is rendered in the new zone.
+The 'tags' field is optional. When omitted, tags of the entry are left untouched.
+
+Notable HTTP status codes:
+ 400 Bad Request: The 'created' field is malformed, before 1900 or more than a day ahead.
+ 400 Bad Request: The 'timezone' field is not a known zone.
+ 400 Bad Request: A tag name is empty, too long or contains a comma, or there are too many tags.
+ 401 Unauthorized: Session is not authorized for the requested entry.
+ 404 Not Found: Requested entry does not exist.
+*/
+
+#[derive(Deserialize)]
+struct EntryUpdate {
+    title: String,
+    body: String,
+    created: Option<String>,
+    timezone: Option<String>,
+    tags: Option<Vec<String>>,
+}
+
+#[patch("/api/users/{user_uuid}/entries/{entry_uuid}")]
+async fn entry_update(
+    session: Session,
+    db_pool: web::Data<PgPool>,
+    info: web::Json<EntryUpdate>,
+    path: web::Path<(String, String)>) -> Result<HttpResponse, Error>
+{
+    let (_, entry_uuid) = path.into_inner();
+
+    let entry = Entry::by_uuid(&db_pool, &entry_uuid, &session.user_uuid).await?;
+
+    let created = match (&info.created, &info.timezone) {
+        (None, None) => None,
+        (created, timezone) => Some(entry::utils::resolve_created(
+            Some(created.as_deref().unwrap_or(&entry.created)),
+            timezone.as_deref(),
+            None)?)
+    };
+
+    entry.update(&db_pool, &info.title, &info.body, info.tags.as_deref(), created).await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/*
+===== DELETE /api/users/{user_uuid}/entries/{entry_uuid} =====
+
+This handler moves an entry to the trash on success.
+Entries in the trash are permanently deleted after a retention period set by the server admin.
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session is not authorized for the requested entry.
+ 404 Not Found: Requested entry does not exist.
+*/
+
+#[delete("/api/users/{user_uuid}/entries/{entry_uuid}")]
+async fn entry_delete(
+    session: Session,
+    db_pool: web::Data<PgPool>,
+    path: web::Path<(String, String)>) -> Result<HttpResponse, Error>
+{
+    let (_, entry_uuid) = path.into_inner();
+
+    let entry = Entry::by_uuid(&db_pool, &entry_uuid, &session.user_uuid).await?;
+    entry.delete(&db_pool).await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/*
+===== GET /api/users/{user_uuid}/entries/trash =====
+
+This handler on success, responds with entries in the trash, recently deleted first.
+
+Response JSON example:
+{
+    "uuid": ["5315486c-02ee-4712-9793-b002193d0275"],
+    "created": ["2023-01-07T06:29:16.035754+09:00"],
+    "deleted_at": ["2023-01-09T21:03:44.671002+09:00"],
+    "title": ["Title 1"],
+    "body": ["Some text here."]
+}
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session is not authorized for this user.
+*/
+
+#[derive(Serialize)]
+struct TrashList {
+    uuid: Vec<String>,
+    created: Vec<String>,
+    deleted_at: Vec<String>,
+    title: Vec<String>,
+    body: Vec<String>,
+}
+
+#[get("/api/users/{user_uuid}/entries/trash")]
+async fn trash_list(
+    session: Session,
+    req: HttpRequest,
+    db_pool: web::Data<PgPool>) -> Result<HttpResponse, Error>
+{
+    let trashed = Entry::trashed_by_user(&db_pool, &session.user_uuid).await?;
+
+    let response = web::Json(TrashList {
+        uuid: trashed.iter().map(|trashed| trashed.entry.uuid.clone()).collect(),
+        created: trashed.iter().map(|trashed| trashed.entry.created.clone()).collect(),
+        deleted_at: trashed.iter().map(|trashed| trashed.deleted_at.clone()).collect(),
+        title: trashed.iter().map(|trashed| trashed.entry.title.clone()).collect(),
+        body: trashed.iter().map(|trashed| trashed.entry.body.clone()).collect(),
+    }).respond_to(&req).map_into_boxed_body();
+
+    Ok(response)
+}
+
+/*
+===== POST /api/users/{user_uuid}/entries/trash/{entry_uuid}/restore =====
+
+This handler moves an entry out of the trash on success.
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session is not authorized for the requested entry.
+ 404 Not Found: Requested entry is not in the trash.
+*/
+
+#[post("/api/users/{user_uuid}/entries/trash/{entry_uuid}/restore")]
+async fn trash_restore(
+    session: Session,
+    db_pool: web::Data<PgPool>,
+    path: web::Path<(String, String)>) -> Result<HttpResponse, Error>
+{
+    let (_, entry_uuid) = path.into_inner();
+
+    let trashed = Entry::trashed_by_uuid(&db_pool, &entry_uuid, &session.user_uuid).await?;
+    trashed.recover(&db_pool).await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/*
+===== DELETE /api/users/{user_uuid}/entries/trash/{entry_uuid} =====
+
+This handler permanently deletes an entry in the trash along with its attachments on success.
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session is not authorized for the requested entry.
+ 404 Not Found: Requested entry is not in the trash.
+*/
+
+#[delete("/api/users/{user_uuid}/entries/trash/{entry_uuid}")]
+async fn trash_delete(
+    session: Session,
+    db_pool: web::Data<PgPool>,
+    storage: web::Data<Storage>,
+    path: web::Path<(String, String)>) -> Result<HttpResponse, Error>
+{
+    let (_, entry_uuid) = path.into_inner();
+
+    let trashed = Entry::trashed_by_uuid(&db_pool, &entry_uuid, &session.user_uuid).await?;
+    trashed.purge(&db_pool, &storage).await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/*
+===== GET /api/users/{user_uuid}/entries/{entry_uuid}/revisions =====
+
+This handler on success, responds with previous contents of an entry, recent first.
+A revision is recorded every time the entry is updated.
+
+Response JSON example:
+{
+    "uuid": ["0b0a4a4e-8f6a-4c55-9d0f-3c7e0b8f2d11", "8d3b2f5e-77a1-4d0e-b1f2-3a9c5e6d7f80"],
+    "created": ["2023-01-08T10:02:11.120044+09:00", "2023-01-07T08:12:40.500012+09:00"],
+    "title": ["Title 2", "Title 1"]
+}
+
+The 'created' field is when the revision was replaced by a newer content.
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session is not authorized for the requested entry.
+ 404 Not Found: Requested entry does not exist.
+*/
+
+#[derive(Serialize)]
+struct RevisionList {
+    uuid: Vec<String>,
+    created: Vec<String>,
+    title: Vec<String>,
+}
+
+#[get("/api/users/{user_uuid}/entries/{entry_uuid}/revisions")]
+async fn revision_list(
+    session: Session,
+    req: HttpRequest,
+    db_pool: web::Data<PgPool>,
+    path: web::Path<(String, String)>) -> Result<HttpResponse, Error>
+{
+    let (_, entry_uuid) = path.into_inner();
+
+    let entry = Entry::by_uuid(&db_pool, &entry_uuid, &session.user_uuid).await?;
+    let revisions = entry.revisions(&db_pool).await?;
+
+    let response = web::Json(RevisionList {
+        uuid: revisions.iter().map(|revision| revision.uuid.clone()).collect(),
+        created: revisions.iter().map(|revision| revision.created.clone()).collect(),
+        title: revisions.iter().map(|revision| revision.title.clone()).collect(),
+    }).respond_to(&req).map_into_boxed_body();
+
+    Ok(response)
+}
+
+/*
+===== GET /api/users/{user_uuid}/entries/{entry_uuid}/revisions/{revision_uuid} =====
+
+This handler responds with content of a revision on success.
+
+Response JSON example:
+{
+    "created": "2023-01-08T10:02:11.120044+09:00",
+    "title": "Old Title",
+    "body": "I did nothing today."
+}
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session is not authorized for the requested entry.
+ 404 Not Found: Requested entry or revision does not exist.
+*/
+
+#[derive(Serialize)]
+struct RevisionDetail {
+    created: String,
+    title: String,
+    body: String,
+}
+
+#[get("/api/users/{user_uuid}/entries/{entry_uuid}/revisions/{revision_uuid}")]
+async fn revision_detail(
+    session: Session,
+    req: HttpRequest,
+    db_pool: web::Data<PgPool>,
+    path: web::Path<(String, String, String)>) -> Result<HttpResponse, Error>
+{
+    let (_, entry_uuid, revision_uuid) = path.into_inner();
+
+    let entry = Entry::by_uuid(&db_pool, &entry_uuid, &session.user_uuid).await?;
+    let revision = entry.revision(&db_pool, &revision_uuid).await?;
+
+    let response = web::Json(RevisionDetail {
+        created: revision.created,
+        title: revision.title,
+        body: revision.body,
+    }).respond_to(&req).map_into_boxed_body();
+
+    Ok(response)
+}
+
+/*
+===== GET /api/users/{user_uuid}/entries/{entry_uuid}/revisions/{revision_uuid}/diff?against={revision_uuid} =====
+
+This handler responds with a line based diff from the revision to another on success.
+When 'against' is omitted, the revision is compared to the current content of the entry.
+
+Response JSON example:
+{
+    "title": [{ "tag": "delete", "text": "Old Title" }, { "tag": "insert", "text": "New Title" }],
+    "body": [
+        { "tag": "equal", "text": "I did nothing today.\n" },
+        { "tag": "insert", "text": "Except writing this.\n" }
+    ]
+}
+
+The 'tag' field is one of "equal", "delete" and "insert".
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session is not authorized for the requested entry.
+ 404 Not Found: Requested entry or revision does not exist.
+*/
+
+#[derive(Deserialize)]
+struct RevisionDiffQuery {
+    against: Option<String>,
+}
+
+#[derive(Serialize)]
+struct DiffChunk {
+    tag: &'static str,
+    text: String,
+}
+
+#[derive(Serialize)]
+struct RevisionDiff {
+    title: Vec<DiffChunk>,
+    body: Vec<DiffChunk>,
+}
+
+fn diff_chunks(old: &str, new: &str) -> Vec<DiffChunk> {
+    revision::diff(old, new).into_iter()
+        .map(|chunk| DiffChunk { tag: chunk.tag, text: chunk.text })
+        .collect()
+}
+
+#[get("/api/users/{user_uuid}/entries/{entry_uuid}/revisions/{revision_uuid}/diff")]
+async fn revision_diff(
+    session: Session,
+    req: HttpRequest,
+    db_pool: web::Data<PgPool>,
+    path: web::Path<(String, String, String)>,
+    query: web::Query<RevisionDiffQuery>) -> Result<HttpResponse, Error>
+{
+    let (_, entry_uuid, revision_uuid) = path.into_inner();
+
+    let entry = Entry::by_uuid(&db_pool, &entry_uuid, &session.user_uuid).await?;
+    let revision = entry.revision(&db_pool, &revision_uuid).await?;
+
+    let (new_title, new_body) = match &query.against {
+        Some(against_uuid) => {
+            let against = entry.revision(&db_pool, against_uuid).await?;
+            (against.title, against.body)
+        },
+        None => (entry.title, entry.body)
+    };
+
+    let response = web::Json(RevisionDiff {
+        title: diff_chunks(&revision.title, &new_title),
+        body: diff_chunks(&revision.body, &new_body),
+    }).respond_to(&req).map_into_boxed_body();
+
+    Ok(response)
+}
+
+/*
+===== POST /api/users/{user_uuid}/entries/{entry_uuid}/revisions/{revision_uuid}/restore =====
+
+This handler replaces content of an entry with the revision on success.
+The content being replaced is recorded as a new revision, so restoring can be undone.
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session is not authorized for the requested entry.
+ 404 Not Found: Requested entry or revision does not exist.
+*/
+
+#[post("/api/users/{user_uuid}/entries/{entry_uuid}/revisions/{revision_uuid}/restore")]
+async fn revision_restore(
+    session: Session,
+    db_pool: web::Data<PgPool>,
+    path: web::Path<(String, String, String)>) -> Result<HttpResponse, Error>
+{
+    let (_, entry_uuid, revision_uuid) = path.into_inner();
+
+    let entry = Entry::by_uuid(&db_pool, &entry_uuid, &session.user_uuid).await?;
+    let revision = entry.revision(&db_pool, &revision_uuid).await?;
+    entry.restore(&db_pool, &revision).await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/*
+===== GET /api/users/{user_uuid}/tags =====
+
+This handler on success, responds with tags of the user and number of entries using each,
+most used first. Entries in the trash are not counted.
+
+Response JSON example:
+{
+    "name": ["family", "travel"],
+    "count": [12, 3]
+}
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session is not authorized for this user.
+*/
+
+#[derive(Serialize)]
+struct TagList {
+    name: Vec<String>,
+    count: Vec<i64>,
+}
+
+#[get("/api/users/{user_uuid}/tags")]
+async fn tag_list(
+    session: Session,
+    req: HttpRequest,
+    db_pool: web::Data<PgPool>) -> Result<HttpResponse, Error>
+{
+    let usages = Entry::tag_usages_by_user(&db_pool, &session.user_uuid).await?;
+
+    let response = web::Json(TagList {
+        name: usages.iter().map(|usage| usage.name.clone()).collect(),
+        count: usages.iter().map(|usage| usage.count).collect(),
+    }).respond_to(&req).map_into_boxed_body();
+
+    Ok(response)
+}
+
+/*
+===== GET /api/users/{user_uuid}/entries/{entry_uuid}/attachments =====
+
+This handler on success, responds with attachments of an entry, oldest first.
+
+Response JSON example:
+{
+    "uuid": ["b8e6d2f1-4c1e-4a5b-9a77-2f4f0c6d1e93"],
+    "filename": ["beach.jpg"],
+    "content_type": ["image/jpeg"],
+    "size": [482113],
+    "created": ["2023-01-07T06:31:02.117203+00:00"]
+}
+
+The 'size' field is in bytes.
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session is not authorized for the requested entry.
+ 404 Not Found: Requested entry does not exist.
+*/
+
+#[derive(Serialize)]
+struct AttachmentList {
+    uuid: Vec<String>,
+    filename: Vec<String>,
+    content_type: Vec<String>,
+    size: Vec<i64>,
+    created: Vec<String>,
+}
+
+#[get("/api/users/{user_uuid}/entries/{entry_uuid}/attachments")]
+async fn attachment_list(
+    session: Session,
+    req: HttpRequest,
+    db_pool: web::Data<PgPool>,
+    path: web::Path<(String, String)>) -> Result<HttpResponse, Error>
+{
+    let (_, entry_uuid) = path.into_inner();
+
+    let entry = Entry::by_uuid(&db_pool, &entry_uuid, &session.user_uuid).await?;
+    let attachments = Attachment::by_entry(&db_pool, &entry).await?;
+
+    let response = web::Json(AttachmentList {
+        uuid: attachments.iter().map(|attachment| attachment.uuid.clone()).collect(),
+        filename: attachments.iter().map(|attachment| attachment.filename.clone()).collect(),
+        content_type: attachments.iter().map(|attachment| attachment.content_type.clone()).collect(),
+        size: attachments.iter().map(|attachment| attachment.size).collect(),
+        created: attachments.iter().map(|attachment| attachment.created.clone()).collect(),
+    }).respond_to(&req).map_into_boxed_body();
+
+    Ok(response)
+}
+
+/*
+===== POST /api/users/{user_uuid}/entries/{entry_uuid}/attachments =====
+
+This handler stores an uploaded file as an attachment of an entry on success.
+
+The request body is multipart/form-data with a single field named 'file'.
+The file name and content type of the field are kept for downloads.
+
+Notable HTTP status codes:
+ 400 Bad Request: There is no field named 'file'.
+ 401 Unauthorized: Session is not authorized for the requested entry.
+ 404 Not Found: Requested entry does not exist.
+ 413 Payload Too Large: The file is too large, or the user's attachment quota is exceeded.
+*/
+
+#[post("/api/users/{user_uuid}/entries/{entry_uuid}/attachments")]
+async fn attachment_upload(
+    session: Session,
+    db_pool: web::Data<PgPool>,
+    storage: web::Data<Storage>,
+    path: web::Path<(String, String)>,
+    mut payload: Multipart) -> Result<HttpResponse, Error>
+{
+    let (_, entry_uuid) = path.into_inner();
+    let entry = Entry::by_uuid(&db_pool, &entry_uuid, &session.user_uuid).await?;
+
+    while let Some(mut field) = payload.try_next().await? {
+        if field.name() != "file" {
+            continue;
+        }
+
+        let filename = field.content_disposition().get_filename().unwrap_or("attachment").to_string();
+        let content_type = match field.content_type() {
+            Some(mime) => mime.to_string(),
+            None => "application/octet-stream".to_string()
+        };
+
+        let mut data = Vec::new();
+        while let Some(chunk) = field.try_next().await? {
+            if (data.len() + chunk.len()) as i64 > storage.max_size {
+                return Err(ErrorPayloadTooLarge("Attachment is too large."));
+            }
+            data.extend_from_slice(&chunk);
+        }
+
+        let attachment = Attachment::create(
+            &db_pool,
+            &storage,
+            &entry,
+            &filename,
+            &content_type,
+            data).await?;
+
+        let attachment_path = format!(
+            "/api/users/{}/entries/{}/attachments/{}",
+            &attachment.user_uuid, &attachment.entry_uuid, &attachment.uuid);
+        return Ok(HttpResponse::Created().insert_header(("Location", attachment_path)).finish());
+    }
+
+    Err(ErrorBadRequest("Field named 'file' is not found."))
+}
+
+/*
+===== GET /api/users/{user_uuid}/entries/{entry_uuid}/attachments/{attachment_uuid} =====
+
+This handler responds with content of an attachment on success.
+The 'Content-Type' header is set to the type given on upload. PNG, JPEG, GIF, WebP
+and AVIF images are served inline, everything else as a download. Responses are
+sandboxed by 'Content-Security-Policy' so that they never run with access to the API.
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session is not authorized for the requested entry.
+ 404 Not Found: Requested entry or attachment does not exist.
+*/
+
+#[get("/api/users/{user_uuid}/entries/{entry_uuid}/attachments/{attachment_uuid}")]
+async fn attachment_download(
+    session: Session,
+    db_pool: web::Data<PgPool>,
+    storage: web::Data<Storage>,
+    path: web::Path<(String, String, String)>) -> Result<HttpResponse, Error>
+{
+    let (_, entry_uuid, attachment_uuid) = path.into_inner();
+
+    let entry = Entry::by_uuid(&db_pool, &entry_uuid, &session.user_uuid).await?;
+    let attachment = Attachment::by_uuid(&db_pool, &attachment_uuid, &entry).await?;
+    let data = storage.get(&attachment.uuid).await?;
+
+    let disposition = match attachment.is_inline() {
+        true => DispositionType::Inline,
+        false => DispositionType::Attachment
+    };
+
+    let content_disposition = ContentDisposition {
+        disposition,
+        parameters: vec![DispositionParam::Filename(attachment.filename)],
+    };
+
+    let response = HttpResponse::Ok()
+        .insert_header(("Content-Type", attachment.content_type))
+        .insert_header(("X-Content-Type-Options", "nosniff"))
+        .insert_header(("Content-Security-Policy", "sandbox; default-src 'none'"))
+        .insert_header(content_disposition)
+        .body(data);
+
+    Ok(response)
+}
+
+/*
+===== DELETE /api/users/{user_uuid}/entries/{entry_uuid}/attachments/{attachment_uuid} =====
+
+This handler deletes an attachment on success.
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session is not authorized for the requested entry.
+ 404 Not Found: Requested entry or attachment does not exist.
+*/
+
+#[delete("/api/users/{user_uuid}/entries/{entry_uuid}/attachments/{attachment_uuid}")]
+async fn attachment_delete(
+    session: Session,
+    db_pool: web::Data<PgPool>,
+    storage: web::Data<Storage>,
+    path: web::Path<(String, String, String)>) -> Result<HttpResponse, Error>
+{
+    let (_, entry_uuid, attachment_uuid) = path.into_inner();
+
+    let entry = Entry::by_uuid(&db_pool, &entry_uuid, &session.user_uuid).await?;
+    let attachment = Attachment::by_uuid(&db_pool, &attachment_uuid, &entry).await?;
+    attachment.delete(&db_pool, &storage).await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/*
+===== GET /api/users/{user_uuid}/export?format={json/markdown/html} =====
+
+This handler on success, responds with every entry of the user as a downloadable file, 
+oldest first. Entries in the trash are not exported.
+
+Formats:
+ json: A single JSON document. Defaults to this format.
+ markdown: A ZIP archive of Markdown files with YAML front matter.
+ html: A single HTML page.
 
-    let entry = Entry::by_uuid(&db_pool, &entry_uuid, &session.user_uuid).await?;
-    entry.delete(&db_pool).await?;
+Each entry keeps its 'created' timestamp along with the 'timezone_offset' and 'timezone' it was
+written in.
+The response is streamed while entries are loaded, so it may be cut short on a server error.
+
+Notable HTTP status codes:
+ 400 Bad Request: Unknown format.
+ 401 Unauthorized: Session is not authorized for this user.
+*/
+
+#[derive(Deserialize)]
+struct ExportQuery {
+    format: Option<String>,
+}
+
+#[get("/api/users/{user_uuid}/export")]
+async fn entry_export(
+    session: Session,
+    db_pool: web::Data<PgPool>,
+    query: web::Query<ExportQuery>) -> Result<HttpResponse, Error>
+{
+    let format = export::Format::parse(query.format.as_deref().unwrap_or("json"))?;
+
+    let content_disposition = ContentDisposition {
+        disposition: DispositionType::Attachment,
+        parameters: vec![DispositionParam::Filename(format.filename().to_string())],
+    };
+
+    let body = export::stream(db_pool.get_ref().clone(), session.user_uuid, &format);
+
+    let response = HttpResponse::Ok()
+        .insert_header(("Content-Type", format.content_type()))
+        .insert_header(content_disposition)
+        .streaming(body);
+
+    Ok(response)
+}
+
+/*
+===== POST /api/users/{user_uuid}/import?format={markdown/centinote/dayone}&dry_run={true/false} =====
+
+This handler creates entries from an uploaded archive or document, keeping their original
+creation dates and timezones.
+
+The request body is multipart/form-data with a single field named 'file'.
+
+Formats:
+ markdown: A ZIP archive of Markdown files with YAML front matter.
+ centinote: A JSON document from GET /api/users/{user_uuid}/export?format=json.
+ dayone: A Day One JSON export, either the ZIP archive or the JSON file in it.
+
+With dry_run=true, nothing is imported and the report tells what would have been.
+Items which fail do not stop the others from being imported.
+
+Response JSON example:
+{
+    "dry_run": false,
+    "imported": 1,
+    "failed": 1,
+    "source": ["2023-01-07-diary.md", "notes/todo.md"],
+    "title": ["My Title", null],
+    "created": ["2023-01-07T06:29:16+09:00", null],
+    "error": [null, "Creation date is missing or malformed."]
+}
+
+Notable HTTP status codes:
+ 400 Bad Request: Unknown format, no field named 'file', or the file cannot be read at all.
+ 401 Unauthorized: Session is not authorized for this user.
+ 413 Payload Too Large: The file is larger than 64 MiB, or files in the archive are
+                       larger than 16 MiB each or 256 MiB in total once decompressed.
+*/
+
+#[derive(Deserialize)]
+struct ImportQuery {
+    format: String,
+    dry_run: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct ImportReport {
+    dry_run: bool,
+    imported: usize,
+    failed: usize,
+    source: Vec<String>,
+    title: Vec<Option<String>>,
+    created: Vec<Option<String>>,
+    error: Vec<Option<String>>,
+}
+
+#[post("/api/users/{user_uuid}/import")]
+async fn entry_import(
+    session: Session,
+    req: HttpRequest,
+    db_pool: web::Data<PgPool>,
+    query: web::Query<ImportQuery>,
+    mut payload: Multipart) -> Result<HttpResponse, Error>
+{
+    let format = import::Format::parse(&query.format)?;
+    let dry_run = query.dry_run.unwrap_or(false);
+
+    let mut data = None;
+    while let Some(mut field) = payload.try_next().await? {
+        if field.name() != "file" {
+            continue;
+        }
+
+        let mut content = Vec::new();
+        while let Some(chunk) = field.try_next().await? {
+            if content.len() + chunk.len() > import::MAX_SIZE {
+                return Err(ErrorPayloadTooLarge("Uploaded file is too large."));
+            }
+            content.extend_from_slice(&chunk);
+        }
+
+        data = Some(content);
+        break;
+    }
+
+    let data = match data {
+        Some(value) => value,
+        None => return Err(ErrorBadRequest("Field named 'file' is not found."))
+    };
+
+    let items = import::parse(format, data).await?;
+    let reports = import::import(&db_pool, &session.user_uuid, items, dry_run).await;
+
+    let failed = reports.iter().filter(|report| report.error.is_some()).count();
+
+    let response = web::Json(ImportReport {
+        dry_run,
+        imported: reports.len() - failed,
+        failed,
+        source: reports.iter().map(|report| report.source.clone()).collect(),
+        title: reports.iter().map(|report| report.title.clone()).collect(),
+        created: reports.iter().map(|report| report.created.clone()).collect(),
+        error: reports.iter().map(|report| report.error.clone()).collect(),
+    }).respond_to(&req).map_into_boxed_body();
+
+    Ok(response)
+}
+
+/*
+===== POST /api/admin/invites =====
+
+This handler creates an invite on success, for registering when registration requires one.
+Only administrators may do this.
+
+Request JSON example: { "max_uses": 5, "lifetime_days": 14 }
+
+Both fields are optional. Invites are single-use and expire after 7 days by default.
+The 'max_uses' field is between 1 and 1000, and 'lifetime_days' between 1 and 365.
+
+Response JSON example:
+{
+    "uuid": "5d0a4d5e-6a1b-4b8e-9f3e-2f7a1c0e9b42",
+    "code": "Xq3Lr8VtY2mN7pKw4sZb9dHc",
+    "expiry": "2023-01-21T06:29:16.035754+00:00"
+}
+
+The 'code' field is only ever shown in this response. The registration page fills it in
+when opened as /register.html?invite={code}.
+
+Notable HTTP status codes:
+ 400 Bad Request: Maximum uses or lifetime is out of range.
+ 401 Unauthorized: Session cannot be verified.
+ 403 Forbidden: User is not an administrator, or access token lacks the 'admin' scope.
+*/
+
+#[derive(Deserialize)]
+struct InviteCreate {
+    max_uses: Option<i32>,
+    lifetime_days: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct InviteCreated {
+    uuid: String,
+    code: String,
+    expiry: String,
+}
+
+#[post("/api/admin/invites")]
+async fn invite_create(
+    admin: Admin,
+    db_pool: web::Data<PgPool>,
+    info: web::Json<InviteCreate>) -> Result<HttpResponse, Error>
+{
+    let (invite, code) = Invite::create(&db_pool, &admin.user, info.max_uses, info.lifetime_days).await?;
+
+    let invite_path = format!("/api/admin/invites/{}", invite.uuid);
+    let response = HttpResponse::Created()
+        .insert_header(("Location", invite_path))
+        .json(InviteCreated {
+            uuid: invite.uuid,
+            code,
+            expiry: invite.expiry,
+        });
+
+    Ok(response)
+}
+
+/*
+===== GET /api/admin/invites =====
+
+This handler responds with every invite on success, recently created first.
+Codes of the invites are not included.
+
+Response JSON example:
+{
+    "uuid": ["5d0a4d5e-6a1b-4b8e-9f3e-2f7a1c0e9b42"],
+    "created_by": ["myusername"],
+    "created": ["2023-01-07T06:29:16.035754+00:00"],
+    "expiry": ["2023-01-21T06:29:16.035754+00:00"],
+    "max_uses": [5],
+    "uses": [2]
+}
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session cannot be verified.
+ 403 Forbidden: User is not an administrator, or access token lacks the 'admin' scope.
+*/
+
+#[derive(Serialize)]
+struct InviteList {
+    uuid: Vec<String>,
+    created_by: Vec<String>,
+    created: Vec<String>,
+    expiry: Vec<String>,
+    max_uses: Vec<i32>,
+    uses: Vec<i32>,
+}
+
+#[get("/api/admin/invites")]
+async fn invite_list(
+    _admin: Admin,
+    req: HttpRequest,
+    db_pool: web::Data<PgPool>) -> Result<HttpResponse, Error>
+{
+    let invites = Invite::list(&db_pool).await?;
+
+    let response = web::Json(InviteList {
+        uuid: invites.iter().map(|invite| invite.uuid.clone()).collect(),
+        created_by: invites.iter().map(|invite| invite.created_by.clone()).collect(),
+        created: invites.iter().map(|invite| invite.created.clone()).collect(),
+        expiry: invites.iter().map(|invite| invite.expiry.clone()).collect(),
+        max_uses: invites.iter().map(|invite| invite.max_uses).collect(),
+        uses: invites.iter().map(|invite| invite.uses).collect(),
+    }).respond_to(&req).map_into_boxed_body();
+
+    Ok(response)
+}
+
+/*
+===== DELETE /api/admin/invites/{invite_uuid} =====
+
+This handler revokes an invite on success. Users who registered with it are kept.
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session cannot be verified.
+ 403 Forbidden: User is not an administrator, or access token lacks the 'admin' scope.
+ 404 Not Found: Invite does not exist.
+*/
+
+#[delete("/api/admin/invites/{invite_uuid}")]
+async fn invite_delete(
+    _admin: Admin,
+    db_pool: web::Data<PgPool>,
+    path: web::Path<String>) -> Result<HttpResponse, Error>
+{
+    Invite::revoke(&db_pool, &path.into_inner()).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/*
+===== GET /api/admin/registrations =====
+
+This handler responds with users waiting for approval on success, earliest registered first.
+
+Response JSON example:
+{
+    "uuid": ["0b8f0f59-3c8a-4d4e-8d43-6a2b4c1f9e27"],
+    "username": ["newcomer"],
+    "created": ["2023-01-07T06:29:16.035754+00:00"]
+}
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session cannot be verified.
+ 403 Forbidden: User is not an administrator, or access token lacks the 'admin' scope.
+*/
+
+#[derive(Serialize)]
+struct RegistrationList {
+    uuid: Vec<String>,
+    username: Vec<String>,
+    created: Vec<String>,
+}
+
+#[get("/api/admin/registrations")]
+async fn registration_list(
+    _admin: Admin,
+    req: HttpRequest,
+    db_pool: web::Data<PgPool>) -> Result<HttpResponse, Error>
+{
+    let users = User::list_pending(&db_pool).await?;
+
+    let response = web::Json(RegistrationList {
+        uuid: users.iter().map(|user| user.uuid.clone()).collect(),
+        username: users.iter().map(|user| user.username.clone()).collect(),
+        created: users.iter().map(|user| user.created.clone()).collect(),
+    }).respond_to(&req).map_into_boxed_body();
+
+    Ok(response)
+}
+
+/*
+===== POST /api/admin/registrations/{user_uuid} =====
+
+This handler approves a user waiting for approval on success, who can log in from then on.
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session cannot be verified.
+ 403 Forbidden: User is not an administrator, or access token lacks the 'admin' scope.
+ 404 Not Found: User does not exist or is not waiting for approval.
+*/
+
+#[post("/api/admin/registrations/{user_uuid}")]
+async fn registration_approve(
+    _admin: Admin,
+    db_pool: web::Data<PgPool>,
+    path: web::Path<String>) -> Result<HttpResponse, Error>
+{
+    User::approve(&db_pool, &path.into_inner()).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/*
+===== DELETE /api/admin/registrations/{user_uuid} =====
+
+This handler rejects a user waiting for approval on success, which deletes the user.
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session cannot be verified.
+ 403 Forbidden: User is not an administrator, or access token lacks the 'admin' scope.
+ 404 Not Found: User does not exist or is not waiting for approval.
+*/
+
+#[delete("/api/admin/registrations/{user_uuid}")]
+async fn registration_reject(
+    _admin: Admin,
+    db_pool: web::Data<PgPool>,
+    path: web::Path<String>) -> Result<HttpResponse, Error>
+{
+    User::reject(&db_pool, &path.into_inner()).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/*
+===== GET /api/admin/users =====
+
+This handler responds with every user on success, earliest registered first.
+Administrators can see how much each user stores, but never what it is.
+
+Response JSON example:
+{
+    "uuid": ["3f1c9a8e-2b7d-4e6f-9a0b-1c2d3e4f5a6b"],
+    "username": ["myusername"],
+    "display_name": ["My Name"],
+    "created": ["2023-01-06T12:00:00+00:00"],
+    "is_admin": [true],
+    "approved": [true],
+    "disabled": [false],
+    "password_reset_required": [false],
+    "two_factor": [false],
+    "entry_count": [42],
+    "attachment_count": [3],
+    "attachment_bytes": [1048576],
+    "last_active": ["2023-01-07T06:29:16.035754+00:00"]
+}
+
+The 'entry_count' field does not count entries in the trash. The 'last_active' field is
+the last use of a session which has not been deleted, or null if there is none.
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session cannot be verified.
+ 403 Forbidden: User is not an administrator, or access token lacks the 'admin' scope.
+*/
+
+#[derive(Serialize)]
+struct AdminUserList {
+    uuid: Vec<String>,
+    username: Vec<String>,
+    display_name: Vec<Option<String>>,
+    created: Vec<String>,
+    is_admin: Vec<bool>,
+    approved: Vec<bool>,
+    disabled: Vec<bool>,
+    password_reset_required: Vec<bool>,
+    two_factor: Vec<bool>,
+    entry_count: Vec<i64>,
+    attachment_count: Vec<i64>,
+    attachment_bytes: Vec<i64>,
+    last_active: Vec<Option<String>>,
+}
+
+#[get("/api/admin/users")]
+async fn admin_user_list(
+    _admin: Admin,
+    req: HttpRequest,
+    db_pool: web::Data<PgPool>) -> Result<HttpResponse, Error>
+{
+    let users = admin::users::list(&db_pool).await?;
+
+    let response = web::Json(AdminUserList {
+        uuid: users.iter().map(|user| user.uuid.clone()).collect(),
+        username: users.iter().map(|user| user.username.clone()).collect(),
+        display_name: users.iter().map(|user| user.display_name.clone()).collect(),
+        created: users.iter().map(|user| user.created.clone()).collect(),
+        is_admin: users.iter().map(|user| user.is_admin).collect(),
+        approved: users.iter().map(|user| user.approved).collect(),
+        disabled: users.iter().map(|user| user.disabled).collect(),
+        password_reset_required: users.iter().map(|user| user.password_reset_required).collect(),
+        two_factor: users.iter().map(|user| user.two_factor).collect(),
+        entry_count: users.iter().map(|user| user.entry_count).collect(),
+        attachment_count: users.iter().map(|user| user.attachment_count).collect(),
+        attachment_bytes: users.iter().map(|user| user.attachment_bytes).collect(),
+        last_active: users.iter().map(|user| user.last_active.clone()).collect(),
+    }).respond_to(&req).map_into_boxed_body();
+
+    Ok(response)
+}
 
+/*
+===== POST /api/admin/users/{user_uuid}/disable =====
+
+This handler disables a user on success. Disabled users are logged out everywhere, and
+cannot log in or use their access tokens until enabled again. Nothing of theirs is deleted.
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session cannot be verified.
+ 403 Forbidden: User is not an administrator, or access token lacks the 'admin' scope.
+ 404 Not Found: User does not exist.
+ 409 Conflict: Administrators cannot disable themselves.
+*/
+
+#[post("/api/admin/users/{user_uuid}/disable")]
+async fn admin_user_disable(
+    admin: Admin,
+    db_pool: web::Data<PgPool>,
+    path: web::Path<String>) -> Result<HttpResponse, Error>
+{
+    let user_uuid = path.into_inner();
+    if user_uuid == admin.user.uuid {
+        return Err(ErrorConflict("Administrators cannot disable themselves."));
+    }
+
+    User::set_disabled(&db_pool, &user_uuid, true).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/*
+===== POST /api/admin/users/{user_uuid}/enable =====
+
+This handler enables a disabled user on success.
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session cannot be verified.
+ 403 Forbidden: User is not an administrator, or access token lacks the 'admin' scope.
+ 404 Not Found: User does not exist.
+*/
+
+#[post("/api/admin/users/{user_uuid}/enable")]
+async fn admin_user_enable(
+    _admin: Admin,
+    db_pool: web::Data<PgPool>,
+    path: web::Path<String>) -> Result<HttpResponse, Error>
+{
+    User::set_disabled(&db_pool, &path.into_inner(), false).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/*
+===== POST /api/admin/users/{user_uuid}/password-reset =====
+
+This handler forces a user to set a new password on success. The user is logged out
+everywhere and their access tokens are revoked. They cannot log in with the password until
+setting a new one through POST /api/password-reset, which asks for the current password,
+so the administrator gets no way into the account. Passkeys of the user keep working.
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session cannot be verified.
+ 403 Forbidden: User is not an administrator, or access token lacks the 'admin' scope.
+ 404 Not Found: User does not exist.
+*/
+
+#[post("/api/admin/users/{user_uuid}/password-reset")]
+async fn admin_password_reset(
+    _admin: Admin,
+    db_pool: web::Data<PgPool>,
+    path: web::Path<String>) -> Result<HttpResponse, Error>
+{
+    User::start_password_reset(&db_pool, &path.into_inner()).await?;
     Ok(HttpResponse::Ok().finish())
 }
+
+/*
+===== DELETE /api/admin/users/{user_uuid}/sessions =====
+
+This handler logs a user out everywhere and revokes their access tokens on success.
+Only sessions are counted in the response.
+
+Response JSON example: { "revoked": 3 }
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session cannot be verified.
+ 403 Forbidden: User is not an administrator, or access token lacks the 'admin' scope.
+*/
+
+#[derive(Serialize)]
+struct SessionsRevoked {
+    revoked: u64,
+}
+
+#[delete("/api/admin/users/{user_uuid}/sessions")]
+async fn admin_session_revoke(
+    _admin: Admin,
+    db_pool: web::Data<PgPool>,
+    path: web::Path<String>) -> Result<HttpResponse, Error>
+{
+    let revoked = Session::revoke_all(&db_pool, &path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(SessionsRevoked { revoked }))
+}
+
+/*
+===== GET /api/admin/stats =====
+
+This handler responds with totals of the whole instance on success.
+
+Response JSON example:
+{
+    "users": 12,
+    "admins": 1,
+    "pending_users": 2,
+    "disabled_users": 0,
+    "entries": 4096,
+    "trashed_entries": 17,
+    "revisions": 9120,
+    "attachments": 230,
+    "attachment_bytes": 734003200,
+    "attachment_quota_bytes": 1073741824,
+    "active_sessions": 9,
+    "database_bytes": 52428800
+}
+
+The 'attachment_quota_bytes' field is the quota of each user, not of the instance.
+
+Notable HTTP status codes:
+ 401 Unauthorized: Session cannot be verified.
+ 403 Forbidden: User is not an administrator, or access token lacks the 'admin' scope.
+*/
+
+#[derive(Serialize)]
+struct AdminStats {
+    users: i64,
+    admins: i64,
+    pending_users: i64,
+    disabled_users: i64,
+    entries: i64,
+    trashed_entries: i64,
+    revisions: i64,
+    attachments: i64,
+    attachment_bytes: i64,
+    attachment_quota_bytes: i64,
+    active_sessions: i64,
+    database_bytes: i64,
+}
+
+#[get("/api/admin/stats")]
+async fn admin_stats(
+    _admin: Admin,
+    db_pool: web::Data<PgPool>,
+    storage: web::Data<Storage>) -> Result<HttpResponse, Error>
+{
+    let stats = admin::stats::stats(&db_pool).await?;
+
+    Ok(HttpResponse::Ok().json(AdminStats {
+        users: stats.users,
+        admins: stats.admins,
+        pending_users: stats.pending_users,
+        disabled_users: stats.disabled_users,
+        entries: stats.entries,
+        trashed_entries: stats.trashed_entries,
+        revisions: stats.revisions,
+        attachments: stats.attachments,
+        attachment_bytes: stats.attachment_bytes,
+        attachment_quota_bytes: storage.quota,
+        active_sessions: stats.active_sessions,
+        database_bytes: stats.database_bytes,
+    }))
+}
@@ -0,0 +1,58 @@
+use actix_web::{Error, error::ErrorBadRequest};
+use chrono::DateTime;
+use serde::Deserialize;
+use super::{Item, ParseResult, timezone_from, timezone_offset_from};
+
+// Reads the JSON document produced by the export endpoint.
+
+#[derive(Deserialize)]
+struct Document {
+    entries: Vec<DocumentEntry>,
+}
+
+#[derive(Deserialize)]
+struct DocumentEntry {
+    created: String,
+    timezone_offset: Option<i32>,
+    timezone: Option<String>,
+    title: String,
+    body: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+fn item_from(source: String, entry: DocumentEntry) -> ParseResult {
+    let created = match DateTime::parse_from_rfc3339(&entry.created) {
+        Ok(value) => value,
+        Err(_) => return Err((source, "Creation date is malformed.".to_string()))
+    };
+
+    let offset = entry.timezone_offset.unwrap_or(timezone_offset_from(created.offset()));
+    let timezone = match timezone_from(entry.timezone.as_deref(), offset, created.naive_utc()) {
+        Ok(value) => value,
+        Err(error) => return Err((source, error))
+    };
+
+    Ok(Item {
+        source,
+        created_utc: created.naive_utc(),
+        timezone,
+        title: entry.title,
+        body: entry.body,
+        tags: entry.tags,
+    })
+}
+
+pub fn parse(data: &[u8]) -> Result<Vec<ParseResult>, Error> {
+    let document: Document = match serde_json::from_slice(data) {
+        Ok(value) => value,
+        Err(_) => return Err(ErrorBadRequest("Uploaded file is not a Centinote JSON export."))
+    };
+
+    let items = document.entries.into_iter()
+        .enumerate()
+        .map(|(index, entry)| item_from(format!("entries[{index}]"), entry))
+        .collect();
+
+    Ok(items)
+}
@@ -0,0 +1,88 @@
+use actix_web::{Error, error::ErrorBadRequest};
+use chrono::DateTime;
+use serde::Deserialize;
+use super::{Item, ParseResult, is_zip, read_zip, timezone_from, timezone_offset_from};
+
+/*
+Reads Day One JSON exports, either as the exported ZIP archive or a JSON file in it.
+Day One entries have no titles. A leading Markdown heading is taken as the title if present.
+*/
+
+#[derive(Deserialize)]
+struct Document {
+    entries: Vec<DocumentEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DocumentEntry {
+    creation_date: String,
+    time_zone: Option<String>,
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+fn split_title(text: &str) -> (String, String) {
+    let (first_line, rest) = match text.split_once('\n') {
+        Some(value) => value,
+        None => (text, "")
+    };
+
+    match first_line.strip_prefix('#') {
+        Some(heading) => (heading.trim_start_matches('#').trim().to_string(), rest.trim_start().to_string()),
+        None => (String::new(), text.to_string())
+    }
+}
+
+fn item_from(source: String, entry: DocumentEntry) -> ParseResult {
+    let created = match DateTime::parse_from_rfc3339(&entry.creation_date) {
+        Ok(value) => value,
+        Err(_) => return Err((source, "Creation date is malformed.".to_string()))
+    };
+
+    let offset = timezone_offset_from(created.offset());
+    let timezone = match timezone_from(entry.time_zone.as_deref(), offset, created.naive_utc()) {
+        Ok(value) => value,
+        Err(error) => return Err((source, error))
+    };
+
+    let (title, body) = split_title(&entry.text);
+
+    Ok(Item {
+        source,
+        created_utc: created.naive_utc(),
+        timezone,
+        title,
+        body,
+        tags: entry.tags,
+    })
+}
+
+fn parse_document(name: &str, data: &[u8]) -> Result<Vec<ParseResult>, Error> {
+    let document: Document = match serde_json::from_slice(data) {
+        Ok(value) => value,
+        Err(_) => return Err(ErrorBadRequest("Uploaded file is not a Day One JSON export."))
+    };
+
+    let items = document.entries.into_iter()
+        .enumerate()
+        .map(|(index, entry)| item_from(format!("{name}entries[{index}]"), entry))
+        .collect();
+
+    Ok(items)
+}
+
+pub fn parse(data: &[u8]) -> Result<Vec<ParseResult>, Error> {
+    if !is_zip(data) {
+        return parse_document("", data);
+    }
+
+    let mut items = Vec::new();
+    for (name, content) in read_zip(data, ".json")? {
+        items.extend(parse_document(&format!("{name}:"), &content)?);
+    }
+
+    Ok(items)
+}
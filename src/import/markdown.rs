@@ -0,0 +1,149 @@
+use actix_web::Error;
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
+use serde_yaml::Value;
+use super::{Item, ParseResult, read_zip, timezone_from, timezone_offset_from};
+
+/*
+Reads a ZIP archive of Markdown files with YAML front matter, like the ones produced by
+the export endpoint. Recognized front matter keys:
+ title: Falls back to a leading heading, then to the file name.
+ created or date: RFC 3339 timestamp, or "YYYY-MM-DD[ HH:MM[:SS]]" local time.
+                  Falls back to a date at the start of the file name.
+ timezone_offset: JS getTimezoneOffset() style value, used for local times.
+ timezone: IANA time zone name, which takes precedence over timezone_offset.
+ tags: A list, or a comma separated string.
+*/
+
+fn split_front_matter(text: &str) -> Result<(Option<&str>, &str), String> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+
+    let rest = match text.strip_prefix("---\n").or_else(|| text.strip_prefix("---\r\n")) {
+        Some(value) => value,
+        None => return Ok((None, text))
+    };
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            let body = &rest[offset + line.len()..];
+            return Ok((Some(&rest[..offset]), body));
+        }
+        offset += line.len();
+    }
+
+    Err("Front matter is not closed.".to_string())
+}
+
+fn parse_local(value: &str) -> Option<NaiveDateTime> {
+    let formats = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M"];
+
+    for format in formats {
+        if let Ok(value) = NaiveDateTime::parse_from_str(value, format) {
+            return Some(value);
+        }
+    }
+
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0)
+}
+
+// Returns UTC timestamp and the timezone offset. Local times are in 'zone' when known.
+fn parse_created(value: &str, timezone_offset: Option<i32>, zone: Option<Tz>) -> Option<(NaiveDateTime, i32)> {
+    if let Ok(created) = DateTime::parse_from_rfc3339(value) {
+        let offset = timezone_offset.unwrap_or(timezone_offset_from(created.offset()));
+        return Some((created.naive_utc(), offset));
+    }
+
+    let local = parse_local(value)?;
+    if let Some(zone) = zone {
+        let created = zone.from_local_datetime(&local).earliest()?;
+        return Some((created.naive_utc(), timezone_offset_from(&created.fixed_offset().timezone())));
+    }
+
+    let offset = timezone_offset.unwrap_or(0);
+    let created = FixedOffset::west_opt(offset * 60)?.from_local_datetime(&local).single()?;
+
+    Some((created.naive_utc(), offset))
+}
+
+fn string_field(front_matter: &Value, key: &str) -> Option<String> {
+    match front_matter.get(key)? {
+        Value::String(value) => Some(value.clone()),
+        Value::Number(value) => Some(value.to_string()),
+        _ => None
+    }
+}
+
+fn tags_field(front_matter: &Value) -> Vec<String> {
+    match front_matter.get("tags") {
+        Some(Value::Sequence(values)) => {
+            values.iter().filter_map(|value| value.as_str()).map(|value| value.to_string()).collect()
+        },
+        Some(Value::String(value)) => {
+            value.split(',').map(|tag| tag.trim()).filter(|tag| !tag.is_empty()).map(|tag| tag.to_string()).collect()
+        },
+        _ => Vec::new()
+    }
+}
+
+fn item_from(name: &str, content: &[u8]) -> Result<Item, String> {
+    let text = match std::str::from_utf8(content) {
+        Ok(value) => value,
+        Err(_) => return Err("File is not in UTF-8.".to_string())
+    };
+
+    let (front_matter, body) = split_front_matter(text)?;
+    let front_matter = match front_matter {
+        Some(yaml) => match serde_yaml::from_str::<Value>(yaml) {
+            Ok(value) => value,
+            Err(_) => return Err("Front matter is malformed.".to_string())
+        },
+        None => Value::Null
+    };
+
+    let stem = name.rsplit('/').next().unwrap_or(name);
+    let stem = &stem[..stem.len() - ".md".len()];
+
+    let timezone_offset = front_matter.get("timezone_offset").and_then(|value| value.as_i64()).map(|value| value as i32);
+    let created = string_field(&front_matter, "created")
+        .or_else(|| string_field(&front_matter, "date"))
+        .or_else(|| stem.get(..10).map(|date| date.to_string()));
+
+    let timezone_name = string_field(&front_matter, "timezone");
+    let zone = timezone_name.as_deref().and_then(|name| name.parse::<Tz>().ok());
+
+    let (created_utc, timezone_offset) = match created.and_then(|value| parse_created(&value, timezone_offset, zone)) {
+        Some(value) => value,
+        None => return Err("Creation date is missing or malformed.".to_string())
+    };
+    let timezone = timezone_from(timezone_name.as_deref(), timezone_offset, created_utc)?;
+
+    let body = body.trim_start_matches(['\r', '\n']);
+    let (title, body) = match string_field(&front_matter, "title") {
+        Some(title) => (title, body.trim_end().to_string()),
+        None => {
+            let (first_line, rest) = body.split_once('\n').unwrap_or((body, ""));
+            match first_line.strip_prefix("# ") {
+                Some(heading) => (heading.trim().to_string(), rest.trim().to_string()),
+                None => (stem.to_string(), body.trim_end().to_string())
+            }
+        }
+    };
+
+    Ok(Item {
+        source: name.to_string(),
+        created_utc,
+        timezone,
+        title,
+        body,
+        tags: tags_field(&front_matter),
+    })
+}
+
+pub fn parse(data: &[u8]) -> Result<Vec<ParseResult>, Error> {
+    let items = read_zip(data, ".md")?.into_iter()
+        .map(|(name, content)| item_from(&name, &content).map_err(|error| (name, error)))
+        .collect();
+
+    Ok(items)
+}
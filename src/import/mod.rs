@@ -0,0 +1,276 @@
+pub mod centinote;
+pub mod dayone;
+pub mod markdown;
+
+use std::io::{Cursor, Read};
+use actix_web::{Error, web, error::{ErrorBadRequest, ErrorInternalServerError, ErrorPayloadTooLarge, InternalError}};
+use chrono::{FixedOffset, NaiveDateTime};
+use sqlx::PgPool;
+use crate::entry::{Entry, tag, utils::{self, Timezone}};
+
+// Size limit of an uploaded archive or document.
+pub const MAX_SIZE: usize = 64 * 1024 * 1024;
+
+// Limits on decompressed sizes of files read from an archive, each and in total.
+pub const MAX_EXTRACTED_FILE_SIZE: u64 = 16 * 1024 * 1024;
+pub const MAX_EXTRACTED_SIZE: u64 = 256 * 1024 * 1024;
+
+pub enum Format {
+    Markdown,
+    Centinote,
+    DayOne,
+}
+
+impl Format {
+    pub fn parse(value: &str) -> Result<Self, Error> {
+        match value {
+            "markdown" => Ok(Format::Markdown),
+            "centinote" => Ok(Format::Centinote),
+            "dayone" => Ok(Format::DayOne),
+            _ => Err(ErrorBadRequest("Format should be one of 'markdown', 'centinote' and 'dayone'."))
+        }
+    }
+}
+
+/*
+An entry read from an import source.
+'source' tells where it came from, such as a file name in an archive, for the report.
+*/
+pub struct Item {
+    pub source: String,
+    pub created_utc: NaiveDateTime,
+    pub timezone: Timezone,
+    pub title: String,
+    pub body: String,
+    pub tags: Vec<String>,
+}
+
+// Outcome of importing an item. Items that could not be read have no title nor creation date.
+pub struct ItemReport {
+    pub source: String,
+    pub title: Option<String>,
+    pub created: Option<String>,
+    pub error: Option<String>,
+}
+
+pub type ParseResult = Result<Item, (String, String)>;
+
+// Converts an offset in seconds east of UTC into the JS getTimezoneOffset() style value.
+pub fn timezone_offset_from(offset: &FixedOffset) -> i32 {
+    -offset.local_minus_utc() / 60
+}
+
+/*
+Timezone of an item from an optional time zone name, falling back to 'offset'.
+Unknown names are reported for the item rather than silently ignored.
+*/
+pub fn timezone_from(name: Option<&str>, offset: i32, created_utc: NaiveDateTime) -> Result<Timezone, String> {
+    match name {
+        Some(name) => match Timezone::from_name(name, created_utc) {
+            Ok(value) => Ok(value),
+            Err(_) => Err("Time zone is unknown.".to_string())
+        },
+        None => Ok(Timezone::from_offset(offset))
+    }
+}
+
+pub fn is_zip(data: &[u8]) -> bool {
+    data.starts_with(b"PK\x03\x04")
+}
+
+// Reads every file in the archive whose name ends with 'extension', in archive order.
+pub fn read_zip(data: &[u8], extension: &str) -> Result<Vec<(String, Vec<u8>)>, Error> {
+    read_zip_limited(data, extension, MAX_EXTRACTED_FILE_SIZE, MAX_EXTRACTED_SIZE)
+}
+
+/*
+Sizes declared in the archive are checked up front, and reads are cut off past the
+limits too since the declarations may lie.
+*/
+fn read_zip_limited(
+    data: &[u8],
+    extension: &str,
+    file_limit: u64,
+    total_limit: u64) -> Result<Vec<(String, Vec<u8>)>, Error>
+{
+    let mut archive = match zip::ZipArchive::new(Cursor::new(data)) {
+        Ok(value) => value,
+        Err(_) => return Err(ErrorBadRequest("Uploaded file is not a valid ZIP archive."))
+    };
+
+    let mut files = Vec::new();
+    let mut total: u64 = 0;
+    for index in 0..archive.len() {
+        let mut file = match archive.by_index(index) {
+            Ok(value) => value,
+            Err(_) => return Err(ErrorBadRequest("Uploaded file is not a valid ZIP archive."))
+        };
+
+        let name = file.name().to_string();
+        let hidden = name.split('/').any(|part| part.starts_with('.') || part == "__MACOSX");
+        if !file.is_file() || hidden || !name.to_lowercase().ends_with(extension) {
+            continue;
+        }
+
+        let limit = file_limit.min(total_limit - total);
+        if file.size() > limit {
+            return Err(ErrorPayloadTooLarge("Files in the archive are too large."));
+        }
+
+        let mut content = Vec::new();
+        if (&mut file).take(limit + 1).read_to_end(&mut content).is_err() {
+            return Err(ErrorBadRequest("Uploaded file is not a valid ZIP archive."));
+        }
+
+        if content.len() as u64 > limit {
+            return Err(ErrorPayloadTooLarge("Files in the archive are too large."));
+        }
+
+        total += content.len() as u64;
+        files.push((name, content));
+    }
+
+    Ok(files)
+}
+
+fn parse_data(format: &Format, data: &[u8]) -> Result<Vec<ParseResult>, Error> {
+    match format {
+        Format::Markdown => markdown::parse(data),
+        Format::Centinote => centinote::parse(data),
+        Format::DayOne => dayone::parse(data),
+    }
+}
+
+/*
+Reads entries from the uploaded data. Fails only when the whole upload is unreadable.
+Archives may take long to decompress, so this runs on a blocking thread. Errors cannot be
+sent across threads, so they come back as their status and message.
+*/
+pub async fn parse(format: Format, data: Vec<u8>) -> Result<Vec<ParseResult>, Error> {
+    let parse_result = web::block(move || {
+        parse_data(&format, &data)
+            .map_err(|error| (error.as_response_error().status_code(), error.to_string()))
+    }).await;
+
+    match parse_result {
+        Ok(Ok(items)) => Ok(items),
+        Ok(Err((status, message))) => Err(InternalError::new(message, status).into()),
+        Err(error) => {
+            println!("{error}");
+            Err(ErrorInternalServerError("Failed to read the upload."))
+        }
+    }
+}
+
+/*
+Creates an entry for each item, keeping its original creation date and timezone.
+With 'dry_run', items are only validated and nothing is written.
+*/
+pub async fn import(
+    db_pool: &PgPool,
+    user_uuid: &str,
+    items: Vec<ParseResult>,
+    dry_run: bool) -> Vec<ItemReport>
+{
+    let mut reports = Vec::new();
+
+    for item in items {
+        let item = match item {
+            Ok(value) => value,
+            Err((source, error)) => {
+                reports.push(ItemReport { source, title: None, created: None, error: Some(error) });
+                continue;
+            }
+        };
+
+        let result = match dry_run {
+            true => utils::validate_created(item.created_utc, &item.timezone)
+                .and_then(|_| tag::normalize(&item.tags).map(|_| ())),
+            false => {
+                Entry::create(
+                    db_pool,
+                    item.created_utc,
+                    item.timezone.clone(),
+                    user_uuid,
+                    &item.title,
+                    &item.body,
+                    &item.tags).await.map(|_| ())
+            }
+        };
+
+        reports.push(ItemReport {
+            created: Some(item.timezone.render(item.created_utc).to_rfc3339()),
+            error: result.err().map(|error| error.to_string()),
+            source: item.source,
+            title: Some(item.title),
+        });
+    }
+
+    reports
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+    use actix_web::http::StatusCode;
+    use zip::write::SimpleFileOptions;
+    use super::read_zip_limited;
+
+    fn archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in files {
+            writer.start_file(*name, SimpleFileOptions::default()).unwrap();
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn reads_matching_files() {
+        let data = archive(&[
+            ("a.md", b"first"),
+            ("b.txt", b"skipped"),
+            (".hidden.md", b"skipped"),
+            ("__MACOSX/a.md", b"skipped"),
+            ("dir/C.MD", b"second"),
+        ]);
+
+        let files = read_zip_limited(&data, ".md", 100, 100).unwrap();
+        assert_eq!(files, vec![
+            ("a.md".to_string(), b"first".to_vec()),
+            ("dir/C.MD".to_string(), b"second".to_vec()),
+        ]);
+    }
+
+    #[test]
+    fn limits_file_size() {
+        let data = archive(&[("a.md", &[b'a'; 101])]);
+        let error = read_zip_limited(&data, ".md", 100, 1000).err().unwrap();
+        assert_eq!(error.as_response_error().status_code(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let data = archive(&[("a.md", &[b'a'; 100])]);
+        assert!(read_zip_limited(&data, ".md", 100, 1000).is_ok());
+    }
+
+    #[test]
+    fn limits_total_size() {
+        let data = archive(&[("a.md", &[b'a'; 60]), ("b.md", &[b'b'; 60])]);
+        assert!(read_zip_limited(&data, ".md", 100, 100).is_err());
+        assert!(read_zip_limited(&data, ".md", 100, 120).is_ok());
+    }
+
+    #[test]
+    fn limits_understated_size() {
+        let mut data = archive(&[("a.md", &[b'a'; 1000])]);
+
+        // Declare 10 bytes as the uncompressed size in both the local and central headers.
+        for signature in [&b"PK\x03\x04"[..], &b"PK\x01\x02"[..]] {
+            let start = data.windows(4).position(|window| window == signature).unwrap();
+            let field = start + if signature[2] == 3 { 22 } else { 24 };
+            data[field..field + 4].copy_from_slice(&10u32.to_le_bytes());
+        }
+
+        let error = read_zip_limited(&data, ".md", 100, 100).err().unwrap();
+        assert_eq!(error.as_response_error().status_code(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
@@ -0,0 +1,82 @@
+use rand::{thread_rng, Rng};
+use rand::distributions::Alphanumeric;
+use actix_web::{Error, error::{ErrorBadRequest, ErrorInternalServerError}};
+use chrono::{Duration, NaiveDateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::session::hash_token;
+use crate::user::User;
+use super::{Invite, DEFAULT_LIFETIME_DAYS, MAX_LIFETIME_DAYS, MAX_USES};
+
+async fn insert_invite_sqlx(
+    db_pool: &PgPool,
+    invite: &Invite,
+    user_uuid: &str,
+    code_hash: &str,
+    created: NaiveDateTime,
+    expiry: NaiveDateTime) -> Result<(), sqlx::Error>
+{
+    sqlx::query(
+        "INSERT INTO invites (uuid, user_uuid, code_hash, created, expiry, max_uses) \
+         VALUES ($1, $2, $3, $4, $5, $6)")
+        .bind(&invite.uuid)
+        .bind(user_uuid)
+        .bind(code_hash)
+        .bind(created)
+        .bind(expiry)
+        .bind(invite.max_uses)
+        .execute(db_pool)
+        .await?;
+
+    Ok(())
+}
+
+impl Invite {
+    /*
+    Returns the invite along with its code. Only a digest of the code is stored,
+    so this is the only time it can be seen. Invites are single-use unless 'max_uses' says
+    otherwise, and expire after a week unless 'lifetime_days' says otherwise.
+    */
+    pub async fn create(
+        db_pool: &PgPool,
+        admin: &User,
+        max_uses: Option<i32>,
+        lifetime_days: Option<i64>) -> Result<(Self, String), Error>
+    {
+        let max_uses = max_uses.unwrap_or(1);
+        if !(1..=MAX_USES).contains(&max_uses) {
+            return Err(ErrorBadRequest("Maximum uses should be between 1 and 1000."));
+        }
+
+        let lifetime_days = lifetime_days.unwrap_or(DEFAULT_LIFETIME_DAYS);
+        if !(1..=MAX_LIFETIME_DAYS).contains(&lifetime_days) {
+            return Err(ErrorBadRequest("Lifetime should be between 1 and 365 days."));
+        }
+
+        let code: String = thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(24)
+            .map(char::from)
+            .collect();
+
+        let created = Utc::now().naive_utc();
+        let expiry = created + Duration::days(lifetime_days);
+
+        let invite = Invite {
+            uuid: Uuid::new_v4().to_string(),
+            created_by: admin.username.clone(),
+            created: created.and_utc().to_rfc3339(),
+            expiry: expiry.and_utc().to_rfc3339(),
+            max_uses,
+            uses: 0,
+        };
+
+        match insert_invite_sqlx(db_pool, &invite, &admin.uuid, &hash_token(&code), created, expiry).await {
+            Ok(_) => Ok((invite, code)),
+            Err(error) => {
+                println!("{error}");
+                Err(ErrorInternalServerError("Database error."))
+            }
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use actix_web::{Error, error::{ErrorInternalServerError, ErrorNotFound}};
+use sqlx::PgPool;
+use super::Invite;
+
+impl Invite {
+    // Users who registered with the invite are kept.
+    pub async fn revoke(db_pool: &PgPool, invite_uuid: &str) -> Result<(), Error> {
+        let delete_result = sqlx::query("DELETE FROM invites WHERE uuid = $1")
+            .bind(invite_uuid)
+            .execute(db_pool)
+            .await;
+
+        let query_result = match delete_result {
+            Ok(value) => value,
+            Err(error) => {
+                println!("{error}");
+                return Err(ErrorInternalServerError("Database error."));
+            }
+        };
+
+        if query_result.rows_affected() == 0 {
+            return Err(ErrorNotFound("Invite not found."));
+        }
+
+        Ok(())
+    }
+}
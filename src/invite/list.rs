@@ -0,0 +1,42 @@
+use actix_web::{Error, error::ErrorInternalServerError};
+use chrono::NaiveDateTime;
+use sqlx::{PgPool, Row, postgres::PgRow};
+use super::Invite;
+
+fn invite_from_row(row: &PgRow) -> Result<Invite, sqlx::Error> {
+    let created: NaiveDateTime = row.try_get("created")?;
+    let expiry: NaiveDateTime = row.try_get("expiry")?;
+
+    Ok(Invite {
+        uuid: row.try_get("uuid")?,
+        created_by: row.try_get("username")?,
+        created: created.and_utc().to_rfc3339(),
+        expiry: expiry.and_utc().to_rfc3339(),
+        max_uses: row.try_get("max_uses")?,
+        uses: row.try_get("uses")?,
+    })
+}
+
+async fn list_sqlx(db_pool: &PgPool) -> Result<Vec<Invite>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT invites.uuid, users.username, invites.created, invites.expiry, invites.max_uses, invites.uses \
+         FROM invites JOIN users ON users.uuid = invites.user_uuid \
+         ORDER BY invites.created DESC")
+        .fetch_all(db_pool)
+        .await?;
+
+    rows.iter().map(invite_from_row).collect()
+}
+
+impl Invite {
+    // Returns every invite, including expired and used up ones, recently created first.
+    pub async fn list(db_pool: &PgPool) -> Result<Vec<Self>, Error> {
+        match list_sqlx(db_pool).await {
+            Ok(value) => Ok(value),
+            Err(error) => {
+                println!("{error}");
+                Err(ErrorInternalServerError("Database error."))
+            }
+        }
+    }
+}
@@ -0,0 +1,18 @@
+pub mod create;
+pub mod delete;
+pub mod list;
+pub mod redeem;
+
+pub const MAX_USES: i32 = 1000;
+pub const MAX_LIFETIME_DAYS: i64 = 365;
+pub const DEFAULT_LIFETIME_DAYS: i64 = 7;
+
+// An invite lets up to 'max_uses' users register before 'expiry', when registration needs one.
+pub struct Invite {
+    pub uuid: String,
+    pub created_by: String,
+    pub created: String,
+    pub expiry: String,
+    pub max_uses: i32,
+    pub uses: i32,
+}
@@ -0,0 +1,22 @@
+use chrono::Utc;
+use sqlx::{Postgres, Transaction};
+use crate::session::hash_token;
+
+/*
+Uses the invite of 'code' once, if it has not expired or been used up.
+Returns whether it was redeemed. Registration redeems it in the same transaction as it
+creates the user, so that a use is not lost when registration fails.
+*/
+pub async fn redeem_sqlx(
+    transaction: &mut Transaction<'_, Postgres>,
+    code: &str) -> Result<bool, sqlx::Error>
+{
+    let query_result = sqlx::query(
+        "UPDATE invites SET uses = uses + 1 WHERE code_hash = $1 AND expiry > $2 AND uses < max_uses")
+        .bind(hash_token(code.trim()))
+        .bind(Utc::now().naive_utc())
+        .execute(&mut *transaction)
+        .await?;
+
+    Ok(query_result.rows_affected() != 0)
+}
@@ -1,12 +1,27 @@
+mod access_token;
+mod admin;
+mod attachment;
+mod audit;
 mod entry;
+mod export;
 mod session;
 mod handlers;
+mod import;
+mod invite;
+mod passkey;
+mod tasks;
 mod user;
 
+use std::collections::HashSet;
 use std::env;
-use std::path::Path;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 use actix_web::{web, App, HttpServer};
+use webauthn_rs::{Webauthn, WebauthnBuilder, prelude::Url};
 use sqlx::{PgPool, postgres::PgPoolOptions, migrate::Migrator};
+use attachment::storage::{LocalStorage, Storage};
 
 async fn db_connect() -> PgPool {
     let db_host = match env::var("CENTINOTE_DB_HOST") {
@@ -48,6 +63,224 @@ async fn db_connect() -> PgPool {
     pool
 }
 
+fn attachment_storage() -> Storage {
+    let attachment_dir = match env::var("CENTINOTE_ATTACHMENT_DIR") {
+        Ok(value) => value,
+        Err(_) => "/var/lib/centinote/attachments".to_string()
+    };
+
+    let quota_megabytes = match env::var("CENTINOTE_ATTACHMENT_QUOTA_MB") {
+        Ok(value) => match value.parse::<i64>() {
+            Ok(megabytes) => megabytes,
+            Err(error) => panic!("CENTINOTE_ATTACHMENT_QUOTA_MB: {}", error)
+        },
+        Err(_) => 1024
+    };
+
+    let max_size_megabytes = match env::var("CENTINOTE_ATTACHMENT_MAX_SIZE_MB") {
+        Ok(value) => match value.parse::<i64>() {
+            Ok(megabytes) => megabytes,
+            Err(error) => panic!("CENTINOTE_ATTACHMENT_MAX_SIZE_MB: {}", error)
+        },
+        Err(_) => 20
+    };
+
+    let backend = match LocalStorage::new(PathBuf::from(&attachment_dir)) {
+        Ok(value) => value,
+        Err(error) => panic!("{}: {}", attachment_dir, error)
+    };
+
+    Storage::new(Arc::new(backend), quota_megabytes * 1024 * 1024, max_size_megabytes * 1024 * 1024)
+}
+
+fn session_lifetimes() -> session::Lifetimes {
+    // Use of a session is recorded at most once a minute, hence the lower bound.
+    let idle_minutes = match env::var("CENTINOTE_SESSION_IDLE_MINUTES") {
+        Ok(value) => match value.parse::<i64>() {
+            Ok(minutes) if minutes >= 5 => minutes,
+            Ok(_) => panic!("CENTINOTE_SESSION_IDLE_MINUTES: should be at least 5"),
+            Err(error) => panic!("CENTINOTE_SESSION_IDLE_MINUTES: {}", error)
+        },
+        Err(_) => 30
+    };
+
+    let absolute_hours = match env::var("CENTINOTE_SESSION_ABSOLUTE_HOURS") {
+        Ok(value) => match value.parse::<i64>() {
+            Ok(hours) if hours > 0 => hours,
+            Ok(_) => panic!("CENTINOTE_SESSION_ABSOLUTE_HOURS: should be positive"),
+            Err(error) => panic!("CENTINOTE_SESSION_ABSOLUTE_HOURS: {}", error)
+        },
+        Err(_) => 24
+    };
+
+    let remember_idle_days = match env::var("CENTINOTE_SESSION_REMEMBER_IDLE_DAYS") {
+        Ok(value) => match value.parse::<i64>() {
+            Ok(days) if days > 0 => days,
+            Ok(_) => panic!("CENTINOTE_SESSION_REMEMBER_IDLE_DAYS: should be positive"),
+            Err(error) => panic!("CENTINOTE_SESSION_REMEMBER_IDLE_DAYS: {}", error)
+        },
+        Err(_) => 14
+    };
+
+    let remember_absolute_days = match env::var("CENTINOTE_SESSION_REMEMBER_ABSOLUTE_DAYS") {
+        Ok(value) => match value.parse::<i64>() {
+            Ok(days) if days > 0 => days,
+            Ok(_) => panic!("CENTINOTE_SESSION_REMEMBER_ABSOLUTE_DAYS: should be positive"),
+            Err(error) => panic!("CENTINOTE_SESSION_REMEMBER_ABSOLUTE_DAYS: {}", error)
+        },
+        Err(_) => 90
+    };
+
+    session::Lifetimes {
+        idle: chrono::Duration::minutes(idle_minutes),
+        absolute: chrono::Duration::hours(absolute_hours),
+        remember_idle: chrono::Duration::days(remember_idle_days),
+        remember_absolute: chrono::Duration::days(remember_absolute_days),
+    }
+}
+
+fn login_throttle() -> session::throttle::Throttle {
+    let username_threshold = match env::var("CENTINOTE_LOGIN_LOCKOUT_THRESHOLD") {
+        Ok(value) => match value.parse::<i32>() {
+            Ok(attempts) if attempts > 0 => attempts,
+            Ok(_) => panic!("CENTINOTE_LOGIN_LOCKOUT_THRESHOLD: should be positive"),
+            Err(error) => panic!("CENTINOTE_LOGIN_LOCKOUT_THRESHOLD: {}", error)
+        },
+        Err(_) => 10
+    };
+
+    let ip_threshold = match env::var("CENTINOTE_LOGIN_IP_LOCKOUT_THRESHOLD") {
+        Ok(value) => match value.parse::<i32>() {
+            Ok(attempts) if attempts > 0 => attempts,
+            Ok(_) => panic!("CENTINOTE_LOGIN_IP_LOCKOUT_THRESHOLD: should be positive"),
+            Err(error) => panic!("CENTINOTE_LOGIN_IP_LOCKOUT_THRESHOLD: {}", error)
+        },
+        Err(_) => 50
+    };
+
+    let lockout_minutes = match env::var("CENTINOTE_LOGIN_LOCKOUT_MINUTES") {
+        Ok(value) => match value.parse::<i64>() {
+            Ok(minutes) if minutes > 0 => minutes,
+            Ok(_) => panic!("CENTINOTE_LOGIN_LOCKOUT_MINUTES: should be positive"),
+            Err(error) => panic!("CENTINOTE_LOGIN_LOCKOUT_MINUTES: {}", error)
+        },
+        Err(_) => 15
+    };
+
+    session::throttle::Throttle {
+        username_threshold,
+        ip_threshold,
+        lockout: chrono::Duration::minutes(lockout_minutes),
+    }
+}
+
+fn trusted_proxies() -> session::TrustedProxies {
+    match env::var("CENTINOTE_TRUSTED_PROXIES") {
+        Ok(value) => match session::TrustedProxies::parse(&value) {
+            Ok(proxies) => proxies,
+            Err(error) => panic!("CENTINOTE_TRUSTED_PROXIES: {}", error)
+        },
+        Err(_) => session::TrustedProxies::default()
+    }
+}
+
+fn password_policy() -> user::password::PasswordPolicy {
+    let min_length = match env::var("CENTINOTE_PASSWORD_MIN_LENGTH") {
+        Ok(value) => match value.parse::<usize>() {
+            Ok(length) if (user::password::MIN_PASSWORD_LENGTH..=user::password::MAX_PASSWORD_LENGTH).contains(&length) => length,
+            Ok(_) => panic!("CENTINOTE_PASSWORD_MIN_LENGTH: should be between 8 and 128"),
+            Err(error) => panic!("CENTINOTE_PASSWORD_MIN_LENGTH: {}", error)
+        },
+        Err(_) => user::password::MIN_PASSWORD_LENGTH
+    };
+
+    // Lists found in the wild are not always valid UTF-8, whose broken lines are harmless.
+    let breached = match env::var("CENTINOTE_PASSWORD_BREACHED_LIST") {
+        Ok(path) => match fs::read(&path) {
+            Ok(content) => String::from_utf8_lossy(&content)
+                .lines()
+                .map(|line| line.trim_end_matches('\r'))
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect(),
+            Err(error) => panic!("CENTINOTE_PASSWORD_BREACHED_LIST: {}: {}", path, error)
+        },
+        Err(_) => HashSet::new()
+    };
+
+    if !breached.is_empty() {
+        println!("Loaded {} breached passwords", breached.len());
+    }
+
+    user::password::PasswordPolicy { min_length, breached }
+}
+
+fn registration() -> user::registration::Registration {
+    match env::var("CENTINOTE_REGISTRATION") {
+        Ok(value) => match user::registration::Registration::parse(&value) {
+            Some(registration) => registration,
+            None => panic!("CENTINOTE_REGISTRATION: should be one of 'open', 'closed', 'invite' and 'approval'")
+        },
+        Err(_) => user::registration::Registration::Open
+    }
+}
+
+/*
+Passkeys are bound to the domain of the server. The relying party ID is that domain, and
+the origin is the URL which users open in their browser.
+*/
+fn webauthn() -> Webauthn {
+    let rp_id = match env::var("CENTINOTE_WEBAUTHN_RP_ID") {
+        Ok(value) => value,
+        Err(_) => "localhost".to_string()
+    };
+
+    let rp_origin = match env::var("CENTINOTE_WEBAUTHN_ORIGIN") {
+        Ok(value) => match Url::parse(&value) {
+            Ok(url) => url,
+            Err(error) => panic!("CENTINOTE_WEBAUTHN_ORIGIN: {}", error)
+        },
+        Err(_) => Url::parse("http://localhost:8080").unwrap()
+    };
+
+    let builder = match WebauthnBuilder::new(&rp_id, &rp_origin) {
+        Ok(builder) => builder,
+        Err(error) => panic!("CENTINOTE_WEBAUTHN_RP_ID: {}", error)
+    };
+
+    match builder.rp_name("Centinote").build() {
+        Ok(webauthn) => webauthn,
+        Err(error) => panic!("{}", error)
+    }
+}
+
+/*
+Commands for the operator, run instead of the web server.
+ grant-admin {username}: Makes the user an administrator.
+ revoke-admin {username}: Makes the user no longer an administrator.
+*/
+async fn run_command(pool: &PgPool, args: &[String]) -> std::io::Result<()> {
+    let (username, is_admin) = match args {
+        [command, username] if command == "grant-admin" => (username, true),
+        [command, username] if command == "revoke-admin" => (username, false),
+        _ => {
+            eprintln!("Usage: centinote [grant-admin | revoke-admin] {{username}}");
+            std::process::exit(2);
+        }
+    };
+
+    match user::User::set_admin(pool, username, is_admin).await {
+        Ok(_) => {
+            println!("{username} is {} an administrator.", if is_admin { "now" } else { "no longer" });
+            Ok(())
+        },
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    }
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     //TODO: Look up resources at relative path from the executable.
@@ -67,18 +300,127 @@ async fn main() -> std::io::Result<()> {
         Err(error) => panic!("{}", error)
     }
 
+    let args: Vec<String> = env::args().skip(1).collect();
+    if !args.is_empty() {
+        return run_command(&pool, &args).await;
+    }
+
+    let trash_retention_days = match env::var("CENTINOTE_TRASH_RETENTION_DAYS") {
+        Ok(value) => match value.parse::<i64>() {
+            Ok(days) if (1..=36500).contains(&days) => days,
+            Ok(_) => panic!("CENTINOTE_TRASH_RETENTION_DAYS: should be between 1 and 36500"),
+            Err(error) => panic!("CENTINOTE_TRASH_RETENTION_DAYS: {}", error)
+        },
+        Err(_) => 30
+    };
+
+    let session_purge_minutes = match env::var("CENTINOTE_SESSION_PURGE_INTERVAL_MINUTES") {
+        Ok(value) => match value.parse::<u64>() {
+            Ok(minutes) if minutes > 0 => minutes,
+            Ok(_) => panic!("CENTINOTE_SESSION_PURGE_INTERVAL_MINUTES: should be positive"),
+            Err(error) => panic!("CENTINOTE_SESSION_PURGE_INTERVAL_MINUTES: {}", error)
+        },
+        Err(_) => 15
+    };
+
+    let throttle_purge_minutes = match env::var("CENTINOTE_THROTTLE_PURGE_INTERVAL_MINUTES") {
+        Ok(value) => match value.parse::<u64>() {
+            Ok(minutes) if minutes > 0 => minutes,
+            Ok(_) => panic!("CENTINOTE_THROTTLE_PURGE_INTERVAL_MINUTES: should be positive"),
+            Err(error) => panic!("CENTINOTE_THROTTLE_PURGE_INTERVAL_MINUTES: {}", error)
+        },
+        Err(_) => 60
+    };
+
+    let storage = attachment_storage();
+    let lifetimes = session_lifetimes();
+    let throttle = login_throttle();
+    let trusted_proxies = web::Data::new(trusted_proxies());
+    let registration = registration();
+    let password_policy = web::Data::new(password_policy());
+    let webauthn = web::Data::new(webauthn());
+    let decoys = match passkey::decoy::Decoys::load(&pool).await {
+        Ok(decoys) => web::Data::new(decoys),
+        Err(error) => panic!("{}", error)
+    };
+
+    tasks::spawn_trash_purge(
+        pool.clone(),
+        storage.clone(),
+        chrono::Duration::days(trash_retention_days),
+        Duration::from_secs(60 * 60));
+
+    tasks::spawn_session_purge(pool.clone(), Duration::from_secs(60 * session_purge_minutes));
+    tasks::spawn_throttle_purge(pool.clone(), throttle, Duration::from_secs(60 * throttle_purge_minutes));
+
     println!("Starting the web server...");
     HttpServer::new(move || { App::new()
             .app_data(web::Data::new(pool.clone()))
+            .app_data(web::Data::new(storage.clone()))
+            .app_data(web::Data::new(lifetimes))
+            .app_data(web::Data::new(throttle))
+            .app_data(trusted_proxies.clone())
+            .app_data(web::Data::new(registration))
+            .app_data(password_policy.clone())
+            .app_data(webauthn.clone())
+            .app_data(decoys.clone())
+            .service(handlers::registration_detail)
             .service(handlers::user_create)
+            .service(handlers::user_detail)
+            .service(handlers::user_update)
+            .service(handlers::user_delete)
+            .service(handlers::password_change)
             .service(handlers::login)
+            .service(handlers::login_totp)
+            .service(handlers::login_passkey_options)
+            .service(handlers::login_passkey)
+            .service(handlers::password_reset)
+            .service(handlers::totp_start)
+            .service(handlers::totp_enable)
+            .service(handlers::totp_disable)
+            .service(handlers::passkey_options)
+            .service(handlers::passkey_create)
+            .service(handlers::passkey_list)
+            .service(handlers::passkey_delete)
             .service(handlers::session_refresh)
+            .service(handlers::session_list)
+            .service(handlers::session_delete_others)
+            .service(handlers::token_create)
+            .service(handlers::token_list)
+            .service(handlers::token_delete)
             .service(handlers::session_delete)
             .service(handlers::entry_list)
+            .service(handlers::entry_search)
+            .service(handlers::trash_list)
+            .service(handlers::trash_restore)
+            .service(handlers::trash_delete)
             .service(handlers::entry_detail)
             .service(handlers::entry_create)
             .service(handlers::entry_update)
             .service(handlers::entry_delete)
+            .service(handlers::revision_list)
+            .service(handlers::revision_detail)
+            .service(handlers::revision_diff)
+            .service(handlers::revision_restore)
+            .service(handlers::tag_list)
+            .service(handlers::attachment_list)
+            .service(handlers::attachment_upload)
+            .service(handlers::attachment_download)
+            .service(handlers::attachment_delete)
+            .service(handlers::entry_export)
+            .service(handlers::entry_import)
+            .service(handlers::invite_create)
+            .service(handlers::invite_list)
+            .service(handlers::invite_delete)
+            .service(handlers::registration_list)
+            .service(handlers::registration_approve)
+            .service(handlers::registration_reject)
+            .service(handlers::admin_user_list)
+            .service(handlers::admin_user_disable)
+            .service(handlers::admin_user_enable)
+            .service(handlers::admin_password_reset)
+            .service(handlers::admin_session_revoke)
+            .service(handlers::admin_stats)
             .service(actix_files::Files::new("/", html_dir).index_file("redirect.html"))
     }).bind(("0.0.0.0", 8080))?.run().await
 }
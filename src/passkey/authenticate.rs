@@ -0,0 +1,414 @@
+use actix_web::{Error, error::{ErrorInternalServerError, ErrorUnauthorized}};
+use chrono::Utc;
+use sqlx::PgPool;
+use webauthn_rs::Webauthn;
+use webauthn_rs::prelude::{
+    AuthenticationResult, Passkey as Credential, PasskeyAuthentication, PublicKeyCredential, RequestChallengeResponse,
+};
+use crate::user::User;
+use super::{Passkey, ceremony, credential, decoy::Decoys};
+
+// The credential is stored again, since its signature counter may have moved.
+async fn record_use_sqlx(
+    db_pool: &PgPool,
+    user_uuid: &str,
+    credential_id: &str,
+    credential: Option<&str>) -> Result<(), sqlx::Error>
+{
+    sqlx::query(
+        "UPDATE passkeys SET last_used = $1, credential = COALESCE($2, credential) \
+         WHERE user_uuid = $3 AND credential_id = $4")
+        .bind(Utc::now().naive_utc())
+        .bind(credential)
+        .bind(user_uuid)
+        .bind(credential_id)
+        .execute(db_pool)
+        .await?;
+
+    Ok(())
+}
+
+/*
+Finds the credential which was used among those of the user. Returns its ID, along with the
+credential serialized again if its signature counter or backup state has moved.
+*/
+fn record_result(credentials: &mut [Credential], result: &AuthenticationResult) -> Result<(String, Option<String>), Error> {
+    let used_credential = match credentials.iter_mut().find(|credential| credential.cred_id() == result.cred_id()) {
+        Some(value) => value,
+        None => return Err(ErrorUnauthorized("Passkey has been removed."))
+    };
+
+    let updated = match used_credential.update_credential(result) {
+        Some(true) => Some(credential::serialize(used_credential)?),
+        _ => None
+    };
+
+    Ok((credential::encode_id(used_credential.cred_id()), updated))
+}
+
+impl Passkey {
+    /*
+    Starts logging in with a passkey. Returns UUID of the ceremony and the options for
+    navigator.credentials.get() of the browser, which only allow passkeys of the user.
+    Usernames without passkeys get decoy options rather than an error.
+    */
+    pub async fn start_authentication(
+        db_pool: &PgPool,
+        webauthn: &Webauthn,
+        decoys: &Decoys,
+        username: &str) -> Result<(String, RequestChallengeResponse), Error>
+    {
+        let user = match User::by_username(db_pool, username).await {
+            Ok(value) => value,
+            Err(error) => {
+                if error.as_response_error().status_code().as_u16() == 404 {
+                    return decoys.options(webauthn, username);
+                } else {
+                    return Err(error);
+                }
+            }
+        };
+
+        let credentials = credential::by_user(db_pool, &user.uuid).await?;
+        if credentials.is_empty() {
+            return decoys.options(webauthn, username);
+        }
+
+        let (mut options, state) = match webauthn.start_passkey_authentication(&credentials) {
+            Ok(value) => value,
+            Err(error) => {
+                println!("{error}");
+                return Err(ErrorInternalServerError("Failed to start passkey login."));
+            }
+        };
+
+        // Decoys cannot know transports, so real options go without them as well.
+        for allowed in options.public_key.allow_credentials.iter_mut() {
+            allowed.transports = None;
+        }
+
+        let ceremony_uuid = ceremony::store(db_pool, &user.uuid, ceremony::Kind::Authentication, &state).await?;
+        Ok((ceremony_uuid, options))
+    }
+
+    // Verifies the signature of the authenticator. Returns UUID of the user who logged in.
+    pub async fn finish_authentication(
+        db_pool: &PgPool,
+        webauthn: &Webauthn,
+        ceremony_uuid: &str,
+        response: &PublicKeyCredential) -> Result<String, Error>
+    {
+        let take_result = ceremony::take::<PasskeyAuthentication>(
+            db_pool,
+            ceremony_uuid,
+            ceremony::Kind::Authentication).await;
+
+        // Decoy ceremonies do not exist, which should not be told apart from a wrong signature.
+        let (user_uuid, state) = match take_result {
+            Ok(value) => value,
+            Err(error) => {
+                if error.as_response_error().status_code().as_u16() == 401 {
+                    return Err(ErrorUnauthorized("Passkey cannot be verified."));
+                } else {
+                    return Err(error);
+                }
+            }
+        };
+
+        let result = match webauthn.finish_passkey_authentication(response, &state) {
+            Ok(value) => value,
+            Err(_) => return Err(ErrorUnauthorized("Passkey cannot be verified."))
+        };
+
+        User::by_uuid(db_pool, &user_uuid).await?.check_active()?;
+
+        let mut credentials = credential::by_user(db_pool, &user_uuid).await?;
+        let (credential_id, updated) = record_result(&mut credentials, &result)?;
+
+        match record_use_sqlx(db_pool, &user_uuid, &credential_id, updated.as_deref()).await {
+            Ok(_) => Ok(user_uuid),
+            Err(error) => {
+                println!("{error}");
+                Err(ErrorInternalServerError("Database error."))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, path::Path};
+    use sqlx::{PgPool, migrate::Migrator};
+    use uuid::Uuid;
+    use webauthn_rs::prelude::{Passkey as Credential, Url, Webauthn, WebauthnBuilder};
+    use crate::passkey::{Passkey, credential, decoy::Decoys, soft_authenticator::{ORIGIN, SoftPasskey}};
+    use crate::user::{User, password::hash_password, username::username_key};
+    use super::record_result;
+
+    const PASSWORD: &str = "correct horse";
+
+    fn webauthn() -> Webauthn {
+        WebauthnBuilder::new("localhost", &Url::parse(ORIGIN).unwrap()).unwrap()
+            .rp_name("Centinote")
+            .build()
+            .unwrap()
+    }
+
+    fn register(webauthn: &Webauthn, authenticator: &mut SoftPasskey) -> Credential {
+        let (options, state) = webauthn.start_passkey_registration(Uuid::new_v4(), "alice", "alice", None).unwrap();
+        webauthn.finish_passkey_registration(&authenticator.create(&options), &state).unwrap()
+    }
+
+    #[test]
+    fn counter_moves_forward() {
+        let webauthn = webauthn();
+        let mut authenticator = SoftPasskey::new();
+        let mut credentials = vec![register(&webauthn, &mut authenticator)];
+        let mut clone = authenticator.clone();
+
+        for counter in 1..=2 {
+            let (options, state) = webauthn.start_passkey_authentication(&credentials).unwrap();
+            let result = webauthn.finish_passkey_authentication(&authenticator.get(&options), &state).unwrap();
+            assert_eq!(result.counter(), counter);
+
+            let (credential_id, updated) = record_result(&mut credentials, &result).unwrap();
+            assert_eq!(credential_id, credential::encode_id(credentials[0].cred_id()));
+            assert!(updated.unwrap().contains(&format!("\"counter\":{counter}")));
+        }
+
+        // A copy of the passkey falls behind, which may tell that it was cloned.
+        let (options, state) = webauthn.start_passkey_authentication(&credentials).unwrap();
+        assert!(webauthn.finish_passkey_authentication(&clone.get(&options), &state).is_err());
+    }
+
+    #[test]
+    fn removed_credential() {
+        let webauthn = webauthn();
+        let mut authenticator = SoftPasskey::new();
+        let credentials = vec![register(&webauthn, &mut authenticator)];
+
+        let (options, state) = webauthn.start_passkey_authentication(&credentials).unwrap();
+        let result = webauthn.finish_passkey_authentication(&authenticator.get(&options), &state).unwrap();
+
+        let mut other_credentials = vec![register(&webauthn, &mut SoftPasskey::new())];
+        assert!(record_result(&mut other_credentials, &result).is_err());
+    }
+
+    /*
+    The tests below run against the database at CENTINOTE_TEST_DATABASE_URL, which is migrated
+    first. Run them with 'cargo test -- --ignored'. Each creates its own user, and removes it.
+    */
+
+    async fn database() -> PgPool {
+        let url = env::var("CENTINOTE_TEST_DATABASE_URL").expect("CENTINOTE_TEST_DATABASE_URL is not set");
+        let db_pool = PgPool::connect(&url).await.unwrap();
+
+        let migrations = Path::new(env!("CARGO_MANIFEST_DIR")).join("sql/migrations");
+        Migrator::new(migrations.as_path()).await.unwrap().run(&db_pool).await.unwrap();
+        db_pool
+    }
+
+    async fn create_user(db_pool: &PgPool) -> User {
+        let uuid = Uuid::new_v4().to_string();
+        let username = format!("passkey-test-{}", &uuid[..8]);
+        sqlx::query(
+            "INSERT INTO users (uuid, username, username_key, password_hash, created) VALUES ($1, $2, $3, $4, now())")
+            .bind(&uuid)
+            .bind(&username)
+            .bind(username_key(&username))
+            .bind(hash_password(PASSWORD).unwrap())
+            .execute(db_pool)
+            .await
+            .unwrap();
+
+        User::by_uuid(db_pool, &uuid).await.unwrap()
+    }
+
+    async fn delete_user(db_pool: &PgPool, user: &User) {
+        for table in ["passkey_ceremonies", "passkeys", "users"] {
+            let column = if table == "users" { "uuid" } else { "user_uuid" };
+            sqlx::query(&format!("DELETE FROM {table} WHERE {column} = $1"))
+                .bind(&user.uuid)
+                .execute(db_pool)
+                .await
+                .unwrap();
+        }
+    }
+
+    async fn register_passkey(db_pool: &PgPool, webauthn: &Webauthn, user: &User, authenticator: &mut SoftPasskey) {
+        let (ceremony, options) = Passkey::start_registration(db_pool, webauthn, user, PASSWORD).await.unwrap();
+        let response = authenticator.create(&options);
+        Passkey::finish_registration(db_pool, webauthn, &user.uuid, &ceremony, "Soft key", &response).await.unwrap();
+    }
+
+    fn status(error: actix_web::Error) -> (u16, String) {
+        (error.as_response_error().status_code().as_u16(), error.to_string())
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs CENTINOTE_TEST_DATABASE_URL"]
+    async fn register_and_log_in() {
+        let db_pool = database().await;
+        let webauthn = webauthn();
+        let decoys = Decoys::new(b"test key").unwrap();
+        let user = create_user(&db_pool).await;
+        let mut authenticator = SoftPasskey::new();
+        let mut clone = authenticator.clone();
+
+        register_passkey(&db_pool, &webauthn, &user, &mut authenticator).await;
+
+        // The authenticator is excluded when registering again, so its response is refused.
+        let (ceremony, options) = Passkey::start_registration(&db_pool, &webauthn, &user, PASSWORD).await.unwrap();
+        assert!(options.public_key.exclude_credentials.as_ref().unwrap().iter()
+            .any(|excluded| excluded.id.as_ref() == authenticator.credential_id.as_slice()));
+        let response = authenticator.clone().create(&options);
+        let error = Passkey::finish_registration(&db_pool, &webauthn, &user.uuid, &ceremony, "Again", &response).await;
+        assert_eq!(status(error.err().unwrap()).0, 400);
+
+        for counter in 1..=2 {
+            let (ceremony, options) = Passkey::start_authentication(&db_pool, &webauthn, &decoys, &user.username).await.unwrap();
+            assert!(authenticator.is_allowed(&options));
+
+            let response = authenticator.get(&options);
+            let user_uuid = Passkey::finish_authentication(&db_pool, &webauthn, &ceremony, &response).await.unwrap();
+            assert_eq!(user_uuid, user.uuid);
+
+            let stored = credential::by_user(&db_pool, &user.uuid).await.unwrap();
+            assert!(credential::serialize(&stored[0]).unwrap().contains(&format!("\"counter\":{counter}")));
+        }
+
+        // The stored counter has moved past that of the copy.
+        let (ceremony, options) = Passkey::start_authentication(&db_pool, &webauthn, &decoys, &user.username).await.unwrap();
+        let error = Passkey::finish_authentication(&db_pool, &webauthn, &ceremony, &clone.get(&options)).await;
+        assert_eq!(status(error.err().unwrap()).0, 401);
+
+        delete_user(&db_pool, &user).await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs CENTINOTE_TEST_DATABASE_URL"]
+    async fn ceremony_cannot_be_reused() {
+        let db_pool = database().await;
+        let webauthn = webauthn();
+        let decoys = Decoys::new(b"test key").unwrap();
+        let user = create_user(&db_pool).await;
+        let mut authenticator = SoftPasskey::new();
+
+        let (ceremony, options) = Passkey::start_registration(&db_pool, &webauthn, &user, PASSWORD).await.unwrap();
+        let response = authenticator.create(&options);
+        Passkey::finish_registration(&db_pool, &webauthn, &user.uuid, &ceremony, "Soft key", &response).await.unwrap();
+        let error = Passkey::finish_registration(&db_pool, &webauthn, &user.uuid, &ceremony, "Soft key", &response).await;
+        assert_eq!(status(error.err().unwrap()).0, 401);
+
+        let (ceremony, options) = Passkey::start_authentication(&db_pool, &webauthn, &decoys, &user.username).await.unwrap();
+        let response = authenticator.get(&options);
+        Passkey::finish_authentication(&db_pool, &webauthn, &ceremony, &response).await.unwrap();
+
+        let error = Passkey::finish_authentication(&db_pool, &webauthn, &ceremony, &response).await;
+        assert_eq!(status(error.err().unwrap()), (401, "Passkey cannot be verified.".to_string()));
+
+        // Neither can a ceremony of the other kind be used.
+        let (ceremony, _) = Passkey::start_authentication(&db_pool, &webauthn, &decoys, &user.username).await.unwrap();
+        let (_, options) = Passkey::start_registration(&db_pool, &webauthn, &user, PASSWORD).await.unwrap();
+        let response = SoftPasskey::new().create(&options);
+        let error = Passkey::finish_registration(&db_pool, &webauthn, &user.uuid, &ceremony, "Soft key", &response).await;
+        assert_eq!(status(error.err().unwrap()).0, 401);
+
+        delete_user(&db_pool, &user).await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs CENTINOTE_TEST_DATABASE_URL"]
+    async fn open_ceremonies_are_capped() {
+        let db_pool = database().await;
+        let webauthn = webauthn();
+        let decoys = Decoys::new(b"test key").unwrap();
+        let user = create_user(&db_pool).await;
+        let mut authenticator = SoftPasskey::new();
+
+        register_passkey(&db_pool, &webauthn, &user, &mut authenticator).await;
+        let (registration, options) = Passkey::start_registration(&db_pool, &webauthn, &user, PASSWORD).await.unwrap();
+
+        let mut ceremonies = Vec::new();
+        for _ in 0..20 {
+            ceremonies.push(Passkey::start_authentication(&db_pool, &webauthn, &decoys, &user.username).await.unwrap());
+        }
+
+        let open: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM passkey_ceremonies WHERE user_uuid = $1")
+            .bind(&user.uuid)
+            .fetch_one(&db_pool)
+            .await
+            .unwrap();
+        assert_eq!(open, 5);
+
+        // The oldest logins are replaced, while the registration of the other kind is kept.
+        let (ceremony, options_0) = &ceremonies[0];
+        let error = Passkey::finish_authentication(&db_pool, &webauthn, ceremony, &authenticator.get(options_0)).await;
+        assert_eq!(status(error.err().unwrap()).0, 401);
+
+        let (ceremony, options_19) = &ceremonies[19];
+        Passkey::finish_authentication(&db_pool, &webauthn, ceremony, &authenticator.get(options_19)).await.unwrap();
+
+        let response = SoftPasskey::new().create(&options);
+        Passkey::finish_registration(&db_pool, &webauthn, &user.uuid, &registration, "Other key", &response).await.unwrap();
+
+        delete_user(&db_pool, &user).await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs CENTINOTE_TEST_DATABASE_URL"]
+    async fn ceremony_expires() {
+        let db_pool = database().await;
+        let webauthn = webauthn();
+        let decoys = Decoys::new(b"test key").unwrap();
+        let user = create_user(&db_pool).await;
+        let mut authenticator = SoftPasskey::new();
+
+        register_passkey(&db_pool, &webauthn, &user, &mut authenticator).await;
+
+        let (ceremony, options) = Passkey::start_authentication(&db_pool, &webauthn, &decoys, &user.username).await.unwrap();
+        sqlx::query("UPDATE passkey_ceremonies SET expiry = now() AT TIME ZONE 'UTC' - interval '1 second' WHERE uuid = $1")
+            .bind(&ceremony)
+            .execute(&db_pool)
+            .await
+            .unwrap();
+
+        let error = Passkey::finish_authentication(&db_pool, &webauthn, &ceremony, &authenticator.get(&options)).await;
+        assert_eq!(status(error.err().unwrap()), (401, "Passkey cannot be verified.".to_string()));
+
+        let (ceremony, options) = Passkey::start_registration(&db_pool, &webauthn, &user, PASSWORD).await.unwrap();
+        sqlx::query("UPDATE passkey_ceremonies SET expiry = now() AT TIME ZONE 'UTC' - interval '1 second' WHERE uuid = $1")
+            .bind(&ceremony)
+            .execute(&db_pool)
+            .await
+            .unwrap();
+
+        let response = SoftPasskey::new().create(&options);
+        let error = Passkey::finish_registration(&db_pool, &webauthn, &user.uuid, &ceremony, "Soft key", &response).await;
+        assert_eq!(status(error.err().unwrap()).0, 401);
+
+        delete_user(&db_pool, &user).await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs CENTINOTE_TEST_DATABASE_URL"]
+    async fn decoys_for_unknown_usernames() {
+        let db_pool = database().await;
+        let webauthn = webauthn();
+        let decoys = Decoys::new(b"test key").unwrap();
+        let user = create_user(&db_pool).await;
+        let mut authenticator = SoftPasskey::new();
+
+        // Users without passkeys get decoys, just like usernames which do not exist.
+        let unknown = format!("{}-unknown", user.username);
+        for username in [unknown.as_str(), user.username.as_str()] {
+            let (ceremony, options) = Passkey::start_authentication(&db_pool, &webauthn, &decoys, username).await.unwrap();
+            assert!(!authenticator.is_allowed(&options));
+
+            let error = Passkey::finish_authentication(&db_pool, &webauthn, &ceremony, &authenticator.get(&options)).await;
+            assert_eq!(status(error.err().unwrap()), (401, "Passkey cannot be verified.".to_string()));
+        }
+
+        delete_user(&db_pool, &user).await;
+    }
+}
@@ -0,0 +1,138 @@
+use actix_web::{Error, error::{ErrorInternalServerError, ErrorUnauthorized}};
+use chrono::{Duration, NaiveDateTime, Utc};
+use serde::{Serialize, de::DeserializeOwned};
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+// Time given to the authenticator, which may wait for the user to touch it.
+const CEREMONY_MINUTES: i64 = 5;
+
+// Unfinished ceremonies kept per user and kind. Login options are given to anyone who
+// knows the username, so starting more replaces the oldest instead of piling up.
+const MAX_OPEN_CEREMONIES: i64 = 4;
+
+/*
+A registration or login in progress. The state holds the challenge sent to the client, and
+is kept here between the request giving the options and the one sending the signed response.
+*/
+#[derive(Clone, Copy)]
+pub enum Kind {
+    Registration,
+    Authentication,
+}
+
+impl Kind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Kind::Registration => "registration",
+            Kind::Authentication => "authentication",
+        }
+    }
+}
+
+async fn store_sqlx(
+    db_pool: &PgPool,
+    user_uuid: &str,
+    kind: Kind,
+    state: &str) -> Result<String, sqlx::Error>
+{
+    let uuid = Uuid::new_v4().to_string();
+    let now = Utc::now().naive_utc();
+
+    // Ceremonies left behind by the user are cleaned up here, along with the oldest ones over the limit.
+    sqlx::query(
+        "DELETE FROM passkey_ceremonies WHERE user_uuid = $1 AND (expiry < $2 OR uuid IN \
+         (SELECT uuid FROM passkey_ceremonies WHERE user_uuid = $1 AND kind = $3 ORDER BY expiry DESC OFFSET $4))")
+        .bind(user_uuid)
+        .bind(now)
+        .bind(kind.as_str())
+        .bind(MAX_OPEN_CEREMONIES - 1)
+        .execute(db_pool)
+        .await?;
+
+    sqlx::query(
+        "INSERT INTO passkey_ceremonies (uuid, user_uuid, kind, state, expiry) VALUES ($1, $2, $3, $4, $5)")
+        .bind(&uuid)
+        .bind(user_uuid)
+        .bind(kind.as_str())
+        .bind(state)
+        .bind(now + Duration::minutes(CEREMONY_MINUTES))
+        .execute(db_pool)
+        .await?;
+
+    Ok(uuid)
+}
+
+// A ceremony is deleted as it is taken, so that its challenge can only be answered once.
+async fn take_sqlx(
+    db_pool: &PgPool,
+    ceremony_uuid: &str,
+    kind: Kind) -> Result<(String, String, NaiveDateTime), sqlx::Error>
+{
+    let row = sqlx::query(
+        "DELETE FROM passkey_ceremonies WHERE uuid = $1 AND kind = $2 RETURNING user_uuid, state, expiry")
+        .bind(ceremony_uuid)
+        .bind(kind.as_str())
+        .fetch_one(db_pool)
+        .await?;
+
+    Ok((row.try_get("user_uuid")?, row.try_get("state")?, row.try_get("expiry")?))
+}
+
+// Returns UUID of the ceremony, which the client sends back along with its response.
+pub async fn store<T: Serialize>(
+    db_pool: &PgPool,
+    user_uuid: &str,
+    kind: Kind,
+    state: &T) -> Result<String, Error>
+{
+    let state = match serde_json::to_string(state) {
+        Ok(value) => value,
+        Err(error) => {
+            println!("{error}");
+            return Err(ErrorInternalServerError("Failed to store passkey ceremony."));
+        }
+    };
+
+    match store_sqlx(db_pool, user_uuid, kind, &state).await {
+        Ok(value) => Ok(value),
+        Err(error) => {
+            println!("{error}");
+            Err(ErrorInternalServerError("Database error."))
+        }
+    }
+}
+
+// Returns UUID of the user who started the ceremony, along with its state.
+pub async fn take<T: DeserializeOwned>(
+    db_pool: &PgPool,
+    ceremony_uuid: &str,
+    kind: Kind) -> Result<(String, T), Error>
+{
+    let (user_uuid, state, expiry) = match take_sqlx(db_pool, ceremony_uuid, kind).await {
+        Ok(value) => value,
+        Err(error) => {
+            return match error {
+                sqlx::Error::RowNotFound => {
+                    Err(ErrorUnauthorized("Passkey ceremony has expired or does not exist."))
+                },
+                _ => {
+                    println!("{error}");
+                    Err(ErrorInternalServerError("Database error."))
+                }
+            };
+        }
+    };
+
+    if expiry < Utc::now().naive_utc() {
+        return Err(ErrorUnauthorized("Passkey ceremony has expired or does not exist."));
+    }
+
+    match serde_json::from_str(&state) {
+        Ok(state) => Ok((user_uuid, state)),
+        Err(error) => {
+            println!("{error}");
+            Err(ErrorInternalServerError("Failed to read passkey ceremony."))
+        }
+    }
+}
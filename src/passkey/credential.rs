@@ -0,0 +1,50 @@
+use actix_web::{Error, error::ErrorInternalServerError};
+use data_encoding::BASE64URL_NOPAD;
+use sqlx::{PgPool, Row};
+use webauthn_rs::prelude::{CredentialID, Passkey as Credential};
+
+// Credential IDs are stored the way browsers encode them, which keeps them readable in the database.
+pub fn encode_id(credential_id: &CredentialID) -> String {
+    BASE64URL_NOPAD.encode(credential_id.as_ref())
+}
+
+async fn by_user_sqlx(
+    db_pool: &PgPool,
+    user_uuid: &str) -> Result<Vec<String>, sqlx::Error>
+{
+    let rows = sqlx::query("SELECT credential FROM passkeys WHERE user_uuid = $1")
+        .bind(user_uuid)
+        .fetch_all(db_pool)
+        .await?;
+
+    rows.iter().map(|row| row.try_get("credential")).collect()
+}
+
+// Returns every credential of the user.
+pub async fn by_user(db_pool: &PgPool, user_uuid: &str) -> Result<Vec<Credential>, Error> {
+    let credentials = match by_user_sqlx(db_pool, user_uuid).await {
+        Ok(value) => value,
+        Err(error) => {
+            println!("{error}");
+            return Err(ErrorInternalServerError("Database error."));
+        }
+    };
+
+    credentials.iter().map(|credential| match serde_json::from_str(credential) {
+        Ok(value) => Ok(value),
+        Err(error) => {
+            println!("{error}");
+            Err(ErrorInternalServerError("Failed to read passkey."))
+        }
+    }).collect()
+}
+
+pub fn serialize(credential: &Credential) -> Result<String, Error> {
+    match serde_json::to_string(credential) {
+        Ok(value) => Ok(value),
+        Err(error) => {
+            println!("{error}");
+            Err(ErrorInternalServerError("Failed to store passkey."))
+        }
+    }
+}
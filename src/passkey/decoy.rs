@@ -0,0 +1,159 @@
+use actix_web::{Error, error::ErrorInternalServerError};
+use rand::{rngs::OsRng, RngCore};
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+use webauthn_rs::Webauthn;
+use webauthn_rs::fake::{FakePasskeyDistribution, WebauthnFakeCredentialGenerator};
+use webauthn_rs::prelude::RequestChallengeResponse;
+use webauthn_rs_proto::AllowCredentials;
+use crate::user::username::username_key;
+
+const KEY_NAME: &str = "passkey_decoy";
+
+// The first server to start stores its key, and every later one reads it.
+async fn load_key_sqlx(db_pool: &PgPool) -> Result<Vec<u8>, sqlx::Error> {
+    let mut key = vec![0u8; 32];
+    OsRng.fill_bytes(&mut key);
+
+    sqlx::query("INSERT INTO server_keys (name, value) VALUES ($1, $2) ON CONFLICT (name) DO NOTHING")
+        .bind(KEY_NAME)
+        .bind(&key)
+        .execute(db_pool)
+        .await?;
+
+    let row = sqlx::query("SELECT value FROM server_keys WHERE name = $1")
+        .bind(KEY_NAME)
+        .fetch_one(db_pool)
+        .await?;
+
+    row.try_get("value")
+}
+
+/*
+Login options for usernames which do not exist or have no passkeys, so that the response does
+not tell them apart from users with passkeys. Credential IDs are derived from the username with
+a key which is kept secret and never changes, so asking again gives the same ones.
+*/
+pub struct Decoys {
+    generator: WebauthnFakeCredentialGenerator<FakePasskeyDistribution>,
+}
+
+impl Decoys {
+    pub async fn load(db_pool: &PgPool) -> Result<Self, Error> {
+        let key = match load_key_sqlx(db_pool).await {
+            Ok(value) => value,
+            Err(error) => {
+                println!("{error}");
+                return Err(ErrorInternalServerError("Database error."));
+            }
+        };
+
+        Self::new(&key)
+    }
+
+    pub fn new(key: &[u8]) -> Result<Self, Error> {
+        match WebauthnFakeCredentialGenerator::new(key) {
+            Ok(generator) => Ok(Decoys { generator }),
+            Err(error) => {
+                println!("{error}");
+                Err(ErrorInternalServerError("Failed to set up passkey decoys."))
+            }
+        }
+    }
+
+    // The ceremony is never stored, so finishing it fails like a wrong signature does.
+    pub fn options(&self, webauthn: &Webauthn, username: &str) -> Result<(String, RequestChallengeResponse), Error> {
+        let username = username_key(username);
+        let credential_ids = match self.generator.generate(username.as_bytes()) {
+            Ok(value) => value,
+            Err(error) => {
+                println!("{error}");
+                return Err(ErrorInternalServerError("Failed to start passkey login."));
+            }
+        };
+
+        let (mut options, _) = match webauthn.start_discoverable_authentication() {
+            Ok(value) => value,
+            Err(error) => {
+                println!("{error}");
+                return Err(ErrorInternalServerError("Failed to start passkey login."));
+            }
+        };
+
+        // Shaped like the options of POST /api/login/passkey/options for a real user.
+        options.mediation = None;
+        options.public_key.extensions = None;
+        options.public_key.allow_credentials = credential_ids.into_iter()
+            .map(|id| AllowCredentials {
+                type_: "public-key".to_string(),
+                id: id.into(),
+                transports: None,
+            })
+            .collect();
+
+        Ok((Uuid::new_v4().to_string(), options))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+    use uuid::Uuid;
+    use webauthn_rs::prelude::{Url, Webauthn, WebauthnBuilder};
+    use crate::passkey::soft_authenticator::{ORIGIN, SoftPasskey};
+    use super::Decoys;
+
+    fn webauthn() -> Webauthn {
+        WebauthnBuilder::new("localhost", &Url::parse(ORIGIN).unwrap()).unwrap()
+            .rp_name("Centinote")
+            .build()
+            .unwrap()
+    }
+
+    fn credential_ids(decoys: &Decoys, username: &str) -> Vec<Vec<u8>> {
+        let (_, options) = decoys.options(&webauthn(), username).unwrap();
+        options.public_key.allow_credentials.iter().map(|allowed| allowed.id.to_vec()).collect()
+    }
+
+    #[test]
+    fn same_username_same_credentials() {
+        let decoys = Decoys::new(b"test key").unwrap();
+        let usernames: Vec<String> = (0..20).map(|i| format!("user{i}")).collect();
+
+        for username in usernames.iter() {
+            assert_eq!(credential_ids(&decoys, username), credential_ids(&decoys, username));
+            assert_eq!(credential_ids(&decoys, username), credential_ids(&decoys, &username.to_uppercase()));
+        }
+
+        // Full width letters are folded along with case, as they are for lookups.
+        assert_eq!(credential_ids(&decoys, "user0"), credential_ids(&decoys, "ＵＳＥＲ０"));
+
+        let other = Decoys::new(b"other key").unwrap();
+        assert!(usernames.iter().any(|username| credential_ids(&decoys, username) != credential_ids(&other, username)));
+    }
+
+    #[test]
+    fn looks_like_real_options() {
+        let webauthn = webauthn();
+        let mut authenticator = SoftPasskey::new();
+        let (options, registration) = webauthn.start_passkey_registration(Uuid::new_v4(), "alice", "alice", None).unwrap();
+        let credential = webauthn.finish_passkey_registration(&authenticator.create(&options), &registration).unwrap();
+        let (real, _) = webauthn.start_passkey_authentication(&[credential]).unwrap();
+
+        let decoys = Decoys::new(b"test key").unwrap();
+        let username = (0..).map(|i| format!("user{i}"))
+            .find(|username| credential_ids(&decoys, username).len() == 1)
+            .unwrap();
+        let (ceremony, decoy) = decoys.options(&webauthn, &username).unwrap();
+        assert!(Uuid::parse_str(&ceremony).is_ok());
+
+        let shape = |value: Value| -> Value {
+            let mut value = value;
+            value["publicKey"]["challenge"] = Value::Null;
+            value["publicKey"]["allowCredentials"][0]["id"] = Value::Null;
+            value
+        };
+
+        assert_eq!(shape(serde_json::to_value(&decoy).unwrap()), shape(serde_json::to_value(&real).unwrap()));
+    }
+}
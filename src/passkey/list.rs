@@ -0,0 +1,47 @@
+use actix_web::{Error, error::ErrorInternalServerError};
+use chrono::NaiveDateTime;
+use sqlx::{PgPool, Row, postgres::PgRow};
+use super::Passkey;
+
+fn passkey_from_row(row: &PgRow) -> Result<Passkey, sqlx::Error> {
+    let created: NaiveDateTime = row.try_get("created")?;
+    let last_used: Option<NaiveDateTime> = row.try_get("last_used")?;
+
+    Ok(Passkey {
+        uuid: row.try_get("uuid")?,
+        user_uuid: row.try_get("user_uuid")?,
+        name: row.try_get("name")?,
+        created: created.and_utc().to_rfc3339(),
+        last_used: last_used.map(|value| value.and_utc().to_rfc3339()),
+    })
+}
+
+async fn by_user_sqlx(
+    db_pool: &PgPool,
+    user_uuid: &str) -> Result<Vec<Passkey>, sqlx::Error>
+{
+    let rows = sqlx::query(
+        "SELECT uuid, user_uuid, name, created, last_used FROM passkeys \
+         WHERE user_uuid = $1 ORDER BY created DESC")
+        .bind(user_uuid)
+        .fetch_all(db_pool)
+        .await?;
+
+    rows.iter().map(passkey_from_row).collect()
+}
+
+impl Passkey {
+    // Returns every passkey of the user, recently registered first.
+    pub async fn list_by_user(
+        db_pool: &PgPool,
+        user_uuid: &str) -> Result<Vec<Self>, Error>
+    {
+        match by_user_sqlx(db_pool, user_uuid).await {
+            Ok(value) => Ok(value),
+            Err(error) => {
+                println!("{error}");
+                Err(ErrorInternalServerError("Database error."))
+            }
+        }
+    }
+}
@@ -0,0 +1,24 @@
+pub mod authenticate;
+pub mod ceremony;
+pub mod credential;
+pub mod decoy;
+pub mod delete;
+pub mod list;
+pub mod register;
+
+#[cfg(test)]
+mod soft_authenticator;
+
+pub const MAX_NAME_LENGTH: usize = 64;
+
+/*
+A passkey registered by a user, which can be used to log in without a password.
+The credential itself, holding the public key and signature counter, never leaves the database.
+*/
+pub struct Passkey {
+    pub uuid: String,
+    pub user_uuid: String,
+    pub name: String,
+    pub created: String,
+    pub last_used: Option<String>,
+}
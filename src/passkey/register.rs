@@ -0,0 +1,140 @@
+use actix_web::{Error, error::{ErrorBadRequest, ErrorConflict, ErrorInternalServerError}};
+use chrono::{NaiveDateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+use webauthn_rs::Webauthn;
+use webauthn_rs::prelude::{CreationChallengeResponse, PasskeyRegistration, RegisterPublicKeyCredential};
+use crate::user::User;
+use super::{Passkey, MAX_NAME_LENGTH, ceremony, credential};
+
+fn validate_name(name: &str) -> Result<String, Error> {
+    let name = name.trim();
+
+    if name.is_empty() {
+        return Err(ErrorBadRequest("Passkey name should not be empty."));
+    }
+
+    if name.chars().count() > MAX_NAME_LENGTH {
+        return Err(ErrorBadRequest("Passkey name is too long."));
+    }
+
+    Ok(name.to_string())
+}
+
+async fn insert_passkey_sqlx(
+    db_pool: &PgPool,
+    passkey: &Passkey,
+    credential_id: &str,
+    credential: &str,
+    created: NaiveDateTime) -> Result<(), sqlx::Error>
+{
+    sqlx::query(
+        "INSERT INTO passkeys (uuid, user_uuid, name, credential_id, credential, created) \
+         VALUES ($1, $2, $3, $4, $5, $6)")
+        .bind(&passkey.uuid)
+        .bind(&passkey.user_uuid)
+        .bind(&passkey.name)
+        .bind(credential_id)
+        .bind(credential)
+        .bind(created)
+        .execute(db_pool)
+        .await?;
+
+    Ok(())
+}
+
+impl Passkey {
+    /*
+    Starts registering a passkey. Returns UUID of the ceremony and the options for
+    navigator.credentials.create() of the browser.
+    The password is confirmed, so that a stolen session cannot add a way to log in.
+    */
+    pub async fn start_registration(
+        db_pool: &PgPool,
+        webauthn: &Webauthn,
+        user: &User,
+        password: &str) -> Result<(String, CreationChallengeResponse), Error>
+    {
+        user.verify_password(password)?;
+
+        let user_handle = match Uuid::parse_str(&user.uuid) {
+            Ok(value) => value,
+            Err(error) => {
+                println!("{error}");
+                return Err(ErrorInternalServerError("User UUID is malformed."));
+            }
+        };
+
+        // Authenticators which already hold a passkey of the user are excluded.
+        let exclude_credentials = credential::by_user(db_pool, &user.uuid).await?
+            .iter()
+            .map(|credential| credential.cred_id().clone())
+            .collect();
+
+        let start_result = webauthn.start_passkey_registration(
+            user_handle,
+            &user.username,
+            &user.username,
+            Some(exclude_credentials));
+
+        let (options, state) = match start_result {
+            Ok(value) => value,
+            Err(error) => {
+                println!("{error}");
+                return Err(ErrorInternalServerError("Failed to start passkey registration."));
+            }
+        };
+
+        let ceremony_uuid = ceremony::store(db_pool, &user.uuid, ceremony::Kind::Registration, &state).await?;
+        Ok((ceremony_uuid, options))
+    }
+
+    // Verifies the response of the authenticator, and stores the new passkey.
+    pub async fn finish_registration(
+        db_pool: &PgPool,
+        webauthn: &Webauthn,
+        user_uuid: &str,
+        ceremony_uuid: &str,
+        name: &str,
+        response: &RegisterPublicKeyCredential) -> Result<Self, Error>
+    {
+        let name = validate_name(name)?;
+
+        let (ceremony_user_uuid, state) = ceremony::take::<PasskeyRegistration>(
+            db_pool,
+            ceremony_uuid,
+            ceremony::Kind::Registration).await?;
+
+        if ceremony_user_uuid != user_uuid {
+            return Err(ErrorBadRequest("Passkey ceremony belongs to another user."));
+        }
+
+        let new_credential = match webauthn.finish_passkey_registration(response, &state) {
+            Ok(value) => value,
+            Err(_) => return Err(ErrorBadRequest("Passkey cannot be verified."))
+        };
+
+        let created = Utc::now().naive_utc();
+        let passkey = Passkey {
+            uuid: Uuid::new_v4().to_string(),
+            user_uuid: user_uuid.to_string(),
+            name,
+            created: created.and_utc().to_rfc3339(),
+            last_used: None,
+        };
+
+        let credential_id = credential::encode_id(new_credential.cred_id());
+        let serialized = credential::serialize(&new_credential)?;
+
+        match insert_passkey_sqlx(db_pool, &passkey, &credential_id, &serialized, created).await {
+            Ok(_) => Ok(passkey),
+            Err(sqlx::Error::Database(error)) if error.constraint() == Some("passkeys_credential_id_key") => {
+                Err(ErrorConflict("Passkey is already registered."))
+            },
+            Err(error) => {
+                println!("{error}");
+                Err(ErrorInternalServerError("Database error."))
+            }
+        }
+    }
+}
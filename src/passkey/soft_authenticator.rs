@@ -0,0 +1,189 @@
+use data_encoding::BASE64URL_NOPAD;
+use openssl::bn::{BigNum, BigNumContext};
+use openssl::ec::{EcGroup, EcKey};
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::{PKey, Private};
+use openssl::sign::Signer;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use webauthn_rs::prelude::{
+    CreationChallengeResponse, PublicKeyCredential, RegisterPublicKeyCredential, RequestChallengeResponse,
+};
+
+pub const ORIGIN: &str = "http://localhost:8080";
+
+// Flags of authenticator data.
+const USER_PRESENT: u8 = 0x01;
+const USER_VERIFIED: u8 = 0x04;
+const ATTESTED_CREDENTIAL: u8 = 0x40;
+
+/*
+Software authenticator for tests, holding a single ES256 passkey. It answers ceremonies the
+way a browser and a platform authenticator do together, with 'none' attestation.
+Cloning it gives a copy of the passkey with its own signature counter.
+*/
+#[derive(Clone)]
+pub struct SoftPasskey {
+    key: PKey<Private>,
+    pub credential_id: Vec<u8>,
+    user_handle: Vec<u8>,
+    pub counter: u32,
+}
+
+fn cbor_head(major: u8, length: usize) -> Vec<u8> {
+    match length {
+        0..=23 => vec![major << 5 | length as u8],
+        24..=255 => vec![major << 5 | 24, length as u8],
+        _ => {
+            let mut head = vec![major << 5 | 25];
+            head.extend((length as u16).to_be_bytes());
+            head
+        }
+    }
+}
+
+fn cbor_bytes(data: &[u8]) -> Vec<u8> {
+    let mut bytes = cbor_head(2, data.len());
+    bytes.extend(data);
+    bytes
+}
+
+fn cbor_text(text: &str) -> Vec<u8> {
+    let mut bytes = cbor_head(3, text.len());
+    bytes.extend(text.as_bytes());
+    bytes
+}
+
+fn decode(value: &Value) -> Vec<u8> {
+    BASE64URL_NOPAD.decode(value.as_str().unwrap().as_bytes()).unwrap()
+}
+
+fn client_data(kind: &str, challenge: &Value) -> Vec<u8> {
+    json!({
+        "type": kind,
+        "challenge": challenge,
+        "origin": ORIGIN,
+        "crossOrigin": false,
+    }).to_string().into_bytes()
+}
+
+fn authenticator_data(rp_id: &Value, flags: u8, counter: u32) -> Vec<u8> {
+    let mut data = Sha256::digest(rp_id.as_str().unwrap().as_bytes()).to_vec();
+    data.push(flags);
+    data.extend(counter.to_be_bytes());
+    data
+}
+
+impl SoftPasskey {
+    pub fn new() -> Self {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+
+        let mut credential_id = vec![0u8; 32];
+        openssl::rand::rand_bytes(&mut credential_id).unwrap();
+
+        SoftPasskey {
+            key,
+            credential_id,
+            user_handle: Vec::new(),
+            counter: 0,
+        }
+    }
+
+    // COSE_Key of the public key, as in RFC 8152.
+    fn cose_key(&self) -> Vec<u8> {
+        let ec_key = self.key.ec_key().unwrap();
+        let mut context = BigNumContext::new().unwrap();
+        let mut x = BigNum::new().unwrap();
+        let mut y = BigNum::new().unwrap();
+        ec_key.public_key().affine_coordinates(ec_key.group(), &mut x, &mut y, &mut context).unwrap();
+
+        let mut key = cbor_head(5, 5);
+        key.extend([0x01, 0x02]); // kty: EC2
+        key.extend([0x03, 0x26]); // alg: ES256
+        key.extend([0x20, 0x01]); // crv: P-256
+        key.push(0x21);
+        key.extend(cbor_bytes(&x.to_vec_padded(32).unwrap()));
+        key.push(0x22);
+        key.extend(cbor_bytes(&y.to_vec_padded(32).unwrap()));
+        key
+    }
+
+    // Result of navigator.credentials.create(), which creates the passkey for the user.
+    pub fn create(&mut self, options: &CreationChallengeResponse) -> RegisterPublicKeyCredential {
+        let options = serde_json::to_value(options).unwrap();
+        let public_key = &options["publicKey"];
+        self.user_handle = decode(&public_key["user"]["id"]);
+
+        let mut auth_data = authenticator_data(
+            &public_key["rp"]["id"],
+            USER_PRESENT | USER_VERIFIED | ATTESTED_CREDENTIAL,
+            self.counter);
+        auth_data.extend([0u8; 16]);
+        auth_data.extend((self.credential_id.len() as u16).to_be_bytes());
+        auth_data.extend(&self.credential_id);
+        auth_data.extend(self.cose_key());
+
+        let mut attestation_object = cbor_head(5, 3);
+        attestation_object.extend(cbor_text("fmt"));
+        attestation_object.extend(cbor_text("none"));
+        attestation_object.extend(cbor_text("attStmt"));
+        attestation_object.extend(cbor_head(5, 0));
+        attestation_object.extend(cbor_text("authData"));
+        attestation_object.extend(cbor_bytes(&auth_data));
+
+        let client_data_json = client_data("webauthn.create", &public_key["challenge"]);
+        let credential_id = BASE64URL_NOPAD.encode(&self.credential_id);
+
+        serde_json::from_value(json!({
+            "id": credential_id,
+            "rawId": credential_id,
+            "type": "public-key",
+            "response": {
+                "attestationObject": BASE64URL_NOPAD.encode(&attestation_object),
+                "clientDataJSON": BASE64URL_NOPAD.encode(&client_data_json),
+            },
+            "extensions": {},
+        })).unwrap()
+    }
+
+    /*
+    Result of navigator.credentials.get(), signed with the passkey whether or not the options
+    allow it, like a misbehaving client could. Every signature moves the counter forward.
+    */
+    pub fn get(&mut self, options: &RequestChallengeResponse) -> PublicKeyCredential {
+        let options = serde_json::to_value(options).unwrap();
+        let public_key = &options["publicKey"];
+
+        self.counter += 1;
+        let auth_data = authenticator_data(&public_key["rpId"], USER_PRESENT | USER_VERIFIED, self.counter);
+        let client_data_json = client_data("webauthn.get", &public_key["challenge"]);
+
+        let mut signer = Signer::new(MessageDigest::sha256(), &self.key).unwrap();
+        signer.update(&auth_data).unwrap();
+        signer.update(&Sha256::digest(&client_data_json)).unwrap();
+        let signature = signer.sign_to_vec().unwrap();
+
+        let credential_id = BASE64URL_NOPAD.encode(&self.credential_id);
+
+        serde_json::from_value(json!({
+            "id": credential_id,
+            "rawId": credential_id,
+            "type": "public-key",
+            "response": {
+                "authenticatorData": BASE64URL_NOPAD.encode(&auth_data),
+                "clientDataJSON": BASE64URL_NOPAD.encode(&client_data_json),
+                "signature": BASE64URL_NOPAD.encode(&signature),
+                "userHandle": BASE64URL_NOPAD.encode(&self.user_handle),
+            },
+            "extensions": {},
+        })).unwrap()
+    }
+
+    // Whether the options allow this passkey, which a browser checks before asking for it.
+    pub fn is_allowed(&self, options: &RequestChallengeResponse) -> bool {
+        options.public_key.allow_credentials.iter()
+            .any(|allowed| allowed.id.as_ref() == self.credential_id.as_slice())
+    }
+}
@@ -0,0 +1,34 @@
+use actix_web::{FromRequest, HttpRequest, dev::Payload, error::{Error, ErrorForbidden}};
+use std::pin::Pin;
+use std::future::Future;
+use crate::user::User;
+use super::from_request::Credentials;
+
+/*
+A session of an administrator, for the API paths under /api/admin, which are not of a
+particular user. Access tokens need the 'admin' scope there.
+*/
+pub struct Admin {
+    pub user: User,
+}
+
+impl FromRequest for Admin {
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let credentials = Credentials::from_request(req);
+
+        Box::pin(async move {
+            let db_pool = credentials.db_pool.clone();
+            let session = credentials.authenticate(None).await?;
+
+            let user = User::by_uuid(&db_pool, &session.user_uuid).await?;
+            if !user.is_admin {
+                return Err(ErrorForbidden("User is not an administrator."));
+            }
+
+            Ok(Admin { user })
+        })
+    }
+}
@@ -0,0 +1,143 @@
+use rand::{thread_rng, Rng};
+use rand::distributions::Alphanumeric;
+use actix_web::error::{Error, ErrorInternalServerError, ErrorUnauthorized};
+use chrono::{Duration, NaiveDateTime, Utc};
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+use super::hash_token;
+
+// Time given to enter a code after the password was accepted.
+const CHALLENGE_MINUTES: i64 = 5;
+
+// Wrong codes allowed for a challenge, before the password has to be entered again.
+const MAX_ATTEMPTS: i32 = 5;
+
+/*
+A login which has passed the password check, and waits for a second factor.
+The session is only created once the challenge is completed.
+*/
+pub struct Challenge {
+    pub uuid: String,
+    pub user_uuid: String,
+    pub remember: bool,
+    pub expiry: String,
+}
+
+async fn create_challenge_sqlx(
+    db_pool: &PgPool,
+    user_uuid: &str,
+    remember: bool) -> Result<(Challenge, String), sqlx::Error>
+{
+    let uuid = Uuid::new_v4().to_string();
+    let token: String = thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(64)
+        .map(char::from)
+        .collect();
+
+    let now = Utc::now().naive_utc();
+    let expiry = now + Duration::minutes(CHALLENGE_MINUTES);
+
+    // Challenges left behind by the user are cleaned up here.
+    sqlx::query("DELETE FROM login_challenges WHERE user_uuid = $1 AND expiry < $2")
+        .bind(user_uuid)
+        .bind(now)
+        .execute(db_pool)
+        .await?;
+
+    sqlx::query(
+        "INSERT INTO login_challenges (uuid, user_uuid, token_hash, remember, expiry) \
+         VALUES ($1, $2, $3, $4, $5)")
+        .bind(&uuid)
+        .bind(user_uuid)
+        .bind(hash_token(&token))
+        .bind(remember)
+        .bind(expiry)
+        .execute(db_pool)
+        .await?;
+
+    let challenge = Challenge {
+        uuid,
+        user_uuid: user_uuid.to_string(),
+        remember,
+        expiry: expiry.and_utc().to_rfc3339(),
+    };
+
+    Ok((challenge, token))
+}
+
+// Counts an attempt up front, so that concurrent attempts cannot exceed the limit.
+async fn attempt_challenge_sqlx(
+    db_pool: &PgPool,
+    token: &str) -> Result<Challenge, sqlx::Error>
+{
+    let row = sqlx::query(
+        "UPDATE login_challenges SET attempts = attempts + 1 \
+         WHERE token_hash = $1 AND expiry > $2 AND attempts < $3 \
+         RETURNING uuid, user_uuid, remember, expiry")
+        .bind(hash_token(token))
+        .bind(Utc::now().naive_utc())
+        .bind(MAX_ATTEMPTS)
+        .fetch_one(db_pool)
+        .await?;
+
+    let expiry: NaiveDateTime = row.try_get("expiry")?;
+
+    Ok(Challenge {
+        uuid: row.try_get("uuid")?,
+        user_uuid: row.try_get("user_uuid")?,
+        remember: row.try_get("remember")?,
+        expiry: expiry.and_utc().to_rfc3339(),
+    })
+}
+
+async fn complete_challenge_sqlx(db_pool: &PgPool, challenge_uuid: &str) -> Result<u64, sqlx::Error> {
+    let query_result = sqlx::query("DELETE FROM login_challenges WHERE uuid = $1")
+        .bind(challenge_uuid)
+        .execute(db_pool)
+        .await?;
+
+    Ok(query_result.rows_affected())
+}
+
+impl Challenge {
+    // Returns the challenge along with its token, which the client sends back with the code.
+    pub async fn create(db_pool: &PgPool, user_uuid: &str, remember: bool) -> Result<(Self, String), Error> {
+        match create_challenge_sqlx(db_pool, user_uuid, remember).await {
+            Ok(value) => Ok(value),
+            Err(error) => {
+                println!("{error}");
+                Err(ErrorInternalServerError("Database error."))
+            }
+        }
+    }
+
+    pub async fn attempt(db_pool: &PgPool, token: &str) -> Result<Self, Error> {
+        match attempt_challenge_sqlx(db_pool, token).await {
+            Ok(value) => Ok(value),
+            Err(error) => {
+                match error {
+                    sqlx::Error::RowNotFound => {
+                        Err(ErrorUnauthorized("Login challenge has expired or does not exist."))
+                    },
+                    _ => {
+                        println!("{error}");
+                        Err(ErrorInternalServerError("Database error."))
+                    }
+                }
+            }
+        }
+    }
+
+    // A challenge can only be completed once, even by concurrent requests.
+    pub async fn complete(self, db_pool: &PgPool) -> Result<(), Error> {
+        match complete_challenge_sqlx(db_pool, &self.uuid).await {
+            Ok(1) => Ok(()),
+            Ok(_) => Err(ErrorUnauthorized("Login challenge has expired or does not exist.")),
+            Err(error) => {
+                println!("{error}");
+                Err(ErrorInternalServerError("Database error."))
+            }
+        }
+    }
+}
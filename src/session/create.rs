@@ -2,13 +2,16 @@ use rand::{thread_rng, Rng};
 use rand::distributions::Alphanumeric;
 use actix_web::error::{Error, ErrorInternalServerError};
 use sqlx::PgPool;
-use chrono::{Duration, Utc};
+use chrono::Utc;
 use uuid::Uuid;
-use super::Session;
+use super::{Client, Lifetimes, Session, hash_token};
 
 async fn create_auth_token(
     db_pool: &PgPool,
-    user_uuid: &str) -> Result<(String, String), sqlx::Error> 
+    user_uuid: &str,
+    client: &Client,
+    remember: bool,
+    lifetimes: &Lifetimes) -> Result<(String, String), sqlx::Error> 
 {
     let uuid = Uuid::new_v4().to_string();
     let token: String = thread_rng()
@@ -17,11 +20,23 @@ async fn create_auth_token(
         .map(char::from)
         .collect();
 
-    sqlx::query("INSERT INTO sessions VALUES ($1, $2, $3, $4);")
+    let now = Utc::now().naive_utc();
+    let absolute_expiry = lifetimes.absolute_expiry(now, remember);
+    let expiry = lifetimes.idle_expiry(now, remember).min(absolute_expiry);
+
+    sqlx::query(
+        "INSERT INTO sessions \
+             (uuid, user_uuid, expiry, absolute_expiry, remember, token_hash, created, last_used, user_agent, ip) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $7, $8, $9);")
         .bind(&uuid)
         .bind(user_uuid)
-        .bind(Utc::now().naive_utc() + Duration::minutes(30))
-        .bind(&token)
+        .bind(expiry)
+        .bind(absolute_expiry)
+        .bind(remember)
+        .bind(hash_token(&token))
+        .bind(now)
+        .bind(&client.user_agent)
+        .bind(&client.ip)
         .execute(db_pool)
         .await?;
 
@@ -30,12 +45,18 @@ async fn create_auth_token(
 
 
 impl Session {
-    pub async fn create(db_pool: &PgPool, user_uuid: &str) -> Result<Self, Error> {
-        match create_auth_token(db_pool, user_uuid).await {
+    pub async fn create(
+        db_pool: &PgPool,
+        user_uuid: &str,
+        client: &Client,
+        remember: bool,
+        lifetimes: &Lifetimes) -> Result<Self, Error>
+    {
+        match create_auth_token(db_pool, user_uuid, client, remember, lifetimes).await {
             Ok((uuid, token)) => Ok(Session {
-                uuid: uuid,
+                uuid,
                 user_uuid: user_uuid.to_string(),
-                token: token
+                token
             }),
             Err(error) => {
                 println!("{error}");
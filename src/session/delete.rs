@@ -23,15 +23,77 @@ async fn delete_session(
     };
 
     if query_result.rows_affected() == 0 {
-        return Err(ErrorNotFound("Entry not found."));
+        return Err(ErrorNotFound("Session not found."));
     }
 
     Ok(())
 }
 
+async fn delete_other_sessions(
+    db_pool: &PgPool,
+    session_uuid: &str,
+    user_uuid: &str) -> Result<u64, Error>
+{
+    let delete_result = 
+        sqlx::query("DELETE FROM sessions WHERE uuid <> $1 AND user_uuid = $2")
+        .bind(session_uuid)
+        .bind(user_uuid)
+        .execute(db_pool)
+        .await;
+
+    match delete_result {
+        Ok(value) => Ok(value.rows_affected()),
+        Err(error) => {
+            println!("{error}");
+            Err(ErrorInternalServerError("Database error."))
+        }
+    }
+}
+
+// Deletes the access tokens of the user as well. Returns number of the sessions deleted.
+async fn delete_all_sessions_sqlx(db_pool: &PgPool, user_uuid: &str) -> Result<u64, sqlx::Error> {
+    let mut transaction = db_pool.begin().await?;
+
+    let query_result = sqlx::query("DELETE FROM sessions WHERE user_uuid = $1")
+        .bind(user_uuid)
+        .execute(&mut transaction)
+        .await?;
+
+    sqlx::query("DELETE FROM access_tokens WHERE user_uuid = $1")
+        .bind(user_uuid)
+        .execute(&mut transaction)
+        .await?;
+
+    transaction.commit().await?;
+    Ok(query_result.rows_affected())
+}
+
+async fn delete_all_sessions(db_pool: &PgPool, user_uuid: &str) -> Result<u64, Error> {
+    match delete_all_sessions_sqlx(db_pool, user_uuid).await {
+        Ok(value) => Ok(value),
+        Err(error) => {
+            println!("{error}");
+            Err(ErrorInternalServerError("Database error."))
+        }
+    }
+}
+
 impl Session {
-    pub async fn delete(self, db_pool: &PgPool) -> Result<Self, Error> {
-        delete_session(db_pool, &self.uuid, &self.user_uuid).await?;
-        Ok(self)
+    // Deletes a session of the same user, which may be this session itself.
+    pub async fn revoke(&self, db_pool: &PgPool, session_uuid: &str) -> Result<(), Error> {
+        delete_session(db_pool, session_uuid, &self.user_uuid).await
+    }
+
+    // Deletes every session of the same user but this one. Returns number of the sessions deleted.
+    pub async fn revoke_others(&self, db_pool: &PgPool) -> Result<u64, Error> {
+        delete_other_sessions(db_pool, &self.uuid, &self.user_uuid).await
+    }
+
+    /*
+    Deletes every session and access token of the user, for administrators.
+    Returns number of the sessions deleted.
+    */
+    pub async fn revoke_all(db_pool: &PgPool, user_uuid: &str) -> Result<u64, Error> {
+        delete_all_sessions(db_pool, user_uuid).await
     }
 }
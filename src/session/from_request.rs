@@ -2,23 +2,36 @@ use actix_web::{
     web, FromRequest, HttpRequest,
     cookie::Cookie,
     dev::{Payload, Path, Url},
-    error::{Error, ErrorUnauthorized, ErrorInternalServerError}
+    error::{Error, ErrorForbidden, ErrorUnauthorized, ErrorInternalServerError},
+    http::header::AUTHORIZATION
 };
 use sqlx::{PgPool, Row};
 use chrono::{NaiveDateTime, Utc};
 use std::pin::Pin;
 use std::future::Future;
-use super::Session;
+use subtle::ConstantTimeEq;
+use crate::access_token::{Scope, authenticate};
+use super::{Client, Lifetimes, Session, hash_token};
 
 async fn get_session_details_sqlx(
     token: &str,
     db_pool: &PgPool) -> Result<(String, String, NaiveDateTime), sqlx::Error>
 {
-    let session_row = sqlx::query("SELECT uuid, user_uuid, expiry FROM sessions WHERE token = $1")
-        .bind(token)
+    let token_hash = hash_token(token);
+
+    let session_row = 
+        sqlx::query("SELECT uuid, user_uuid, expiry, token_hash FROM sessions WHERE token_hash = $1")
+        .bind(&token_hash)
         .fetch_one(db_pool)
         .await?;
 
+    // The index lookup may leak timing, but only about digests, which are useless to an attacker.
+    // The stored digest is still compared in constant time before being trusted.
+    let stored_hash: String = session_row.try_get("token_hash")?;
+    if !bool::from(stored_hash.as_bytes().ct_eq(token_hash.as_bytes())) {
+        return Err(sqlx::Error::RowNotFound);
+    }
+
     let uuid: String = session_row.try_get("uuid")?;
     let user_uuid: String = session_row.try_get("user_uuid")?;
     let expiry: NaiveDateTime = session_row.try_get("expiry")?;
@@ -30,7 +43,7 @@ async fn get_session_details(
     token: &str,
     db_pool: &PgPool) -> Result<(String, String, NaiveDateTime), Error>
 {
-    match get_session_details_sqlx(&token, &db_pool).await {
+    match get_session_details_sqlx(token, db_pool).await {
         Ok(value) => Ok(value),
         Err(error) => {
             match error {
@@ -46,6 +59,42 @@ async fn get_session_details(
     }
 }
 
+/*
+Records the use of a session and slides its expiry forward, up to the absolute expiry.
+To avoid a write on every request, the row is only updated when a minute has passed
+since the last record or the client has changed.
+*/
+async fn touch_session(
+    db_pool: &PgPool,
+    session_uuid: &str,
+    client: &Client,
+    lifetimes: &Lifetimes) -> Result<(), Error>
+{
+    let now = Utc::now().naive_utc();
+
+    let update_result = sqlx::query(
+        "UPDATE sessions SET last_used = $1, user_agent = $2, ip = $3, \
+             expiry = LEAST(CASE WHEN remember THEN $4 ELSE $5 END, absolute_expiry) \
+         WHERE uuid = $6 AND (last_used < $1 - INTERVAL '1 minute' \
+             OR user_agent IS DISTINCT FROM $2 OR ip IS DISTINCT FROM $3)")
+        .bind(now)
+        .bind(&client.user_agent)
+        .bind(&client.ip)
+        .bind(lifetimes.idle_expiry(now, true))
+        .bind(lifetimes.idle_expiry(now, false))
+        .bind(session_uuid)
+        .execute(db_pool)
+        .await;
+
+    match update_result {
+        Ok(_) => Ok(()),
+        Err(error) => {
+            println!("{error}");
+            Err(ErrorInternalServerError("Database error."))
+        }
+    }
+}
+
 fn get_request_user_uuid(path: Path<Url>) -> Result<String, Error> {
     match path.get("user_uuid") {
         Some(value) => Ok(value.to_string()),
@@ -60,35 +109,113 @@ fn get_auth_cookie_value(cookie_opt: Option<Cookie>) -> Result<String, Error> {
     }
 }
 
+// Returns the token of an 'Authorization: Bearer' header, if any.
+fn get_bearer_token(req: &HttpRequest) -> Result<Option<String>, Error> {
+    let header = match req.headers().get(AUTHORIZATION) {
+        Some(value) => value,
+        None => return Ok(None)
+    };
+
+    let value = header.to_str().unwrap_or("");
+    match value.split_once(' ') {
+        Some((scheme, token)) if scheme.eq_ignore_ascii_case("bearer") && !token.trim().is_empty() => {
+            Ok(Some(token.trim().to_string()))
+        },
+        _ => Err(ErrorUnauthorized("Authorization header should be in the form of 'Bearer {token}'."))
+    }
+}
+
+/*
+Authenticates a request made with a personal access token. The session takes the UUID of
+the token, and may only be used where the scopes of the token allow.
+*/
+async fn token_session(
+    db_pool: &PgPool,
+    token: String,
+    request_user_uuid: Option<&str>,
+    required_scope: Scope) -> Result<Session, Error>
+{
+    let access_token = authenticate::authenticate(db_pool, &token).await?;
+
+    if request_user_uuid.is_some_and(|user_uuid| user_uuid != access_token.user_uuid) {
+        return Err(ErrorUnauthorized("Access token is not authenticated for this user."));
+    }
+
+    if !access_token.scopes.iter().any(|scope| scope.covers(required_scope)) {
+        let message = format!("Access token lacks the '{}' scope.", required_scope.as_str());
+        return Err(ErrorForbidden(message));
+    }
+
+    Ok(Session {
+        uuid: access_token.uuid,
+        user_uuid: access_token.user_uuid,
+        token,
+    })
+}
+
+// What a request is authenticated with, taken out of it before going async.
+pub struct Credentials {
+    pub db_pool: web::Data<PgPool>,
+    lifetimes: web::Data<Lifetimes>,
+    auth_cookie: Option<Cookie<'static>>,
+    client: Client,
+    bearer_token: Result<Option<String>, Error>,
+    required_scope: Scope,
+}
+
+impl Credentials {
+    pub fn from_request(req: &HttpRequest) -> Self {
+        Credentials {
+            db_pool: req.app_data::<web::Data<PgPool>>().unwrap().clone(),
+            lifetimes: req.app_data::<web::Data<Lifetimes>>().unwrap().clone(),
+            auth_cookie: req.cookie("auth"),
+            client: Client::from_request(req),
+            bearer_token: get_bearer_token(req),
+            required_scope: Scope::required_for(req.method(), req.path()),
+        }
+    }
+
+    // Returns the session, which has to be of 'request_user_uuid' if given.
+    pub async fn authenticate(self, request_user_uuid: Option<&str>) -> Result<Session, Error> {
+        // Access tokens take precedence over the cookie.
+        if let Some(token) = self.bearer_token? {
+            return token_session(&self.db_pool, token, request_user_uuid, self.required_scope).await;
+        }
+
+        let token = get_auth_cookie_value(self.auth_cookie)?;
+
+        let (session_uuid, auth_user_uuid, auth_expiry) 
+            = get_session_details(&token, &self.db_pool).await?;
+
+        if request_user_uuid.is_some_and(|user_uuid| user_uuid != auth_user_uuid) {
+            return Err(ErrorUnauthorized("Session is not authenticated for this user."));
+        }
+
+        if auth_expiry.and_utc().timestamp() < Utc::now().timestamp() {
+            return Err(ErrorUnauthorized("Session has expired."));
+        }
+
+        touch_session(&self.db_pool, &session_uuid, &self.client, &self.lifetimes).await?;
+
+        Ok(Session {
+            uuid: session_uuid,
+            user_uuid: auth_user_uuid,
+            token,
+        })
+    }
+}
+
 impl FromRequest for Session {
     type Error = Error;
     type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
 
     fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
-        let db_pool = req.app_data::<web::Data<PgPool>>().unwrap().clone();
-        let auth_cookie = req.cookie("auth");
+        let credentials = Credentials::from_request(req);
         let path = req.match_info().clone();
 
         Box::pin(async move {
-            let token = get_auth_cookie_value(auth_cookie)?;
             let request_user_uuid = get_request_user_uuid(path)?;
-
-            let (session_uuid, auth_user_uuid, auth_expiry) 
-                = get_session_details(&token, &db_pool).await?;
-
-            if request_user_uuid != auth_user_uuid {
-                return Err(ErrorUnauthorized("Session is not authenticated for this user."));
-            }
-
-            if auth_expiry.timestamp() < Utc::now().naive_utc().timestamp() {
-                return Err(ErrorUnauthorized("Session has expired."));
-            }
-
-            Ok(Session {
-                uuid: session_uuid,
-                user_uuid: auth_user_uuid,
-                token: token,
-            })
+            credentials.authenticate(Some(&request_user_uuid)).await
         })
     }
 }
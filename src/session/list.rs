@@ -0,0 +1,58 @@
+use actix_web::error::{Error, ErrorInternalServerError};
+use chrono::NaiveDateTime;
+use sqlx::{PgPool, Row, postgres::PgRow};
+use super::Session;
+
+pub struct SessionDetail {
+    pub uuid: String,
+    pub created: String,
+    pub last_used: String,
+    pub expiry: String,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
+
+fn detail_from_row(row: &PgRow) -> Result<SessionDetail, sqlx::Error> {
+    let created: NaiveDateTime = row.try_get("created")?;
+    let last_used: NaiveDateTime = row.try_get("last_used")?;
+    let expiry: NaiveDateTime = row.try_get("expiry")?;
+
+    Ok(SessionDetail {
+        uuid: row.try_get("uuid")?,
+        created: created.and_utc().to_rfc3339(),
+        last_used: last_used.and_utc().to_rfc3339(),
+        expiry: expiry.and_utc().to_rfc3339(),
+        user_agent: row.try_get("user_agent")?,
+        ip: row.try_get("ip")?,
+    })
+}
+
+async fn by_user_sqlx(
+    db_pool: &PgPool,
+    user_uuid: &str) -> Result<Vec<SessionDetail>, sqlx::Error>
+{
+    let rows = sqlx::query(
+        "SELECT uuid, created, last_used, expiry, user_agent, ip FROM sessions \
+         WHERE user_uuid = $1 ORDER BY last_used DESC")
+        .bind(user_uuid)
+        .fetch_all(db_pool)
+        .await?;
+
+    rows.iter().map(detail_from_row).collect()
+}
+
+impl Session {
+    // Returns every session of the user, including expired ones, recently used first.
+    pub async fn list_by_user(
+        db_pool: &PgPool,
+        user_uuid: &str) -> Result<Vec<SessionDetail>, Error>
+    {
+        match by_user_sqlx(db_pool, user_uuid).await {
+            Ok(value) => Ok(value),
+            Err(error) => {
+                println!("{error}");
+                Err(ErrorInternalServerError("Database error."))
+            }
+        }
+    }
+}
@@ -1,10 +1,236 @@
 pub mod from_request;
+pub mod admin;
+pub mod challenge;
 pub mod create;
 pub mod delete;
+pub mod list;
+pub mod purge;
 pub mod refresh;
+pub mod throttle;
+
+use std::net::IpAddr;
+use actix_web::{HttpRequest, web, http::header::USER_AGENT};
+use chrono::{Duration, NaiveDateTime};
+use sha2::{Digest, Sha256};
 
 pub struct Session {
     pub uuid: String,
     pub token: String,
     pub user_uuid: String,
 }
+
+/*
+How long sessions last, set by the operator. A session expires once it has not been used
+for the idle lifetime, and in any case once the absolute lifetime has passed since login.
+Sessions created with "remember me" use the longer pair.
+*/
+#[derive(Clone, Copy)]
+pub struct Lifetimes {
+    pub idle: Duration,
+    pub absolute: Duration,
+    pub remember_idle: Duration,
+    pub remember_absolute: Duration,
+}
+
+impl Lifetimes {
+    // Expiry of a session used at 'now', before being capped by its absolute expiry.
+    pub fn idle_expiry(&self, now: NaiveDateTime, remember: bool) -> NaiveDateTime {
+        match remember {
+            true => now + self.remember_idle,
+            false => now + self.idle,
+        }
+    }
+
+    pub fn absolute_expiry(&self, now: NaiveDateTime, remember: bool) -> NaiveDateTime {
+        match remember {
+            true => now + self.remember_absolute,
+            false => now + self.absolute,
+        }
+    }
+}
+
+/*
+Only digests of tokens are stored, so that a leaked database does not leak working tokens.
+Tokens are random enough that a plain SHA-256 suffices, without salt nor stretching.
+*/
+pub fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes()).iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+// Longer user agents are cut off.
+const MAX_USER_AGENT_LENGTH: usize = 512;
+
+/*
+Reverse proxies set by the operator, as IP addresses or CIDR ranges. Requests from them are
+taken to come from the address they put in the X-Forwarded-For header.
+*/
+#[derive(Clone, Default)]
+pub struct TrustedProxies {
+    ranges: Vec<(IpAddr, u8)>,
+}
+
+impl TrustedProxies {
+    // Parses a comma separated list, such as "127.0.0.1, 10.0.0.0/8, ::1".
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let mut ranges = Vec::new();
+
+        for range in value.split(',').map(str::trim).filter(|range| !range.is_empty()) {
+            let (address, prefix) = match range.split_once('/') {
+                Some((address, prefix)) => (address, Some(prefix)),
+                None => (range, None)
+            };
+
+            let address: IpAddr = match address.parse() {
+                Ok(value) => value,
+                Err(_) => return Err(format!("{range} is not an IP address or CIDR range"))
+            };
+
+            let max_prefix = if address.is_ipv4() { 32 } else { 128 };
+            let prefix = match prefix.map(str::parse::<u8>) {
+                None => max_prefix,
+                Some(Ok(prefix)) if prefix <= max_prefix => prefix,
+                Some(_) => return Err(format!("{range} has an invalid prefix length"))
+            };
+
+            ranges.push((address, prefix));
+        }
+
+        Ok(TrustedProxies { ranges })
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        let ip = ip.to_canonical();
+
+        self.ranges.iter().any(|(address, prefix)| match (address, ip) {
+            (IpAddr::V4(address), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - *prefix as u32).unwrap_or(0);
+                u32::from(*address) & mask == u32::from(ip) & mask
+            },
+            (IpAddr::V6(address), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - *prefix as u32).unwrap_or(0);
+                u128::from(*address) & mask == u128::from(ip) & mask
+            },
+            _ => false
+        })
+    }
+
+    /*
+    Address of the client. The X-Forwarded-For header is walked from the right for as long as
+    the addresses belong to trusted proxies, since those to the left of the first untrusted
+    one are whatever the client made up.
+    */
+    fn client_ip(&self, req: &HttpRequest) -> Option<IpAddr> {
+        let mut client = req.peer_addr()?.ip().to_canonical();
+
+        let forwarded: Vec<&str> = req.headers().get_all("X-Forwarded-For")
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .collect();
+
+        for hop in forwarded.iter().rev() {
+            if !self.contains(&client) {
+                break;
+            }
+
+            client = match hop.trim().parse::<IpAddr>() {
+                Ok(value) => value.to_canonical(),
+                Err(_) => break
+            };
+        }
+
+        Some(client)
+    }
+}
+
+/*
+Details of the client using a session, so that users can tell their sessions apart.
+The IP address is that of the connection, unless it comes through a trusted proxy.
+Failed logins are throttled by it as well.
+*/
+pub struct Client {
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
+
+impl Client {
+    pub fn from_request(req: &HttpRequest) -> Self {
+        let user_agent = req.headers().get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.chars().take(MAX_USER_AGENT_LENGTH).collect());
+
+        let ip = match req.app_data::<web::Data<TrustedProxies>>() {
+            Some(proxies) => proxies.client_ip(req),
+            None => TrustedProxies::default().client_ip(req)
+        };
+
+        Client {
+            user_agent,
+            ip: ip.map(|value| value.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test::TestRequest, web};
+    use super::{Client, TrustedProxies};
+
+    fn client_ip(proxies: Option<&str>, peer: &str, forwarded: &[&str]) -> Option<String> {
+        let mut request = TestRequest::default().peer_addr(peer.parse().unwrap());
+        for value in forwarded {
+            request = request.append_header(("X-Forwarded-For", *value));
+        }
+
+        if let Some(proxies) = proxies {
+            request = request.app_data(web::Data::new(TrustedProxies::parse(proxies).unwrap()));
+        }
+
+        Client::from_request(&request.to_http_request()).ip
+    }
+
+    #[test]
+    fn forwarded_header_is_ignored_without_trusted_proxies() {
+        assert_eq!(client_ip(None, "203.0.113.7:4000", &["198.51.100.1"]).unwrap(), "203.0.113.7");
+        assert_eq!(client_ip(Some("10.0.0.1"), "203.0.113.7:4000", &["198.51.100.1"]).unwrap(), "203.0.113.7");
+    }
+
+    #[test]
+    fn forwarded_header_of_trusted_proxies() {
+        let proxies = Some("10.0.0.0/8, ::1");
+
+        assert_eq!(client_ip(proxies, "10.1.2.3:4000", &["198.51.100.1"]).unwrap(), "198.51.100.1");
+        assert_eq!(client_ip(proxies, "[::1]:4000", &["198.51.100.1"]).unwrap(), "198.51.100.1");
+
+        // Addresses the client made up are to the left of the first untrusted one.
+        assert_eq!(client_ip(proxies, "10.1.2.3:4000", &["192.0.2.9, 198.51.100.1, 10.0.0.2"]).unwrap(), "198.51.100.1");
+        assert_eq!(client_ip(proxies, "10.1.2.3:4000", &["192.0.2.9", "198.51.100.1"]).unwrap(), "198.51.100.1");
+
+        // Without a usable header, the proxy is the client.
+        assert_eq!(client_ip(proxies, "10.1.2.3:4000", &[]).unwrap(), "10.1.2.3");
+        assert_eq!(client_ip(proxies, "10.1.2.3:4000", &["unknown"]).unwrap(), "10.1.2.3");
+        assert_eq!(client_ip(proxies, "10.1.2.3:4000", &["10.0.0.2"]).unwrap(), "10.0.0.2");
+    }
+
+    #[test]
+    fn ranges() {
+        let proxies = TrustedProxies::parse("192.168.0.0/16, 2001:db8::/32, 127.0.0.1").unwrap();
+
+        for ip in ["192.168.0.1", "192.168.255.255", "2001:db8::1", "127.0.0.1", "::ffff:127.0.0.1"] {
+            assert!(proxies.contains(&ip.parse().unwrap()), "{ip} should be trusted");
+        }
+
+        for ip in ["192.169.0.1", "127.0.0.2", "2001:db9::1", "::1"] {
+            assert!(!proxies.contains(&ip.parse().unwrap()), "{ip} should not be trusted");
+        }
+
+        assert!(TrustedProxies::parse("0.0.0.0/0").unwrap().contains(&"8.8.8.8".parse().unwrap()));
+        assert!(TrustedProxies::parse("").unwrap().ranges.is_empty());
+    }
+
+    #[test]
+    fn invalid_ranges() {
+        for value in ["localhost", "10.0.0.0/33", "::1/129", "10.0.0.0/x"] {
+            assert!(TrustedProxies::parse(value).is_err(), "{value:?} should be rejected");
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use chrono::Utc;
+use sqlx::PgPool;
+
+// Number of sessions deleted by a statement, so that a large backlog does not hold locks for long.
+const BATCH_SIZE: i64 = 1000;
+
+async fn purge_batch_sqlx(db_pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let query_result = sqlx::query(
+        "DELETE FROM sessions WHERE uuid IN \
+         (SELECT uuid FROM sessions WHERE expiry < $1 LIMIT $2)")
+        .bind(Utc::now().naive_utc())
+        .bind(BATCH_SIZE)
+        .execute(db_pool)
+        .await?;
+
+    Ok(query_result.rows_affected())
+}
+
+// Deletes every expired session in batches. Returns number of the sessions deleted.
+pub async fn purge_expired(db_pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let mut total = 0;
+    loop {
+        let count = purge_batch_sqlx(db_pool).await?;
+        total += count;
+
+        if count < BATCH_SIZE as u64 {
+            return Ok(total);
+        }
+    }
+}
@@ -1,16 +1,23 @@
 use actix_web::error::{Error, ErrorInternalServerError};
-use chrono::{Duration, Utc};
+use chrono::Utc;
 use sqlx::PgPool;
-use super::Session;
+use super::{Lifetimes, Session};
 
+// Expiry never goes past the absolute expiry set at login.
 async fn refresh_auth_token(
     db_pool: &PgPool,
     session_uuid: &str,
-    user_uuid: &str) -> Result<(), Error>
+    user_uuid: &str,
+    lifetimes: &Lifetimes) -> Result<(), Error>
 {
-    let update_result = 
-        sqlx::query("UPDATE sessions SET expiry = $1 WHERE uuid = $2 AND user_uuid = $3")
-        .bind(Utc::now().naive_utc() + Duration::minutes(30))
+    let now = Utc::now().naive_utc();
+
+    let update_result = sqlx::query(
+        "UPDATE sessions \
+         SET expiry = LEAST(CASE WHEN remember THEN $1 ELSE $2 END, absolute_expiry) \
+         WHERE uuid = $3 AND user_uuid = $4")
+        .bind(lifetimes.idle_expiry(now, true))
+        .bind(lifetimes.idle_expiry(now, false))
         .bind(session_uuid)
         .bind(user_uuid)
         .execute(db_pool)
@@ -26,8 +33,8 @@ async fn refresh_auth_token(
 }
 
 impl Session {
-    pub async fn refresh(self, db_pool: &PgPool) -> Result<Self, Error> {
-        refresh_auth_token(db_pool, &self.uuid, &self.user_uuid).await?;
+    pub async fn refresh(self, db_pool: &PgPool, lifetimes: &Lifetimes) -> Result<Self, Error> {
+        refresh_auth_token(db_pool, &self.uuid, &self.user_uuid, lifetimes).await?;
         Ok(self)
     }
 }
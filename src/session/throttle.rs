@@ -0,0 +1,447 @@
+use actix_web::{HttpResponse, error::{Error, ErrorInternalServerError, InternalError}};
+use chrono::{Duration, NaiveDateTime, Utc};
+use sqlx::{PgPool, Row};
+use crate::audit::{self, Event};
+use crate::user::username::username_key;
+
+// The longest wait between attempts before a lockout, so that backoff does not outgrow it.
+const MAX_BACKOFF_SECONDS: i64 = 60 * 5;
+
+// Number of failure counts deleted by a statement, so that a large backlog does not hold locks for long.
+const PURGE_BATCH_SIZE: i64 = 1000;
+
+/*
+How failed logins are limited, set by the operator. Failures are counted per username and
+per IP address, and a count restarts once 'lockout' has passed since its last failure.
+After a third of the threshold, each further attempt has to wait twice as long as the one
+before, starting from a second. At the threshold, logins are locked out for 'lockout'.
+*/
+#[derive(Clone, Copy)]
+pub struct Throttle {
+    pub username_threshold: i32,
+    pub ip_threshold: i32,
+    pub lockout: Duration,
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Username,
+    Ip,
+}
+
+impl Kind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Kind::Username => "username",
+            Kind::Ip => "ip",
+        }
+    }
+}
+
+impl Throttle {
+    fn threshold(&self, kind: Kind) -> i32 {
+        match kind {
+            Kind::Username => self.username_threshold,
+            Kind::Ip => self.ip_threshold,
+        }
+    }
+
+    fn free_attempts(&self, kind: Kind) -> i32 {
+        (self.threshold(kind) / 3).max(1)
+    }
+
+    // Time to wait after the last failure, or None while attempts are free.
+    fn backoff(&self, kind: Kind, failures: i32) -> Option<Duration> {
+        let free_attempts = self.free_attempts(kind);
+        if failures < free_attempts {
+            return None;
+        }
+
+        let exponent = (failures - free_attempts).min(16) as u32;
+        Some(Duration::seconds((1i64 << exponent).min(MAX_BACKOFF_SECONDS)))
+    }
+
+    // Time left until the subject may be attempted again, judged from its count.
+    fn wait(
+        &self,
+        kind: Kind,
+        (failures, last_failure, locked_until): (i32, NaiveDateTime, Option<NaiveDateTime>),
+        now: NaiveDateTime) -> Option<Duration>
+    {
+        if let Some(locked_until) = locked_until {
+            if locked_until > now {
+                return Some(locked_until - now);
+            }
+        }
+
+        if last_failure < now - self.lockout {
+            return None;
+        }
+
+        match self.backoff(kind, failures) {
+            Some(backoff) if last_failure + backoff > now => Some(last_failure + backoff - now),
+            _ => None
+        }
+    }
+}
+
+// Usernames are counted as they are looked up, so that changing case or form does not reset the count.
+fn subjects(username: &str, ip: Option<&str>) -> Vec<(Kind, String)> {
+    let mut subjects = vec![(Kind::Username, username_key(username))];
+    if let Some(ip) = ip {
+        subjects.push((Kind::Ip, ip.to_string()));
+    }
+
+    subjects
+}
+
+fn too_many_requests(retry_after: Duration) -> Error {
+    let message = "Too many failed login attempts. Try again later.";
+    let response = HttpResponse::TooManyRequests()
+        .insert_header(("Retry-After", retry_after.num_seconds().max(1).to_string()))
+        .body(message);
+
+    InternalError::from_response(message, response).into()
+}
+
+async fn failures_sqlx(
+    db_pool: &PgPool,
+    kind: Kind,
+    subject: &str) -> Result<Option<(i32, NaiveDateTime, Option<NaiveDateTime>)>, sqlx::Error>
+{
+    let row = sqlx::query(
+        "SELECT failures, last_failure, locked_until FROM login_failures WHERE kind = $1 AND subject = $2")
+        .bind(kind.as_str())
+        .bind(subject)
+        .fetch_optional(db_pool)
+        .await?;
+
+    match row {
+        Some(row) => Ok(Some((row.try_get("failures")?, row.try_get("last_failure")?, row.try_get("locked_until")?))),
+        None => Ok(None)
+    }
+}
+
+/*
+Reserves an attempt by counting it as a failure up front, in a single statement, so that
+concurrent attempts cannot all get past the count before any of them fails. The count
+restarts if the last failure is older than the lockout duration. Attempts are refused while
+the subject is locked out or backing off, or when attempts still being verified already
+reach the threshold. Returns whether the attempt was reserved.
+*/
+async fn reserve_sqlx(
+    db_pool: &PgPool,
+    throttle: &Throttle,
+    kind: Kind,
+    subject: &str) -> Result<bool, sqlx::Error>
+{
+    let now = Utc::now().naive_utc();
+
+    let row = sqlx::query(
+        "INSERT INTO login_failures (kind, subject, failures, last_failure) VALUES ($1, $2, 1, $3) \
+         ON CONFLICT (kind, subject) DO UPDATE \
+         SET failures = CASE WHEN login_failures.last_failure < $4 THEN 1 ELSE login_failures.failures + 1 END, \
+             last_failure = EXCLUDED.last_failure \
+         WHERE (login_failures.locked_until IS NULL OR login_failures.locked_until <= $3) \
+           AND (login_failures.last_failure < $4 \
+                OR (login_failures.failures < $5 \
+                    AND (login_failures.failures < $6 \
+                         OR login_failures.last_failure \
+                            + make_interval(secs => LEAST(power(2, LEAST(login_failures.failures - $6, 16)), $7)) \
+                            <= $3))) \
+         RETURNING failures")
+        .bind(kind.as_str())
+        .bind(subject)
+        .bind(now)
+        .bind(now - throttle.lockout)
+        .bind(throttle.threshold(kind))
+        .bind(throttle.free_attempts(kind))
+        .bind(MAX_BACKOFF_SECONDS as f64)
+        .fetch_optional(db_pool)
+        .await?;
+
+    Ok(row.is_some())
+}
+
+// Gives back a reserved attempt which turned out not to be a failure.
+async fn refund_sqlx(db_pool: &PgPool, kind: Kind, subject: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE login_failures SET failures = GREATEST(failures - 1, 0) WHERE kind = $1 AND subject = $2")
+        .bind(kind.as_str())
+        .bind(subject)
+        .execute(db_pool)
+        .await?;
+
+    Ok(())
+}
+
+/*
+Locks the subject out once a reserved attempt has failed and the count reaches the
+threshold, which restarts the count. Returns whether this failure caused a lockout.
+*/
+async fn lock_out_sqlx(
+    db_pool: &PgPool,
+    throttle: &Throttle,
+    kind: Kind,
+    subject: &str) -> Result<bool, sqlx::Error>
+{
+    let query_result = sqlx::query(
+        "UPDATE login_failures SET failures = 0, locked_until = $1 \
+         WHERE kind = $2 AND subject = $3 AND failures >= $4")
+        .bind(Utc::now().naive_utc() + throttle.lockout)
+        .bind(kind.as_str())
+        .bind(subject)
+        .bind(throttle.threshold(kind))
+        .execute(db_pool)
+        .await?;
+
+    Ok(query_result.rows_affected() > 0)
+}
+
+async fn clear_sqlx(db_pool: &PgPool, kind: Kind, subject: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM login_failures WHERE kind = $1 AND subject = $2")
+        .bind(kind.as_str())
+        .bind(subject)
+        .execute(db_pool)
+        .await?;
+
+    Ok(())
+}
+
+// Deletes counts that no longer have an effect: the lockout is over and the count would restart.
+async fn purge_batch_sqlx(db_pool: &PgPool, throttle: &Throttle) -> Result<u64, sqlx::Error> {
+    let now = Utc::now().naive_utc();
+
+    let query_result = sqlx::query(
+        "DELETE FROM login_failures WHERE (kind, subject) IN \
+         (SELECT kind, subject FROM login_failures \
+          WHERE last_failure < $1 AND (locked_until IS NULL OR locked_until < $2) LIMIT $3)")
+        .bind(now - throttle.lockout)
+        .bind(now)
+        .bind(PURGE_BATCH_SIZE)
+        .execute(db_pool)
+        .await?;
+
+    Ok(query_result.rows_affected())
+}
+
+impl Throttle {
+    /*
+    Refuses with 429 Too Many Requests while the username or IP address is locked out or
+    backing off, without counting anything. For requests which do not verify credentials.
+    Unknown usernames are treated the same as existing ones.
+    */
+    pub async fn check(&self, db_pool: &PgPool, username: &str, ip: Option<&str>) -> Result<(), Error> {
+        let now = Utc::now().naive_utc();
+
+        for (kind, subject) in subjects(username, ip) {
+            let failures = match failures_sqlx(db_pool, kind, &subject).await {
+                Ok(Some(value)) => value,
+                Ok(None) => continue,
+                Err(error) => {
+                    println!("{error}");
+                    return Err(ErrorInternalServerError("Database error."));
+                }
+            };
+
+            if let Some(wait) = self.wait(kind, failures, now) {
+                return Err(too_many_requests(wait));
+            }
+        }
+
+        Ok(())
+    }
+
+    /*
+    Reserves a login attempt against both the username and the IP address before credentials
+    are verified, or refuses it with 429 Too Many Requests as check() does. Every reserved
+    attempt has to end in record_failure(), record_success() or release().
+    */
+    pub async fn attempt(&self, db_pool: &PgPool, username: &str, ip: Option<&str>) -> Result<(), Error> {
+        let subjects = subjects(username, ip);
+
+        for (index, (kind, subject)) in subjects.iter().enumerate() {
+            let reserved = match reserve_sqlx(db_pool, self, *kind, subject).await {
+                Ok(value) => value,
+                Err(error) => {
+                    println!("{error}");
+                    return Err(ErrorInternalServerError("Database error."));
+                }
+            };
+
+            if reserved {
+                continue;
+            }
+
+            for (kind, subject) in &subjects[..index] {
+                if let Err(error) = refund_sqlx(db_pool, *kind, subject).await {
+                    println!("{error}");
+                    return Err(ErrorInternalServerError("Database error."));
+                }
+            }
+
+            // Attempts still being verified may hold the count without a wait to tell.
+            let now = Utc::now().naive_utc();
+            let wait = match failures_sqlx(db_pool, *kind, subject).await {
+                Ok(Some(failures)) => self.wait(*kind, failures, now),
+                Ok(None) => None,
+                Err(error) => {
+                    println!("{error}");
+                    return Err(ErrorInternalServerError("Database error."));
+                }
+            };
+
+            return Err(too_many_requests(wait.unwrap_or_else(|| Duration::seconds(1))));
+        }
+
+        Ok(())
+    }
+
+    // Keeps a reserved attempt as failed, which may lock out the username or the IP address.
+    pub async fn record_failure(&self, db_pool: &PgPool, username: &str, ip: Option<&str>) -> Result<(), Error> {
+        for (kind, subject) in subjects(username, ip) {
+            let locked_out = match lock_out_sqlx(db_pool, self, kind, &subject).await {
+                Ok(value) => value,
+                Err(error) => {
+                    println!("{error}");
+                    return Err(ErrorInternalServerError("Database error."));
+                }
+            };
+
+            if locked_out {
+                let detail = format!(
+                    "Locked out by {} for {} minutes after {} failed attempts.",
+                    kind.as_str(),
+                    self.lockout.num_minutes(),
+                    self.threshold(kind));
+                audit::record(db_pool, Event::LoginLockout, Some(username), ip, &detail).await;
+            }
+        }
+
+        Ok(())
+    }
+
+    /*
+    Clears the failures of the username after a successful login. Those of the IP address
+    are kept, so that logging into an account of one's own does not reset guessing others,
+    but the reserved attempt is given back.
+    */
+    pub async fn record_success(&self, db_pool: &PgPool, username: &str, ip: Option<&str>) -> Result<(), Error> {
+        let mut clear_result = clear_sqlx(db_pool, Kind::Username, &username_key(username)).await;
+        if let (Ok(()), Some(ip)) = (&clear_result, ip) {
+            clear_result = refund_sqlx(db_pool, Kind::Ip, ip).await;
+        }
+
+        match clear_result {
+            Ok(value) => Ok(value),
+            Err(error) => {
+                println!("{error}");
+                Err(ErrorInternalServerError("Database error."))
+            }
+        }
+    }
+
+    // Gives back a reserved attempt which ended neither way, such as when a second factor is due.
+    pub async fn release(&self, db_pool: &PgPool, username: &str, ip: Option<&str>) -> Result<(), Error> {
+        for (kind, subject) in subjects(username, ip) {
+            if let Err(error) = refund_sqlx(db_pool, kind, &subject).await {
+                println!("{error}");
+                return Err(ErrorInternalServerError("Database error."));
+            }
+        }
+
+        Ok(())
+    }
+
+    /*
+    Deletes the failure counts that have run out, in batches, so that attempts with made-up
+    usernames do not grow the table for good. Returns number of the counts deleted.
+    */
+    pub async fn purge_expired(&self, db_pool: &PgPool) -> Result<u64, sqlx::Error> {
+        let mut total = 0;
+        loop {
+            let count = purge_batch_sqlx(db_pool, self).await?;
+            total += count;
+
+            if count < PURGE_BATCH_SIZE as u64 {
+                return Ok(total);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, path::Path};
+    use chrono::Duration;
+    use futures::future::join_all;
+    use sqlx::{PgPool, migrate::Migrator};
+    use uuid::Uuid;
+    use super::Throttle;
+
+    const THROTTLE: Throttle = Throttle {
+        username_threshold: 6,
+        ip_threshold: 50,
+        lockout: Duration::minutes(15),
+    };
+
+    /*
+    The tests below run against the database at CENTINOTE_TEST_DATABASE_URL, which is migrated
+    first. Run them with 'cargo test -- --ignored'. Each uses its own username, and removes it.
+    */
+
+    async fn database() -> PgPool {
+        let url = env::var("CENTINOTE_TEST_DATABASE_URL").expect("CENTINOTE_TEST_DATABASE_URL is not set");
+        let db_pool = PgPool::connect(&url).await.unwrap();
+
+        let migrations = Path::new(env!("CARGO_MANIFEST_DIR")).join("sql/migrations");
+        Migrator::new(migrations.as_path()).await.unwrap().run(&db_pool).await.unwrap();
+        db_pool
+    }
+
+    async fn clear(db_pool: &PgPool, username: &str) {
+        sqlx::query("DELETE FROM login_failures WHERE kind = 'username' AND subject = $1")
+            .bind(username)
+            .execute(db_pool)
+            .await
+            .unwrap();
+    }
+
+    fn status(result: &Result<(), actix_web::Error>) -> u16 {
+        result.as_ref().unwrap_err().as_response_error().status_code().as_u16()
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs CENTINOTE_TEST_DATABASE_URL"]
+    async fn concurrent_attempts_stop_at_free_attempts() {
+        let db_pool = database().await;
+        let username = format!("throttle-test-{}", &Uuid::new_v4().to_string()[..8]);
+
+        let attempts = (0..20).map(|_| THROTTLE.attempt(&db_pool, &username, None));
+        let results = join_all(attempts).await;
+
+        assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 2);
+        assert!(results.iter().filter(|result| result.is_err()).all(|result| status(result) == 429));
+
+        clear(&db_pool, &username).await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs CENTINOTE_TEST_DATABASE_URL"]
+    async fn released_attempts_are_given_back() {
+        let db_pool = database().await;
+        let username = format!("throttle-test-{}", &Uuid::new_v4().to_string()[..8]);
+
+        for _ in 0..5 {
+            THROTTLE.attempt(&db_pool, &username, None).await.unwrap();
+            THROTTLE.release(&db_pool, &username, None).await.unwrap();
+        }
+
+        THROTTLE.attempt(&db_pool, &username, None).await.unwrap();
+        THROTTLE.record_failure(&db_pool, &username, None).await.unwrap();
+        THROTTLE.attempt(&db_pool, &username, None).await.unwrap();
+        THROTTLE.record_failure(&db_pool, &username, None).await.unwrap();
+        assert_eq!(status(&THROTTLE.attempt(&db_pool, &username, None).await), 429);
+
+        clear(&db_pool, &username).await;
+    }
+}
@@ -0,0 +1,57 @@
+use std::time::Duration;
+use actix_web::rt;
+use sqlx::PgPool;
+
+use crate::attachment::storage::Storage;
+use crate::entry::trash;
+use crate::session::{self, throttle::Throttle};
+
+// Permanently deletes trashed entries older than 'retention' once every 'interval'.
+pub fn spawn_trash_purge(
+    db_pool: PgPool,
+    storage: Storage,
+    retention: chrono::Duration,
+    interval: Duration)
+{
+    rt::spawn(async move {
+        let mut interval = rt::time::interval(interval);
+        loop {
+            interval.tick().await;
+            match trash::purge_expired(&db_pool, &storage, retention).await {
+                Ok(0) => {},
+                Ok(count) => println!("Purged {count} entries from the trash."),
+                Err(error) => println!("Failed to purge the trash: {error}")
+            }
+        }
+    });
+}
+
+// Deletes expired sessions once every 'interval'.
+pub fn spawn_session_purge(db_pool: PgPool, interval: Duration) {
+    rt::spawn(async move {
+        let mut interval = rt::time::interval(interval);
+        loop {
+            interval.tick().await;
+            match session::purge::purge_expired(&db_pool).await {
+                Ok(0) => {},
+                Ok(count) => println!("Purged {count} expired sessions."),
+                Err(error) => println!("Failed to purge expired sessions: {error}")
+            }
+        }
+    });
+}
+
+// Deletes failure counts of the login throttle that have run out once every 'interval'.
+pub fn spawn_throttle_purge(db_pool: PgPool, throttle: Throttle, interval: Duration) {
+    rt::spawn(async move {
+        let mut interval = rt::time::interval(interval);
+        loop {
+            interval.tick().await;
+            match throttle.purge_expired(&db_pool).await {
+                Ok(0) => {},
+                Ok(count) => println!("Purged {count} expired login failure counts."),
+                Err(error) => println!("Failed to purge login failure counts: {error}")
+            }
+        }
+    });
+}
@@ -1,83 +1,120 @@
-use rand::rngs::OsRng;
-use actix_web::{Error, error::{ErrorInternalServerError, ErrorConflict}};
+use actix_web::{Error, error::{ErrorConflict, ErrorForbidden, ErrorInternalServerError}};
+use chrono::Utc;
 use sqlx::{Row, PgPool};
-use argon2::{Argon2, password_hash::{PasswordHasher, SaltString}};
 use uuid::Uuid;
+use crate::invite::redeem::redeem_sqlx;
 use super::User;
+use super::password::{PasswordPolicy, hash_password};
+use super::registration::Registration;
+use super::username::{username_key, validate_username};
 
-fn hash_password(password: &str) -> Result<String, Error> {
-    let salt = SaltString::generate(&mut OsRng);
-    match Argon2::default().hash_password(password.as_bytes(), &salt) {
-        Ok(hash) => Ok(hash.to_string()),
-        Err(error) => {
-            println!("{}", error);
-            Err(ErrorInternalServerError("Failed to hash password."))
-        }
-    }
+enum Outcome {
+    Created,
+    Taken,
+    Closed,
+    InviteRequired,
+    InviteInvalid,
 }
 
-async fn insert_user(
+/*
+Inserts the user unless 'registration' refuses. The users table is locked meanwhile,
+so that concurrent registrations cannot both become the first or take the same username.
+Fills in 'is_admin' and 'approved' of the user.
+*/
+async fn insert_user_sqlx(
     db_pool: &PgPool,
-    uuid: &str,
-    username: &str,
-    password_hash: &str) -> Result<(), Error> 
+    user: &mut User,
+    registration: Registration,
+    invite_code: Option<&str>) -> Result<Outcome, sqlx::Error>
 {
-    let insert_result = sqlx::query("INSERT INTO users VALUES ($1, $2, $3);")
-        .bind(uuid)
-        .bind(username)
-        .bind(password_hash)
-        .execute(db_pool)
-        .await;
+    let mut transaction = db_pool.begin().await?;
+
+    sqlx::query("LOCK TABLE users IN SHARE ROW EXCLUSIVE MODE")
+        .execute(&mut transaction)
+        .await?;
+
+    let count_row = sqlx::query(
+        "SELECT COUNT(*) AS count, COUNT(*) FILTER (WHERE username_key = $1) AS taken FROM users")
+        .bind(username_key(&user.username))
+        .fetch_one(&mut transaction)
+        .await?;
 
-    match insert_result {
-        Ok(_) => Ok(()),
-        Err(error) => {
-            println!("{}", error);
-            Err(ErrorInternalServerError("Database error."))
+    if count_row.try_get::<i64, _>("taken")? != 0 {
+        return Ok(Outcome::Taken);
+    }
+
+    let first = count_row.try_get::<i64, _>("count")? == 0;
+    if !first {
+        let redeemed = match (registration, invite_code) {
+            (Registration::Closed, _) => return Ok(Outcome::Closed),
+            (Registration::Invite, None) => return Ok(Outcome::InviteRequired),
+            (Registration::Invite, Some(code)) => redeem_sqlx(&mut transaction, code).await?,
+            _ => true
+        };
+
+        if !redeemed {
+            return Ok(Outcome::InviteInvalid);
         }
     }
-}
 
-async fn count_user_by_username_sqlx(
-    db_pool: &PgPool,
-    username: &str) -> Result<i64, sqlx::Error>
-{
-    let user_count_row = sqlx::query("SELECT COUNT(*) AS count FROM users WHERE username = $1")
-        .bind(username)
-        .fetch_one(db_pool)
+    user.is_admin = first;
+    user.approved = first || registration != Registration::Approval;
+
+    sqlx::query(
+        "INSERT INTO users (uuid, username, username_key, password_hash, created, is_admin, approved) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7)")
+        .bind(&user.uuid)
+        .bind(&user.username)
+        .bind(username_key(&user.username))
+        .bind(&user.password_hash)
+        .bind(Utc::now().naive_utc())
+        .bind(user.is_admin)
+        .bind(user.approved)
+        .execute(&mut transaction)
         .await?;
 
-    let user_count: i64 = user_count_row.try_get("count")?;
-    Ok(user_count)
+    transaction.commit().await?;
+    Ok(Outcome::Created)
 }
 
 impl User {
+    /*
+    Registers a user as 'registration' allows. Users registered while approval is required
+    are not approved yet, which 'approved' of the returned user tells. Usernames are unique
+    regardless of case, and stored in their normalized form.
+    */
     pub async fn create(
         db_pool: &PgPool,
         username: &str,
-        password: &str) -> Result<Self, Error> 
+        password: &str,
+        registration: Registration,
+        invite_code: Option<&str>,
+        policy: &PasswordPolicy) -> Result<Self, Error> 
     {
-        let user_count = match count_user_by_username_sqlx(db_pool, username).await {
-            Ok(value) => value,
+        let username = validate_username(username)?;
+        policy.validate(password, &username)?;
+
+        let mut user = User {
+            uuid: Uuid::new_v4().to_string(),
+            username,
+            password_hash: hash_password(password)?,
+            totp_enabled: false,
+            is_admin: false,
+            approved: false,
+            disabled: false,
+            password_reset_required: false,
+        };
+
+        match insert_user_sqlx(db_pool, &mut user, registration, invite_code).await {
+            Ok(Outcome::Created) => Ok(user),
+            Ok(Outcome::Taken) => Err(ErrorConflict("User already exists.")),
+            Ok(Outcome::Closed) => Err(ErrorForbidden("Registration is closed.")),
+            Ok(Outcome::InviteRequired) => Err(ErrorForbidden("Registration requires an invite code.")),
+            Ok(Outcome::InviteInvalid) => Err(ErrorForbidden("Invite code is wrong, expired or used up.")),
             Err(error) => {
                 println!("{error}");
-                return Err(ErrorInternalServerError("Database error."));
+                Err(ErrorInternalServerError("Database error."))
             }
-        };
-
-        if user_count != 0 {
-            return Err(ErrorConflict("User already exists."));
         }
-
-        let user_uuid = Uuid::new_v4().to_string();
-        let password_hash = hash_password(&password)?;
-
-        insert_user(db_pool, &user_uuid, &username, &password_hash).await?;
-
-        Ok(User {
-            uuid: user_uuid,
-            username: username.to_string(),
-            password_hash: password_hash,
-        })
     }
 }
@@ -0,0 +1,127 @@
+use actix_web::{Error, error::ErrorInternalServerError};
+use sqlx::{PgPool, Postgres, Row, Transaction};
+use crate::attachment::storage::Storage;
+use super::User;
+use super::username::username_key;
+
+// What was removed along with a user.
+pub struct Receipt {
+    pub username: String,
+    pub entries: u64,
+    pub revisions: u64,
+    pub tags: u64,
+    pub attachments: u64,
+    pub attachment_bytes: i64,
+    pub sessions: u64,
+}
+
+async fn delete_by_user_sqlx(
+    transaction: &mut Transaction<'_, Postgres>,
+    table: &str,
+    user_uuid: &str) -> Result<u64, sqlx::Error>
+{
+    let sql = format!("DELETE FROM {table} WHERE user_uuid = $1");
+    let query_result = sqlx::query(&sql)
+        .bind(user_uuid)
+        .execute(&mut *transaction)
+        .await?;
+
+    Ok(query_result.rows_affected())
+}
+
+/*
+Deletes the user and everything that belongs to them, including trashed entries.
+Returns the receipt and keys of the attachments to be removed from storage.
+*/
+async fn delete_user_sqlx(
+    db_pool: &PgPool,
+    user: &User) -> Result<(Receipt, Vec<String>), sqlx::Error>
+{
+    let mut transaction = db_pool.begin().await?;
+
+    // Locks the user row, so that nothing is added for the user while deleting.
+    sqlx::query("SELECT uuid FROM users WHERE uuid = $1 FOR UPDATE")
+        .bind(&user.uuid)
+        .execute(&mut transaction)
+        .await?;
+
+    let attachment_rows = sqlx::query("DELETE FROM attachments WHERE user_uuid = $1 RETURNING uuid, size")
+        .bind(&user.uuid)
+        .fetch_all(&mut transaction)
+        .await?;
+
+    let mut attachment_keys = Vec::new();
+    let mut attachment_bytes = 0;
+    for row in attachment_rows.iter() {
+        attachment_keys.push(row.try_get("uuid")?);
+        attachment_bytes += row.try_get::<i64, _>("size")?;
+    }
+
+    // Revisions and tags of entries would go with the entries, but are counted for the receipt.
+    let revisions_result = sqlx::query(
+        "DELETE FROM journal_revisions \
+         WHERE journal_uuid IN (SELECT uuid FROM journals WHERE user_uuid = $1)")
+        .bind(&user.uuid)
+        .execute(&mut transaction)
+        .await?;
+
+    let entries = delete_by_user_sqlx(&mut transaction, "journals", &user.uuid).await?;
+    let tags = delete_by_user_sqlx(&mut transaction, "tags", &user.uuid).await?;
+    delete_by_user_sqlx(&mut transaction, "user_settings", &user.uuid).await?;
+    let sessions = delete_by_user_sqlx(&mut transaction, "sessions", &user.uuid).await?;
+    delete_by_user_sqlx(&mut transaction, "access_tokens", &user.uuid).await?;
+    delete_by_user_sqlx(&mut transaction, "login_challenges", &user.uuid).await?;
+    delete_by_user_sqlx(&mut transaction, "recovery_codes", &user.uuid).await?;
+    delete_by_user_sqlx(&mut transaction, "passkey_ceremonies", &user.uuid).await?;
+    delete_by_user_sqlx(&mut transaction, "passkeys", &user.uuid).await?;
+    delete_by_user_sqlx(&mut transaction, "invites", &user.uuid).await?;
+
+    // Failed logins are counted by username, as they may be of unknown users.
+    // Audit events are kept, since they are the record of what happened.
+    sqlx::query("DELETE FROM login_failures WHERE kind = 'username' AND subject = $1")
+        .bind(username_key(&user.username))
+        .execute(&mut transaction)
+        .await?;
+
+    sqlx::query("DELETE FROM users WHERE uuid = $1")
+        .bind(&user.uuid)
+        .execute(&mut transaction)
+        .await?;
+
+    transaction.commit().await?;
+
+    let receipt = Receipt {
+        username: user.username.clone(),
+        entries,
+        revisions: revisions_result.rows_affected(),
+        tags,
+        attachments: attachment_keys.len() as u64,
+        attachment_bytes,
+        sessions,
+    };
+
+    Ok((receipt, attachment_keys))
+}
+
+impl User {
+    // The password is confirmed again, since this cannot be undone.
+    pub async fn delete(
+        self,
+        db_pool: &PgPool,
+        storage: &Storage,
+        password: &str) -> Result<Receipt, Error>
+    {
+        self.verify_password(password)?;
+
+        let (receipt, attachment_keys) = match delete_user_sqlx(db_pool, &self).await {
+            Ok(value) => value,
+            Err(error) => {
+                println!("{error}");
+                return Err(ErrorInternalServerError("Database error."));
+            }
+        };
+
+        storage.delete_all(attachment_keys).await;
+        Ok(receipt)
+    }
+}
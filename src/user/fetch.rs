@@ -1,24 +1,29 @@
 use actix_web::{Error, error::{ErrorNotFound, ErrorInternalServerError}};
 use sqlx::{Row, PgPool};
 use super::User;
+use super::username::username_key;
 
 async fn by_username_sqlx(
     db_pool: &PgPool,
     username: &str) -> Result<User, sqlx::Error> 
 {
     let user_row = 
-        sqlx::query("SELECT password_hash, uuid FROM users WHERE username = $1")
-        .bind(username)
+        sqlx::query(
+            "SELECT uuid, username, password_hash, totp_enabled, is_admin, approved, disabled, password_reset_required \
+             FROM users WHERE username_key = $1")
+        .bind(username_key(username))
         .fetch_one(db_pool)
         .await?;
 
-    let user_uuid: String = user_row.try_get("uuid")?;
-    let password_hash: String = user_row.try_get("password_hash")?;
-
     Ok(User {
-        uuid: user_uuid,
-        username: username.to_string(),
-        password_hash: password_hash,
+        uuid: user_row.try_get("uuid")?,
+        username: user_row.try_get("username")?,
+        password_hash: user_row.try_get("password_hash")?,
+        totp_enabled: user_row.try_get("totp_enabled")?,
+        is_admin: user_row.try_get("is_admin")?,
+        approved: user_row.try_get("approved")?,
+        disabled: user_row.try_get("disabled")?,
+        password_reset_required: user_row.try_get("password_reset_required")?,
     })
 }
 
@@ -38,3 +43,45 @@ impl User {
         }
     }
 }
+
+async fn by_uuid_sqlx(
+    db_pool: &PgPool,
+    user_uuid: &str) -> Result<User, sqlx::Error> 
+{
+    let user_row = 
+        sqlx::query("SELECT username, password_hash, totp_enabled, is_admin, approved, disabled, password_reset_required FROM users WHERE uuid = $1")
+        .bind(user_uuid)
+        .fetch_one(db_pool)
+        .await?;
+
+    Ok(User {
+        uuid: user_uuid.to_string(),
+        username: user_row.try_get("username")?,
+        password_hash: user_row.try_get("password_hash")?,
+        totp_enabled: user_row.try_get("totp_enabled")?,
+        is_admin: user_row.try_get("is_admin")?,
+        approved: user_row.try_get("approved")?,
+        disabled: user_row.try_get("disabled")?,
+        password_reset_required: user_row.try_get("password_reset_required")?,
+    })
+}
+
+impl User {
+    pub async fn by_uuid(
+        db_pool: &PgPool,
+        user_uuid: &str) -> Result<User, Error> 
+    {
+        match by_uuid_sqlx(db_pool, user_uuid).await {
+            Ok(value) => Ok(value),
+            Err(error) => {
+                match error {
+                    sqlx::Error::RowNotFound => Err(ErrorNotFound("User not found.")),
+                    _ => {
+                        println!("{error}");
+                        Err(ErrorInternalServerError("Database error."))
+                    }
+                }
+            }
+        }
+    }
+}
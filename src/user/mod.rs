@@ -1,10 +1,26 @@
 pub mod create;
+pub mod delete;
 pub mod fetch;
-pub mod user;
+pub mod password;
+pub mod profile;
+pub mod recovery;
+pub mod registration;
+pub mod reset;
+pub mod settings;
+pub mod status;
+pub mod totp;
+pub mod update;
+pub mod username;
+pub mod validation;
 pub mod verify_password;
 
 pub struct User {
     pub uuid: String,
     pub username: String,
     pub password_hash: String,
+    pub totp_enabled: bool,
+    pub is_admin: bool,
+    pub approved: bool,
+    pub disabled: bool,
+    pub password_reset_required: bool,
 }
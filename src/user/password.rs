@@ -0,0 +1,167 @@
+use rand::rngs::OsRng;
+use actix_web::{Error, error::ErrorInternalServerError};
+use argon2::{Argon2, password_hash::{PasswordHasher, SaltString}};
+use sqlx::PgPool;
+use std::collections::HashSet;
+use super::User;
+use super::validation::Violation;
+
+// Operators may ask for longer passwords, but not shorter ones.
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+pub const MAX_PASSWORD_LENGTH: usize = 128;
+
+pub fn hash_password(password: &str) -> Result<String, Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    match Argon2::default().hash_password(password.as_bytes(), &salt) {
+        Ok(hash) => Ok(hash.to_string()),
+        Err(error) => {
+            println!("{}", error);
+            Err(ErrorInternalServerError("Failed to hash password."))
+        }
+    }
+}
+
+/*
+Rules for new passwords, set by the operator. Passwords in the breached list are refused,
+which is read from a file of one password per line, such as a list of the most common ones.
+*/
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub breached: HashSet<String>,
+}
+
+impl PasswordPolicy {
+    pub fn validate(&self, password: &str, username: &str) -> Result<(), Error> {
+        let length = password.chars().count();
+
+        if length < self.min_length {
+            let message = format!("Password should be at least {} characters long.", self.min_length);
+            return Err(Violation::new("password", "too_short", message).into());
+        }
+
+        if length > MAX_PASSWORD_LENGTH {
+            let message = format!("Password should be at most {MAX_PASSWORD_LENGTH} characters long.");
+            return Err(Violation::new("password", "too_long", message).into());
+        }
+
+        if password.to_lowercase() == username.to_lowercase() {
+            let message = "Password should not be the same as the username.";
+            return Err(Violation::new("password", "same_as_username", message).into());
+        }
+
+        if self.breached.contains(password) {
+            let message = "Password is known from data breaches, and should not be used.";
+            return Err(Violation::new("password", "breached", message).into());
+        }
+
+        Ok(())
+    }
+}
+
+// Replaces the password hash, which fulfils a pending reset, and deletes every access token
+// and every session of the user except 'kept_session_uuid'.
+async fn change_password_sqlx(
+    db_pool: &PgPool,
+    user_uuid: &str,
+    password_hash: &str,
+    kept_session_uuid: &str) -> Result<(), sqlx::Error>
+{
+    let mut transaction = db_pool.begin().await?;
+
+    sqlx::query("UPDATE users SET password_hash = $1, password_reset_required = FALSE WHERE uuid = $2")
+        .bind(password_hash)
+        .bind(user_uuid)
+        .execute(&mut transaction)
+        .await?;
+
+    sqlx::query("DELETE FROM access_tokens WHERE user_uuid = $1")
+        .bind(user_uuid)
+        .execute(&mut transaction)
+        .await?;
+
+    sqlx::query("DELETE FROM sessions WHERE user_uuid = $1 AND uuid <> $2")
+        .bind(user_uuid)
+        .bind(kept_session_uuid)
+        .execute(&mut transaction)
+        .await?;
+
+    transaction.commit().await?;
+    Ok(())
+}
+
+impl User {
+    // Other sessions of the user are logged out and access tokens revoked, while 'kept_session_uuid' stays alive.
+    pub async fn change_password(
+        &self,
+        db_pool: &PgPool,
+        current_password: &str,
+        new_password: &str,
+        kept_session_uuid: &str,
+        policy: &PasswordPolicy) -> Result<(), Error>
+    {
+        self.verify_password(current_password)?;
+        policy.validate(new_password, &self.username)?;
+
+        let password_hash = hash_password(new_password)?;
+
+        match change_password_sqlx(db_pool, &self.uuid, &password_hash, kept_session_uuid).await {
+            Ok(value) => Ok(value),
+            Err(error) => {
+                println!("{error}");
+                Err(ErrorInternalServerError("Database error."))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::Error;
+    use std::collections::HashSet;
+    use crate::user::validation::Violation;
+    use super::{MAX_PASSWORD_LENGTH, MIN_PASSWORD_LENGTH, PasswordPolicy};
+
+    fn policy() -> PasswordPolicy {
+        PasswordPolicy {
+            min_length: MIN_PASSWORD_LENGTH,
+            breached: HashSet::from(["password1".to_string(), "qwertyuiop".to_string()]),
+        }
+    }
+
+    fn rule(result: Result<(), Error>) -> &'static str {
+        result.unwrap_err().as_error::<Violation>().unwrap().rule
+    }
+
+    #[test]
+    fn valid_password() {
+        assert!(policy().validate("correct horse battery", "alice").is_ok());
+    }
+
+    #[test]
+    fn length_bounds() {
+        let policy = policy();
+        assert_eq!(rule(policy.validate(&"é".repeat(MIN_PASSWORD_LENGTH - 1), "alice")), "too_short");
+        assert!(policy.validate(&"é".repeat(MIN_PASSWORD_LENGTH), "alice").is_ok());
+        assert!(policy.validate(&"é".repeat(MAX_PASSWORD_LENGTH), "alice").is_ok());
+        assert_eq!(rule(policy.validate(&"é".repeat(MAX_PASSWORD_LENGTH + 1), "alice")), "too_long");
+    }
+
+    #[test]
+    fn operator_minimum() {
+        let policy = PasswordPolicy { min_length: 12, breached: HashSet::new() };
+        assert_eq!(rule(policy.validate("elevenchars", "alice")), "too_short");
+        assert!(policy.validate("twelve chars", "alice").is_ok());
+    }
+
+    #[test]
+    fn same_as_username() {
+        assert_eq!(rule(policy().validate("alice.liddell", "alice.liddell")), "same_as_username");
+        assert_eq!(rule(policy().validate("Alice.Liddell", "alice.liddell")), "same_as_username");
+    }
+
+    #[test]
+    fn breached() {
+        assert_eq!(rule(policy().validate("password1", "alice")), "breached");
+        assert_eq!(rule(policy().validate("qwertyuiop", "alice")), "breached");
+    }
+}
@@ -0,0 +1,51 @@
+use actix_web::{Error, error::{ErrorNotFound, ErrorInternalServerError}};
+use chrono::NaiveDateTime;
+use sqlx::{Row, PgPool};
+use super::User;
+
+pub struct Profile {
+    pub username: String,
+    pub display_name: Option<String>,
+    pub created: String,
+    pub entry_count: i64,
+}
+
+// Entries in the trash are not counted.
+async fn profile_sqlx(
+    db_pool: &PgPool,
+    user_uuid: &str) -> Result<(Option<String>, NaiveDateTime, i64), sqlx::Error>
+{
+    let row = sqlx::query(
+        "SELECT display_name, created, \
+             (SELECT COUNT(*) FROM journals WHERE user_uuid = $1 AND deleted_at IS NULL) AS entry_count \
+         FROM users WHERE uuid = $1")
+        .bind(user_uuid)
+        .fetch_one(db_pool)
+        .await?;
+
+    Ok((row.try_get("display_name")?, row.try_get("created")?, row.try_get("entry_count")?))
+}
+
+impl User {
+    pub async fn profile(&self, db_pool: &PgPool) -> Result<Profile, Error> {
+        let (display_name, created, entry_count) = match profile_sqlx(db_pool, &self.uuid).await {
+            Ok(value) => value,
+            Err(error) => {
+                return match error {
+                    sqlx::Error::RowNotFound => Err(ErrorNotFound("User not found.")),
+                    _ => {
+                        println!("{error}");
+                        Err(ErrorInternalServerError("Database error."))
+                    }
+                };
+            }
+        };
+
+        Ok(Profile {
+            username: self.username.clone(),
+            display_name,
+            created: created.and_utc().to_rfc3339(),
+            entry_count,
+        })
+    }
+}
@@ -0,0 +1,84 @@
+use rand::{thread_rng, Rng};
+use chrono::Utc;
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
+use crate::session::hash_token;
+
+const RECOVERY_CODE_COUNT: usize = 10;
+
+// Characters easily told apart when written down, without 0/o nor 1/l/i.
+const CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+const CODE_GROUPS: usize = 4;
+const CODE_GROUP_LENGTH: usize = 4;
+
+// Codes are shown grouped like 'k3fm-9xqa-7tbe-2wnp', about 79 bits in total.
+fn generate_code() -> String {
+    let mut rng = thread_rng();
+    let groups: Vec<String> = (0..CODE_GROUPS)
+        .map(|_| (0..CODE_GROUP_LENGTH)
+            .map(|_| CODE_ALPHABET[rng.gen_range(0..CODE_ALPHABET.len())] as char)
+            .collect())
+        .collect();
+
+    groups.join("-")
+}
+
+// Dashes and case are ignored, so only the characters themselves are hashed.
+fn hash_code(code: &str) -> String {
+    let normalized: String = code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+
+    hash_token(&normalized)
+}
+
+// Replaces every recovery code of the user with new ones, which are returned.
+pub async fn replace_sqlx(
+    transaction: &mut Transaction<'_, Postgres>,
+    user_uuid: &str) -> Result<Vec<String>, sqlx::Error>
+{
+    delete_sqlx(transaction, user_uuid).await?;
+
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT).map(|_| generate_code()).collect();
+    for code in codes.iter() {
+        sqlx::query("INSERT INTO recovery_codes (uuid, user_uuid, code_hash) VALUES ($1, $2, $3)")
+            .bind(Uuid::new_v4().to_string())
+            .bind(user_uuid)
+            .bind(hash_code(code))
+            .execute(&mut *transaction)
+            .await?;
+    }
+
+    Ok(codes)
+}
+
+// Marks the code as used. Returns false if it does not exist or was used already.
+pub async fn consume_sqlx(
+    transaction: &mut Transaction<'_, Postgres>,
+    user_uuid: &str,
+    code: &str) -> Result<bool, sqlx::Error>
+{
+    let query_result = sqlx::query(
+        "UPDATE recovery_codes SET used_at = $1 \
+         WHERE user_uuid = $2 AND code_hash = $3 AND used_at IS NULL")
+        .bind(Utc::now().naive_utc())
+        .bind(user_uuid)
+        .bind(hash_code(code))
+        .execute(&mut *transaction)
+        .await?;
+
+    Ok(query_result.rows_affected() == 1)
+}
+
+pub async fn delete_sqlx(
+    transaction: &mut Transaction<'_, Postgres>,
+    user_uuid: &str) -> Result<u64, sqlx::Error>
+{
+    let query_result = sqlx::query("DELETE FROM recovery_codes WHERE user_uuid = $1")
+        .bind(user_uuid)
+        .execute(&mut *transaction)
+        .await?;
+
+    Ok(query_result.rows_affected())
+}
@@ -0,0 +1,131 @@
+use actix_web::{Error, error::{ErrorInternalServerError, ErrorNotFound}};
+use chrono::NaiveDateTime;
+use sqlx::{PgPool, Row};
+use super::User;
+
+/*
+Who may register, set by the operator.
+ open: Anyone.
+ closed: No one.
+ invite: Those with an invite code from an administrator.
+ approval: Anyone, but an administrator has to approve them before they can log in.
+The first user can register in any mode, and becomes the administrator.
+*/
+#[derive(Clone, Copy, PartialEq)]
+pub enum Registration {
+    Open,
+    Closed,
+    Invite,
+    Approval,
+}
+
+impl Registration {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "open" => Some(Registration::Open),
+            "closed" => Some(Registration::Closed),
+            "invite" => Some(Registration::Invite),
+            "approval" => Some(Registration::Approval),
+            _ => None
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Registration::Open => "open",
+            Registration::Closed => "closed",
+            Registration::Invite => "invite",
+            Registration::Approval => "approval",
+        }
+    }
+
+    // The mode which applies right now, which is open until the first user registers.
+    pub async fn effective(&self, db_pool: &PgPool) -> Result<Self, Error> {
+        let exists_result = sqlx::query("SELECT EXISTS (SELECT 1 FROM users) AS exists")
+            .fetch_one(db_pool)
+            .await
+            .and_then(|row| row.try_get::<bool, _>("exists"));
+
+        match exists_result {
+            Ok(true) => Ok(*self),
+            Ok(false) => Ok(Registration::Open),
+            Err(error) => {
+                println!("{error}");
+                Err(ErrorInternalServerError("Database error."))
+            }
+        }
+    }
+}
+
+// A user waiting for approval.
+pub struct PendingUser {
+    pub uuid: String,
+    pub username: String,
+    pub created: String,
+}
+
+async fn pending_sqlx(db_pool: &PgPool) -> Result<Vec<PendingUser>, sqlx::Error> {
+    let rows = sqlx::query("SELECT uuid, username, created FROM users WHERE NOT approved ORDER BY created")
+        .fetch_all(db_pool)
+        .await?;
+
+    rows.iter().map(|row| {
+        let created: NaiveDateTime = row.try_get("created")?;
+
+        Ok(PendingUser {
+            uuid: row.try_get("uuid")?,
+            username: row.try_get("username")?,
+            created: created.and_utc().to_rfc3339(),
+        })
+    }).collect()
+}
+
+impl User {
+    // Returns users waiting for approval, earliest registered first.
+    pub async fn list_pending(db_pool: &PgPool) -> Result<Vec<PendingUser>, Error> {
+        match pending_sqlx(db_pool).await {
+            Ok(value) => Ok(value),
+            Err(error) => {
+                println!("{error}");
+                Err(ErrorInternalServerError("Database error."))
+            }
+        }
+    }
+
+    pub async fn approve(db_pool: &PgPool, user_uuid: &str) -> Result<(), Error> {
+        let update_result = sqlx::query("UPDATE users SET approved = TRUE WHERE uuid = $1 AND NOT approved")
+            .bind(user_uuid)
+            .execute(db_pool)
+            .await;
+
+        match update_result {
+            Ok(query_result) if query_result.rows_affected() == 0 => {
+                Err(ErrorNotFound("Pending user not found."))
+            },
+            Ok(_) => Ok(()),
+            Err(error) => {
+                println!("{error}");
+                Err(ErrorInternalServerError("Database error."))
+            }
+        }
+    }
+
+    // Pending users have never logged in, so there is nothing else of them to delete.
+    pub async fn reject(db_pool: &PgPool, user_uuid: &str) -> Result<(), Error> {
+        let delete_result = sqlx::query("DELETE FROM users WHERE uuid = $1 AND NOT approved")
+            .bind(user_uuid)
+            .execute(db_pool)
+            .await;
+
+        match delete_result {
+            Ok(query_result) if query_result.rows_affected() == 0 => {
+                Err(ErrorNotFound("Pending user not found."))
+            },
+            Ok(_) => Ok(()),
+            Err(error) => {
+                println!("{error}");
+                Err(ErrorInternalServerError("Database error."))
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use actix_web::{Error, error::{ErrorConflict, ErrorInternalServerError, ErrorNotFound}};
+use sqlx::PgPool;
+use super::User;
+use super::password::{PasswordPolicy, hash_password};
+use super::validation::Violation;
+
+// Returns the number of users affected.
+async fn start_reset_sqlx(db_pool: &PgPool, user_uuid: &str) -> Result<u64, sqlx::Error> {
+    let mut transaction = db_pool.begin().await?;
+
+    let query_result = sqlx::query("UPDATE users SET password_reset_required = TRUE WHERE uuid = $1")
+        .bind(user_uuid)
+        .execute(&mut transaction)
+        .await?;
+
+    if query_result.rows_affected() == 0 {
+        return Ok(0);
+    }
+
+    sqlx::query("DELETE FROM sessions WHERE user_uuid = $1")
+        .bind(user_uuid)
+        .execute(&mut transaction)
+        .await?;
+
+    sqlx::query("DELETE FROM access_tokens WHERE user_uuid = $1")
+        .bind(user_uuid)
+        .execute(&mut transaction)
+        .await?;
+
+    transaction.commit().await?;
+    Ok(query_result.rows_affected())
+}
+
+async fn reset_password_sqlx(
+    db_pool: &PgPool,
+    user_uuid: &str,
+    password_hash: &str) -> Result<(), sqlx::Error>
+{
+    sqlx::query("UPDATE users SET password_hash = $1, password_reset_required = FALSE WHERE uuid = $2")
+        .bind(password_hash)
+        .bind(user_uuid)
+        .execute(db_pool)
+        .await?;
+
+    Ok(())
+}
+
+impl User {
+    /*
+    Makes the user set a new password before logging in with a password again, logs them out
+    everywhere and revokes their access tokens. Nothing is handed to the administrator, so the
+    user proves the current password to set a new one. Passkeys of the user keep working.
+    */
+    pub async fn start_password_reset(db_pool: &PgPool, user_uuid: &str) -> Result<(), Error> {
+        match start_reset_sqlx(db_pool, user_uuid).await {
+            Ok(0) => Err(ErrorNotFound("User not found.")),
+            Ok(_) => Ok(()),
+            Err(error) => {
+                println!("{error}");
+                Err(ErrorInternalServerError("Database error."))
+            }
+        }
+    }
+
+    // Unknown usernames and wrong passwords get the same response.
+    pub async fn reset_password(
+        db_pool: &PgPool,
+        username: &str,
+        current_password: &str,
+        new_password: &str,
+        policy: &PasswordPolicy) -> Result<(), Error>
+    {
+        let user = User::verify_credentials(db_pool, username, current_password).await?;
+
+        // Only told to those who know the password.
+        user.check_active()?;
+        if !user.password_reset_required {
+            return Err(ErrorConflict("Password does not have to be reset."));
+        }
+
+        policy.validate(new_password, &user.username)?;
+        if new_password == current_password {
+            let message = "Password should not be the same as the current one.";
+            return Err(Violation::new("password", "same_as_current", message).into());
+        }
+
+        let password_hash = hash_password(new_password)?;
+
+        match reset_password_sqlx(db_pool, &user.uuid, &password_hash).await {
+            Ok(()) => Ok(()),
+            Err(error) => {
+                println!("{error}");
+                Err(ErrorInternalServerError("Database error."))
+            }
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use actix_web::{Error, error::{ErrorBadRequest, ErrorInternalServerError}};
+use chrono_tz::Tz;
+use sqlx::{Row, PgPool, postgres::PgRow};
+use super::User;
+
+pub const WEEK_STARTS: [&str; 3] = ["monday", "sunday", "saturday"];
+pub const THEMES: [&str; 3] = ["system", "light", "dark"];
+
+pub struct Settings {
+    pub timezone: Option<String>,
+    pub week_start: String,
+    pub theme: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            timezone: None,
+            week_start: WEEK_STARTS[0].to_string(),
+            theme: THEMES[0].to_string(),
+        }
+    }
+}
+
+pub fn validate_timezone(timezone: &str) -> Result<(), Error> {
+    match timezone.parse::<Tz>() {
+        Ok(_) => Ok(()),
+        Err(_) => Err(ErrorBadRequest("Timezone should be an IANA time zone name."))
+    }
+}
+
+pub fn validate_week_start(week_start: &str) -> Result<(), Error> {
+    match WEEK_STARTS.contains(&week_start) {
+        true => Ok(()),
+        false => Err(ErrorBadRequest("Week start should be one of 'monday', 'sunday' and 'saturday'."))
+    }
+}
+
+pub fn validate_theme(theme: &str) -> Result<(), Error> {
+    match THEMES.contains(&theme) {
+        true => Ok(()),
+        false => Err(ErrorBadRequest("Theme should be one of 'system', 'light' and 'dark'."))
+    }
+}
+
+fn settings_from_row(row: &PgRow) -> Result<Settings, sqlx::Error> {
+    Ok(Settings {
+        timezone: row.try_get("timezone")?,
+        week_start: row.try_get("week_start")?,
+        theme: row.try_get("theme")?,
+    })
+}
+
+async fn settings_sqlx(
+    db_pool: &PgPool,
+    user_uuid: &str) -> Result<Settings, sqlx::Error>
+{
+    let row = sqlx::query("SELECT timezone, week_start, theme FROM user_settings WHERE user_uuid = $1")
+        .bind(user_uuid)
+        .fetch_optional(db_pool)
+        .await?;
+
+    match row {
+        Some(row) => settings_from_row(&row),
+        None => Ok(Settings::default())
+    }
+}
+
+impl User {
+    pub async fn settings(&self, db_pool: &PgPool) -> Result<Settings, Error> {
+        match settings_sqlx(db_pool, &self.uuid).await {
+            Ok(value) => Ok(value),
+            Err(error) => {
+                println!("{error}");
+                Err(ErrorInternalServerError("Database error."))
+            }
+        }
+    }
+}
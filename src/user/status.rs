@@ -0,0 +1,73 @@
+use actix_web::{Error, error::{ErrorForbidden, ErrorInternalServerError, ErrorNotFound}};
+use sqlx::PgPool;
+use super::User;
+use super::username::username_key;
+
+// Disabling logs the user out everywhere, while access tokens are refused until enabled again.
+async fn set_disabled_sqlx(
+    db_pool: &PgPool,
+    user_uuid: &str,
+    disabled: bool) -> Result<u64, sqlx::Error>
+{
+    let mut transaction = db_pool.begin().await?;
+
+    let query_result = sqlx::query("UPDATE users SET disabled = $1 WHERE uuid = $2")
+        .bind(disabled)
+        .bind(user_uuid)
+        .execute(&mut transaction)
+        .await?;
+
+    if disabled {
+        sqlx::query("DELETE FROM sessions WHERE user_uuid = $1")
+            .bind(user_uuid)
+            .execute(&mut transaction)
+            .await?;
+    }
+
+    transaction.commit().await?;
+    Ok(query_result.rows_affected())
+}
+
+impl User {
+    // Whether the user may log in at all, whichever way they do.
+    pub fn check_active(&self) -> Result<(), Error> {
+        if !self.approved {
+            return Err(ErrorForbidden("Account is waiting for approval by an administrator."));
+        }
+
+        if self.disabled {
+            return Err(ErrorForbidden("Account is disabled."));
+        }
+
+        Ok(())
+    }
+
+    pub async fn set_disabled(db_pool: &PgPool, user_uuid: &str, disabled: bool) -> Result<(), Error> {
+        match set_disabled_sqlx(db_pool, user_uuid, disabled).await {
+            Ok(0) => Err(ErrorNotFound("User not found.")),
+            Ok(_) => Ok(()),
+            Err(error) => {
+                println!("{error}");
+                Err(ErrorInternalServerError("Database error."))
+            }
+        }
+    }
+
+    // For the command line, where administrators are designated by username.
+    pub async fn set_admin(db_pool: &PgPool, username: &str, is_admin: bool) -> Result<(), Error> {
+        let update_result = sqlx::query("UPDATE users SET is_admin = $1 WHERE username_key = $2")
+            .bind(is_admin)
+            .bind(username_key(username))
+            .execute(db_pool)
+            .await;
+
+        match update_result {
+            Ok(query_result) if query_result.rows_affected() == 0 => Err(ErrorNotFound("User not found.")),
+            Ok(_) => Ok(()),
+            Err(error) => {
+                println!("{error}");
+                Err(ErrorInternalServerError("Database error."))
+            }
+        }
+    }
+}
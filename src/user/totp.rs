@@ -0,0 +1,379 @@
+use actix_web::{Error, error::{ErrorConflict, ErrorInternalServerError, ErrorUnauthorized}};
+use chrono::Utc;
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use rand::{rngs::OsRng, RngCore};
+use sha1::Sha1;
+use sqlx::{PgPool, Postgres, Row, Transaction};
+use subtle::ConstantTimeEq;
+use super::{User, recovery};
+
+const ISSUER: &str = "Centinote";
+
+// RFC 4226 recommends 160 bits, the output length of HMAC-SHA1.
+const SECRET_LENGTH: usize = 20;
+const STEP_SECONDS: i64 = 30;
+const DIGITS: usize = 6;
+
+// Codes of the steps right before and after are accepted too, for clocks drifting apart.
+const ALLOWED_DRIFT: i64 = 1;
+
+// A secret waiting for its first code, along with what authenticator apps are given.
+pub struct Enrollment {
+    pub secret: String,
+    pub uri: String,
+}
+
+fn generate_secret() -> String {
+    let mut secret = [0u8; SECRET_LENGTH];
+    OsRng.fill_bytes(&mut secret);
+    BASE32_NOPAD.encode(&secret)
+}
+
+// HOTP value of RFC 4226, zero padded.
+fn code_at(secret: &[u8], step: i64) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC takes keys of any length");
+    mac.update(&step.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3]
+    ]);
+
+    format!("{:0width$}", binary % 10u32.pow(DIGITS as u32), width = DIGITS)
+}
+
+fn is_totp_code(code: &str) -> bool {
+    code.len() == DIGITS && code.chars().all(|c| c.is_ascii_digit())
+}
+
+/*
+Returns the step which 'code' belongs to, if it is valid around now.
+Steps up to 'last_step' were used already, and are rejected so that a code cannot be replayed.
+*/
+fn matching_step(secret: &str, code: &str, last_step: Option<i64>) -> Option<i64> {
+    matching_step_at(secret, code, last_step, Utc::now().timestamp() / STEP_SECONDS)
+}
+
+fn matching_step_at(secret: &str, code: &str, last_step: Option<i64>, current_step: i64) -> Option<i64> {
+    let secret = BASE32_NOPAD.decode(secret.as_bytes()).ok()?;
+    if !is_totp_code(code) {
+        return None;
+    }
+
+    (current_step - ALLOWED_DRIFT..=current_step + ALLOWED_DRIFT)
+        .filter(|step| last_step.is_none_or(|last_step| *step > last_step))
+        .find(|step| bool::from(code_at(&secret, *step).as_bytes().ct_eq(code.as_bytes())))
+}
+
+// Leaves unreserved characters of RFC 3986 as they are.
+fn percent_encode(value: &str) -> String {
+    value.bytes().map(|byte| match byte {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (byte as char).to_string(),
+        _ => format!("%{byte:02X}")
+    }).collect()
+}
+
+// Key URI understood by authenticator apps, usually shown as a QR code.
+fn provisioning_uri(secret: &str, username: &str) -> String {
+    format!(
+        "otpauth://totp/{issuer}:{username}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits={DIGITS}&period={STEP_SECONDS}",
+        issuer = percent_encode(ISSUER),
+        username = percent_encode(username))
+}
+
+// Normalizes what users type, who may group digits with spaces.
+fn normalize_code(code: &str) -> String {
+    code.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+/*
+Checks either a TOTP code or an unused recovery code, and marks it as used.
+The user row is locked, so that concurrent requests cannot use the same code twice.
+*/
+async fn verify_second_factor_sqlx(
+    transaction: &mut Transaction<'_, Postgres>,
+    user_uuid: &str,
+    code: &str) -> Result<bool, sqlx::Error>
+{
+    let row = sqlx::query(
+        "SELECT totp_secret, totp_last_step FROM users WHERE uuid = $1 AND totp_enabled FOR UPDATE")
+        .bind(user_uuid)
+        .fetch_one(&mut *transaction)
+        .await?;
+
+    let code = normalize_code(code);
+    if !is_totp_code(&code) {
+        return recovery::consume_sqlx(transaction, user_uuid, &code).await;
+    }
+
+    let secret: String = row.try_get("totp_secret")?;
+    let step = match matching_step(&secret, &code, row.try_get("totp_last_step")?) {
+        Some(step) => step,
+        None => return Ok(false)
+    };
+
+    sqlx::query("UPDATE users SET totp_last_step = $1 WHERE uuid = $2")
+        .bind(step)
+        .bind(user_uuid)
+        .execute(&mut *transaction)
+        .await?;
+
+    Ok(true)
+}
+
+async fn start_totp_sqlx(
+    db_pool: &PgPool,
+    user_uuid: &str,
+    secret: &str) -> Result<u64, sqlx::Error>
+{
+    let query_result = sqlx::query(
+        "UPDATE users SET totp_secret = $1, totp_last_step = NULL WHERE uuid = $2 AND NOT totp_enabled")
+        .bind(secret)
+        .bind(user_uuid)
+        .execute(db_pool)
+        .await?;
+
+    Ok(query_result.rows_affected())
+}
+
+// Returns the recovery codes if the code is right, or None otherwise.
+async fn enable_totp_sqlx(
+    db_pool: &PgPool,
+    user_uuid: &str,
+    code: &str) -> Result<Option<Vec<String>>, sqlx::Error>
+{
+    let mut transaction = db_pool.begin().await?;
+
+    let row = sqlx::query(
+        "SELECT totp_secret FROM users \
+         WHERE uuid = $1 AND totp_secret IS NOT NULL AND NOT totp_enabled FOR UPDATE")
+        .bind(user_uuid)
+        .fetch_one(&mut transaction)
+        .await?;
+
+    let secret: String = row.try_get("totp_secret")?;
+    let step = match matching_step(&secret, &normalize_code(code), None) {
+        Some(step) => step,
+        None => return Ok(None)
+    };
+
+    sqlx::query("UPDATE users SET totp_enabled = TRUE, totp_last_step = $1 WHERE uuid = $2")
+        .bind(step)
+        .bind(user_uuid)
+        .execute(&mut transaction)
+        .await?;
+
+    let recovery_codes = recovery::replace_sqlx(&mut transaction, user_uuid).await?;
+
+    transaction.commit().await?;
+    Ok(Some(recovery_codes))
+}
+
+async fn disable_totp_sqlx(
+    db_pool: &PgPool,
+    user_uuid: &str,
+    code: &str) -> Result<bool, sqlx::Error>
+{
+    let mut transaction = db_pool.begin().await?;
+
+    if !verify_second_factor_sqlx(&mut transaction, user_uuid, code).await? {
+        return Ok(false);
+    }
+
+    sqlx::query(
+        "UPDATE users SET totp_secret = NULL, totp_enabled = FALSE, totp_last_step = NULL WHERE uuid = $1")
+        .bind(user_uuid)
+        .execute(&mut transaction)
+        .await?;
+
+    recovery::delete_sqlx(&mut transaction, user_uuid).await?;
+
+    transaction.commit().await?;
+    Ok(true)
+}
+
+async fn verify_sqlx(
+    db_pool: &PgPool,
+    user_uuid: &str,
+    code: &str) -> Result<bool, sqlx::Error>
+{
+    let mut transaction = db_pool.begin().await?;
+    let accepted = verify_second_factor_sqlx(&mut transaction, user_uuid, code).await?;
+    transaction.commit().await?;
+
+    Ok(accepted)
+}
+
+impl User {
+    /*
+    Generates a new secret, which takes effect once a code from it is verified by 'enable_totp'.
+    Starting over replaces the pending secret.
+    */
+    pub async fn start_totp(&self, db_pool: &PgPool, password: &str) -> Result<Enrollment, Error> {
+        self.verify_password(password)?;
+
+        let secret = generate_secret();
+        match start_totp_sqlx(db_pool, &self.uuid, &secret).await {
+            Ok(0) => Err(ErrorConflict("Two-factor authentication is already enabled.")),
+            Ok(_) => Ok(Enrollment {
+                uri: provisioning_uri(&secret, &self.username),
+                secret,
+            }),
+            Err(error) => {
+                println!("{error}");
+                Err(ErrorInternalServerError("Database error."))
+            }
+        }
+    }
+
+    // Returns recovery codes, which are only ever shown here.
+    pub async fn enable_totp(&self, db_pool: &PgPool, code: &str) -> Result<Vec<String>, Error> {
+        if self.totp_enabled {
+            return Err(ErrorConflict("Two-factor authentication is already enabled."));
+        }
+
+        match enable_totp_sqlx(db_pool, &self.uuid, code).await {
+            Ok(Some(recovery_codes)) => Ok(recovery_codes),
+            Ok(None) => Err(ErrorUnauthorized("Code is wrong.")),
+            Err(error) => {
+                match error {
+                    sqlx::Error::RowNotFound => {
+                        Err(ErrorConflict("Two-factor authentication has not been set up."))
+                    },
+                    _ => {
+                        println!("{error}");
+                        Err(ErrorInternalServerError("Database error."))
+                    }
+                }
+            }
+        }
+    }
+
+    // Both the password and a code are required, so that a stolen session cannot turn it off.
+    pub async fn disable_totp(&self, db_pool: &PgPool, password: &str, code: &str) -> Result<(), Error> {
+        if !self.totp_enabled {
+            return Err(ErrorConflict("Two-factor authentication is not enabled."));
+        }
+
+        self.verify_password(password)?;
+
+        match disable_totp_sqlx(db_pool, &self.uuid, code).await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(ErrorUnauthorized("Code is wrong.")),
+            Err(error) => {
+                println!("{error}");
+                Err(ErrorInternalServerError("Database error."))
+            }
+        }
+    }
+
+    // Accepts a TOTP code or a recovery code. Either can only be used once.
+    pub async fn verify_second_factor(&self, db_pool: &PgPool, code: &str) -> Result<(), Error> {
+        match verify_sqlx(db_pool, &self.uuid, code).await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(ErrorUnauthorized("Code is wrong.")),
+            Err(error) => {
+                match error {
+                    sqlx::Error::RowNotFound => {
+                        Err(ErrorConflict("Two-factor authentication is not enabled."))
+                    },
+                    _ => {
+                        println!("{error}");
+                        Err(ErrorInternalServerError("Database error."))
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use data_encoding::BASE32_NOPAD;
+    use super::{STEP_SECONDS, code_at, matching_step_at, normalize_code};
+
+    // Shared secret of the SHA-1 test vectors in RFC 4226 and RFC 6238.
+    const SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn rfc4226_vectors() {
+        let expected = [
+            "755224", "287082", "359152", "969429", "338314",
+            "254676", "287922", "162583", "399871", "520489",
+        ];
+
+        for (counter, code) in expected.iter().enumerate() {
+            assert_eq!(code_at(SECRET, counter as i64), *code, "counter {counter}");
+        }
+    }
+
+    #[test]
+    fn rfc6238_vectors() {
+        // Six digit codes are the last six digits of the eight digit codes in the RFC.
+        let expected = [
+            (59, "94287082"),
+            (1111111109, "07081804"),
+            (1111111111, "14050471"),
+            (1234567890, "89005924"),
+            (2000000000, "69279037"),
+            (20000000000, "65353130"),
+        ];
+
+        for (time, code) in expected {
+            assert_eq!(code_at(SECRET, time / STEP_SECONDS), code[2..], "time {time}");
+        }
+    }
+
+    #[test]
+    fn accepts_drift_of_one_step() {
+        let secret = BASE32_NOPAD.encode(SECRET);
+        let step = 1234567890 / STEP_SECONDS;
+
+        for drift in [-1, 0, 1] {
+            let code = code_at(SECRET, step + drift);
+            assert_eq!(matching_step_at(&secret, &code, None, step), Some(step + drift));
+        }
+
+        for drift in [-2, 2] {
+            let code = code_at(SECRET, step + drift);
+            assert_eq!(matching_step_at(&secret, &code, None, step), None);
+        }
+    }
+
+    #[test]
+    fn rejects_replay() {
+        let secret = BASE32_NOPAD.encode(SECRET);
+        let step = 1234567890 / STEP_SECONDS;
+        let code = code_at(SECRET, step);
+
+        assert_eq!(matching_step_at(&secret, &code, Some(step - 1), step), Some(step));
+        assert_eq!(matching_step_at(&secret, &code, Some(step), step), None);
+        assert_eq!(matching_step_at(&secret, &code, Some(step), step + 1), None);
+
+        // A code of an earlier step within the drift is refused once a later one was used.
+        let previous = code_at(SECRET, step - 1);
+        assert_eq!(matching_step_at(&secret, &previous, Some(step), step), None);
+    }
+
+    #[test]
+    fn rejects_malformed_codes() {
+        let secret = BASE32_NOPAD.encode(SECRET);
+        let step = 1234567890 / STEP_SECONDS;
+
+        for code in ["", "00592", "0059244", "89005924", "abcdef"] {
+            assert_eq!(matching_step_at(&secret, code, None, step), None, "{code:?}");
+        }
+
+        assert_eq!(matching_step_at("not base32!", &code_at(SECRET, step), None, step), None);
+    }
+
+    #[test]
+    fn normalizes_grouped_codes() {
+        assert_eq!(normalize_code(" 005 924 "), "005924");
+    }
+}
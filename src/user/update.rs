@@ -0,0 +1,97 @@
+use actix_web::{Error, error::{ErrorBadRequest, ErrorInternalServerError}};
+use sqlx::PgPool;
+use super::User;
+use super::settings::{self, Settings};
+
+pub const MAX_DISPLAY_NAME_LENGTH: usize = 64;
+
+/*
+Changes to the profile and settings of a user. Fields left as None are not changed.
+An empty 'display_name' or 'timezone' clears it.
+*/
+pub struct ProfileUpdate<'a> {
+    pub display_name: Option<&'a str>,
+    pub timezone: Option<&'a str>,
+    pub week_start: Option<&'a str>,
+    pub theme: Option<&'a str>,
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    match value.trim() {
+        "" => None,
+        trimmed => Some(trimmed.to_string())
+    }
+}
+
+async fn update_profile_sqlx(
+    db_pool: &PgPool,
+    user_uuid: &str,
+    display_name: Option<Option<String>>,
+    settings: &Settings) -> Result<(), sqlx::Error>
+{
+    let mut transaction = db_pool.begin().await?;
+
+    if let Some(display_name) = display_name {
+        sqlx::query("UPDATE users SET display_name = $1 WHERE uuid = $2")
+            .bind(display_name)
+            .bind(user_uuid)
+            .execute(&mut transaction)
+            .await?;
+    }
+
+    sqlx::query(
+        "INSERT INTO user_settings (user_uuid, timezone, week_start, theme) VALUES ($1, $2, $3, $4) \
+         ON CONFLICT (user_uuid) DO UPDATE \
+         SET timezone = EXCLUDED.timezone, week_start = EXCLUDED.week_start, theme = EXCLUDED.theme")
+        .bind(user_uuid)
+        .bind(&settings.timezone)
+        .bind(&settings.week_start)
+        .bind(&settings.theme)
+        .execute(&mut transaction)
+        .await?;
+
+    transaction.commit().await?;
+    Ok(())
+}
+
+impl User {
+    pub async fn update_profile(
+        &self,
+        db_pool: &PgPool,
+        update: &ProfileUpdate<'_>) -> Result<(), Error>
+    {
+        let display_name = update.display_name.map(non_empty);
+        if let Some(Some(display_name)) = &display_name {
+            if display_name.chars().count() > MAX_DISPLAY_NAME_LENGTH {
+                return Err(ErrorBadRequest("Display name is too long."));
+            }
+        }
+
+        let mut settings = self.settings(db_pool).await?;
+
+        if let Some(timezone) = update.timezone {
+            settings.timezone = non_empty(timezone);
+            if let Some(timezone) = &settings.timezone {
+                settings::validate_timezone(timezone)?;
+            }
+        }
+
+        if let Some(week_start) = update.week_start {
+            settings::validate_week_start(week_start)?;
+            settings.week_start = week_start.to_string();
+        }
+
+        if let Some(theme) = update.theme {
+            settings::validate_theme(theme)?;
+            settings.theme = theme.to_string();
+        }
+
+        match update_profile_sqlx(db_pool, &self.uuid, display_name, &settings).await {
+            Ok(value) => Ok(value),
+            Err(error) => {
+                println!("{error}");
+                Err(ErrorInternalServerError("Database error."))
+            }
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use actix_web::Error;
+use unicode_normalization::UnicodeNormalization;
+use super::validation::Violation;
+
+pub const MIN_USERNAME_LENGTH: usize = 3;
+pub const MAX_USERNAME_LENGTH: usize = 32;
+
+fn is_allowed_symbol(c: char) -> bool {
+    c == '.' || c == '-' || c == '_'
+}
+
+/*
+Usernames are kept in NFKC form, so that look-alike forms of the same characters, such as
+full-width letters, make the same username. Usernames given to log in are normalized too.
+*/
+pub fn normalize_username(username: &str) -> String {
+    username.trim().nfkc().collect()
+}
+
+/*
+Key by which usernames are compared, case-folded in Rust rather than by the database, whose
+lower() depends on its collation. Uniqueness, lookups and the login throttle all use it.
+*/
+pub fn username_key(username: &str) -> String {
+    normalize_username(username).to_lowercase()
+}
+
+/*
+Returns the username as it is to be stored. Usernames have 3 to 32 letters of any script,
+digits, '.', '-' and '_', and start and end with a letter or digit.
+*/
+pub fn validate_username(username: &str) -> Result<String, Error> {
+    let username = normalize_username(username);
+    let length = username.chars().count();
+
+    if length == 0 {
+        return Err(Violation::new("username", "empty", "Username should not be empty.").into());
+    }
+
+    if length < MIN_USERNAME_LENGTH {
+        let message = format!("Username should be at least {MIN_USERNAME_LENGTH} characters long.");
+        return Err(Violation::new("username", "too_short", message).into());
+    }
+
+    if length > MAX_USERNAME_LENGTH {
+        let message = format!("Username should be at most {MAX_USERNAME_LENGTH} characters long.");
+        return Err(Violation::new("username", "too_long", message).into());
+    }
+
+    if !username.chars().all(|c| c.is_alphanumeric() || is_allowed_symbol(c)) {
+        let message = "Username should only have letters, digits, '.', '-' and '_'.";
+        return Err(Violation::new("username", "invalid_character", message).into());
+    }
+
+    let starts_and_ends_alphanumeric = username.chars().next().is_some_and(char::is_alphanumeric)
+        && username.chars().last().is_some_and(char::is_alphanumeric);
+    if !starts_and_ends_alphanumeric {
+        let message = "Username should start and end with a letter or digit.";
+        return Err(Violation::new("username", "invalid_edge", message).into());
+    }
+
+    Ok(username)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::Error;
+    use crate::user::validation::Violation;
+    use super::{MAX_USERNAME_LENGTH, MIN_USERNAME_LENGTH, username_key, validate_username};
+
+    fn rule(result: Result<String, Error>) -> &'static str {
+        result.unwrap_err().as_error::<Violation>().unwrap().rule
+    }
+
+    #[test]
+    fn valid_usernames() {
+        assert_eq!(validate_username(" alice ").unwrap(), "alice");
+        assert_eq!(validate_username("jean-luc.picard_2").unwrap(), "jean-luc.picard_2");
+        assert_eq!(validate_username("Zoë").unwrap(), "Zoë");
+        assert_eq!(validate_username("山田太郎").unwrap(), "山田太郎");
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(rule(validate_username("")), "empty");
+        assert_eq!(rule(validate_username("   ")), "empty");
+    }
+
+    #[test]
+    fn length_bounds() {
+        assert_eq!(rule(validate_username(&"a".repeat(MIN_USERNAME_LENGTH - 1))), "too_short");
+        assert!(validate_username(&"a".repeat(MIN_USERNAME_LENGTH)).is_ok());
+        assert!(validate_username(&"é".repeat(MAX_USERNAME_LENGTH)).is_ok());
+        assert_eq!(rule(validate_username(&"é".repeat(MAX_USERNAME_LENGTH + 1))), "too_long");
+    }
+
+    #[test]
+    fn invalid_character() {
+        assert_eq!(rule(validate_username("al ice")), "invalid_character");
+        assert_eq!(rule(validate_username("alice@home")), "invalid_character");
+        assert_eq!(rule(validate_username("alice\u{200b}")), "invalid_character");
+    }
+
+    #[test]
+    fn invalid_edge() {
+        assert_eq!(rule(validate_username(".alice")), "invalid_edge");
+        assert_eq!(rule(validate_username("alice_")), "invalid_edge");
+        assert_eq!(rule(validate_username("-a-")), "invalid_edge");
+    }
+
+    #[test]
+    fn full_width_is_normalized() {
+        assert_eq!(validate_username("ａｌｉｃｅ").unwrap(), "alice");
+        assert_eq!(validate_username("Ａｌｉｃｅ１").unwrap(), "Alice1");
+        assert_eq!(username_key("ＡＬＩＣＥ"), username_key("alice"));
+        assert_eq!(username_key(" Alice "), "alice");
+    }
+}
@@ -0,0 +1,41 @@
+use actix_web::{HttpResponse, ResponseError, http::StatusCode};
+use serde::Serialize;
+use std::fmt;
+
+/*
+A username or password breaking a rule, which responds with 422 Unprocessable Entity and
+JSON telling the field and rule, so that clients can point at what to fix.
+{ "field": "password", "rule": "too_short", "message": "Password should be at least 8 characters long." }
+*/
+#[derive(Debug, Serialize)]
+pub struct Violation {
+    pub field: &'static str,
+    pub rule: &'static str,
+    pub message: String,
+}
+
+impl Violation {
+    pub fn new(field: &'static str, rule: &'static str, message: impl Into<String>) -> Self {
+        Violation {
+            field,
+            rule,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl ResponseError for Violation {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::UNPROCESSABLE_ENTITY
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(self)
+    }
+}
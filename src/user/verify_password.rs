@@ -1,6 +1,12 @@
-use actix_web::{Error, error::{ErrorUnauthorized, ErrorInternalServerError}};
+use actix_web::{Error, error::{ErrorForbidden, ErrorUnauthorized, ErrorInternalServerError}};
 use argon2::{Argon2, PasswordHash, PasswordVerifier, password_hash};
+use sqlx::PgPool;
+use std::sync::OnceLock;
 use super::User;
+use super::password::hash_password;
+
+// Hash verified against when the user does not exist, so that it takes as long as when it does.
+static DUMMY_PASSWORD_HASH: OnceLock<String> = OnceLock::new();
 
 fn verify_password_argon2(
     password_hash: &str,
@@ -11,6 +17,14 @@ fn verify_password_argon2(
     Ok(())
 }
 
+fn verify_dummy_password(password: &str) {
+    let dummy_hash = DUMMY_PASSWORD_HASH.get_or_init(|| {
+        hash_password("centinote-dummy-password").unwrap_or_default()
+    });
+
+    let _ = verify_password_argon2(dummy_hash, password);
+}
+
 impl User {
     pub fn verify_password(
         &self,
@@ -30,4 +44,56 @@ impl User {
             }
         }
     }
+
+    /*
+    Finds the user and verifies the password. Unknown usernames and wrong passwords get the
+    same response in about the same time, so that neither tells which usernames exist.
+    */
+    pub async fn verify_credentials(
+        db_pool: &PgPool,
+        username: &str,
+        password: &str) -> Result<User, Error>
+    {
+        let user = match User::by_username(db_pool, username).await {
+            Ok(value) => value,
+            Err(error) => {
+                if error.as_response_error().status_code().as_u16() == 404 {
+                    verify_dummy_password(password);
+                    return Err(ErrorUnauthorized("Username and/or password is wrong."));
+                } else {
+                    return Err(error);
+                }
+            }
+        };
+
+        if let Err(error) = user.verify_password(password) {
+            if error.as_response_error().status_code().as_u16() == 401 {
+                return Err(ErrorUnauthorized("Username and/or password is wrong."));
+            } else {
+                return Err(error);
+            }
+        }
+
+        Ok(user)
+    }
+
+    /*
+    Verifies the credentials for logging in. Users who are not active, or whose password has
+    to be reset, cannot log in.
+    */
+    pub async fn authenticate(
+        db_pool: &PgPool,
+        username: &str,
+        password: &str) -> Result<User, Error>
+    {
+        let user = User::verify_credentials(db_pool, username, password).await?;
+
+        // Only told to those who know the password.
+        user.check_active()?;
+        if user.password_reset_required {
+            return Err(ErrorForbidden("Password has to be reset before logging in."));
+        }
+
+        Ok(user)
+    }
 }